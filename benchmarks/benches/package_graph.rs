@@ -2,11 +2,13 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
-use guppy::graph::{DependencyDirection, PackageGraph};
+use guppy::graph::feature::{FeatureGraph, FeatureId};
+use guppy::graph::{DependencyDirection, PackageGraph, PackageLink};
 use guppy::PackageId;
 use guppy_benchmarks::ValueGenerator;
 use proptest::collection::vec;
 use proptest::prelude::*;
+use std::collections::HashSet;
 
 pub fn benchmarks(c: &mut Criterion) {
     let package_graph = make_package_graph();
@@ -53,6 +55,122 @@ pub fn benchmarks(c: &mut Criterion) {
             BatchSize::SmallInput,
         )
     });
+
+    c.bench_function("links_twice", |b| {
+        b.iter(|| {
+            let package_set = package_graph.resolve_all();
+            for _ in 0..2 {
+                let _ = package_set.links(DependencyDirection::Forward).count();
+            }
+        })
+    });
+
+    // Use separate package graphs for these benchmarks: the reachability matrix is used by every
+    // depends_on query once it's computed, and the feature_graph_build benchmark below needs a
+    // package graph that hasn't built its feature graph yet.
+    let feature_package_graph = package_graph.clone();
+    let reachability_package_graph = package_graph.clone();
+    let feature_graph = feature_package_graph.feature_graph();
+    let reachability_feature_graph = reachability_package_graph.feature_graph();
+    reachability_feature_graph.reachability();
+
+    c.bench_function("feature_depends_on", |b| {
+        b.iter_batched_ref(
+            || gen.generate(feature_id_pairs_strategy(feature_graph)),
+            |feature_ids| {
+                feature_ids.iter().for_each(|(feature_a, feature_b)| {
+                    let _ = feature_graph.depends_on(*feature_a, *feature_b);
+                })
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    c.bench_function("feature_depends_on_reachability", |b| {
+        b.iter_batched_ref(
+            || gen.generate(feature_id_pairs_strategy(reachability_feature_graph)),
+            |feature_ids| {
+                feature_ids.iter().for_each(|(feature_a, feature_b)| {
+                    let _ = reachability_feature_graph.depends_on(*feature_a, *feature_b);
+                })
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    // Toggle 10 links within cycles of a 2000-package graph. With cycles already computed, only
+    // the cycles that were broken are recomputed.
+    let cyclic_package_graph = make_cyclic_package_graph(100, 20);
+    let removed_names: Vec<_> = (0..10)
+        .map(|cycle| {
+            (
+                format!("pkg-{}-0", cycle * 10),
+                format!("pkg-{}-1", cycle * 10),
+            )
+        })
+        .collect();
+    let removed: HashSet<_> = cyclic_package_graph
+        .resolve_all()
+        .links(DependencyDirection::Forward)
+        .filter(|link| {
+            removed_names
+                .iter()
+                .any(|(from, to)| link.from().name() == from && link.to().name() == to)
+        })
+        .map(|link| (link.from().id(), link.to().id()))
+        .collect();
+    assert_eq!(
+        removed.len(),
+        10,
+        "one link is removed from each of 10 cycles"
+    );
+    let retain = |_: &PackageGraph, link: PackageLink<'_>| {
+        !removed.contains(&(link.from().id(), link.to().id()))
+    };
+
+    c.bench_function("retain_edges_incremental_cycles", |b| {
+        b.iter_batched(
+            || {
+                let package_graph = cyclic_package_graph.clone();
+                assert!(package_graph.is_cyclic(), "cycles are computed");
+                package_graph
+            },
+            |mut package_graph| {
+                package_graph.retain_edges(retain);
+                let _ = package_graph.is_cyclic();
+                package_graph
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    c.bench_function("retain_edges_full_cycles", |b| {
+        b.iter_batched(
+            || {
+                let mut package_graph = cyclic_package_graph.clone();
+                package_graph.invalidate_caches();
+                package_graph
+            },
+            |mut package_graph| {
+                package_graph.retain_edges(retain);
+                let _ = package_graph.is_cyclic();
+                package_graph
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    // Build with `--features rayon` to compare against parallel construction.
+    c.bench_function("feature_graph_build", |b| {
+        b.iter_batched(
+            || package_graph.clone(),
+            |package_graph| {
+                let _ = package_graph.feature_graph().feature_count();
+                package_graph
+            },
+            BatchSize::LargeInput,
+        )
+    });
 }
 
 fn make_package_graph() -> PackageGraph {
@@ -63,6 +181,83 @@ fn make_package_graph() -> PackageGraph {
     .unwrap()
 }
 
+/// Creates a package graph with `cycle_count` dependency cycles of `cycle_len` packages each.
+///
+/// Each cycle is made of dev-dependencies, and the first package in each cycle depends on the first
+/// package in the next one.
+fn make_cyclic_package_graph(cycle_count: usize, cycle_len: usize) -> PackageGraph {
+    let root = "/fakeuser/local/testcrates/cyclic";
+    let id = |cycle: usize, idx: usize| {
+        format!(
+            "pkg-{}-{} 0.1.0 (path+file://{}/pkg-{}-{})",
+            cycle, idx, root, cycle, idx
+        )
+    };
+
+    let mut packages = vec![];
+    let mut nodes = vec![];
+    let mut members = vec![];
+    for cycle in 0..cycle_count {
+        for idx in 0..cycle_len {
+            let mut deps = vec![(cycle, (idx + 1) % cycle_len, r#""dev""#)];
+            if idx == 0 && cycle + 1 < cycle_count {
+                deps.push((cycle + 1, 0, "null"));
+            }
+
+            let dependencies: Vec<_> = deps
+                .iter()
+                .map(|(dep_cycle, dep_idx, kind)| {
+                    format!(
+                        r#"{{"name":"pkg-{}-{}","source":null,"req":"*","kind":{},"rename":null,"optional":false,"uses_default_features":true,"features":[],"target":null,"registry":null}}"#,
+                        dep_cycle, dep_idx, kind
+                    )
+                })
+                .collect();
+            let node_deps: Vec<_> = deps
+                .iter()
+                .map(|(dep_cycle, dep_idx, kind)| {
+                    format!(
+                        r#"{{"name":"pkg_{}_{}","pkg":"{}","dep_kinds":[{{"kind":{},"target":null}}]}}"#,
+                        dep_cycle,
+                        dep_idx,
+                        id(*dep_cycle, *dep_idx),
+                        kind
+                    )
+                })
+                .collect();
+            let dep_ids: Vec<_> = deps
+                .iter()
+                .map(|(dep_cycle, dep_idx, _)| format!(r#""{}""#, id(*dep_cycle, *dep_idx)))
+                .collect();
+
+            packages.push(format!(
+                r#"{{"name":"pkg-{cycle}-{idx}","version":"0.1.0","id":"{id}","license":null,"license_file":null,"description":null,"source":null,"dependencies":[{dependencies}],"targets":[{{"kind":["lib"],"crate_types":["lib"],"name":"pkg_{cycle}_{idx}","src_path":"{root}/pkg-{cycle}-{idx}/src/lib.rs","edition":"2018","doctest":true}}],"features":{{}},"manifest_path":"{root}/pkg-{cycle}-{idx}/Cargo.toml","metadata":null,"publish":null,"authors":[],"categories":[],"keywords":[],"readme":null,"repository":null,"edition":"2018","links":null}}"#,
+                cycle = cycle,
+                idx = idx,
+                id = id(cycle, idx),
+                dependencies = dependencies.join(","),
+                root = root,
+            ));
+            nodes.push(format!(
+                r#"{{"id":"{}","dependencies":[{}],"deps":[{}],"features":[]}}"#,
+                id(cycle, idx),
+                dep_ids.join(","),
+                node_deps.join(",")
+            ));
+            members.push(format!(r#""{}""#, id(cycle, idx)));
+        }
+    }
+
+    let json = format!(
+        r#"{{"packages":[{}],"workspace_members":[{}],"resolve":{{"nodes":[{}],"root":null}},"target_directory":"{root}/target","version":1,"workspace_root":"{root}"}}"#,
+        packages.join(","),
+        members.join(","),
+        nodes.join(","),
+        root = root,
+    );
+    PackageGraph::from_json(json).unwrap()
+}
+
 /// Generate pairs of IDs for benchmarks.
 fn id_pairs_strategy<'g>(
     graph: &'g PackageGraph,
@@ -73,6 +268,16 @@ fn id_pairs_strategy<'g>(
     )
 }
 
+/// Generate pairs of feature IDs for benchmarks.
+fn feature_id_pairs_strategy<'g>(
+    graph: FeatureGraph<'g>,
+) -> impl Strategy<Value = Vec<(FeatureId<'g>, FeatureId<'g>)>> + 'g {
+    vec(
+        (graph.prop09_id_strategy(), graph.prop09_id_strategy()),
+        256,
+    )
+}
+
 /// Generate IDs and directions for benchmarks.
 fn ids_directions_strategy<'g>(
     graph: &'g PackageGraph,
@@ -25,8 +25,22 @@ pub enum Error {
     UnknownPackageId(PackageId),
     /// A feature ID was unknown to this `FeatureGraph`.
     UnknownFeatureId(PackageId, Option<String>),
+    /// A feature name was not declared by any package in the workspace.
+    UnknownWorkspaceFeature(String),
     /// An internal error occurred within this `PackageGraph`.
     PackageGraphInternalError(String),
+    /// An error occurred while serializing data to JSON.
+    JsonSerializeError(serde_json::Error),
+    /// A set operation was performed on sets that were derived from different package graphs.
+    MismatchedGraphs,
+    /// A feature spec string couldn't be parsed or resolved.
+    FeatureSpecError(String),
+    /// A feature ID couldn't be enabled from the given packages.
+    UnreachableFeatureId(PackageId, Option<String>),
+    /// Several feature names were unknown to the packages they were requested for.
+    UnknownFeatures(Vec<(PackageId, String)>),
+    /// A key-value `cfg` option (`name`, `value`) was passed in, but only plain flags are supported.
+    UnsupportedKeyValueCfg(String, String),
 }
 
 impl fmt::Display for Error {
@@ -46,7 +60,40 @@ impl fmt::Display for Error {
                 Some(feature) => write!(f, "Unknown feature ID: '{}' '{}'", package_id, feature),
                 None => write!(f, "Unknown feature ID: '{}' (base)", package_id),
             },
+            UnknownWorkspaceFeature(feature) => {
+                write!(f, "Unknown feature for workspace: '{}'", feature)
+            }
             PackageGraphInternalError(msg) => write!(f, "Internal error in package graph: {}", msg),
+            JsonSerializeError(err) => write!(f, "Error while serializing to JSON: {}", err),
+            MismatchedGraphs => write!(f, "Sets are from different package graphs"),
+            FeatureSpecError(msg) => write!(f, "Error while resolving feature spec: {}", msg),
+            UnreachableFeatureId(package_id, feature) => match feature {
+                Some(feature) => write!(
+                    f,
+                    "Feature ID can't be enabled from the given packages: '{}' '{}'",
+                    package_id, feature
+                ),
+                None => write!(
+                    f,
+                    "Feature ID can't be enabled from the given packages: '{}' (base)",
+                    package_id
+                ),
+            },
+            UnknownFeatures(features) => {
+                write!(f, "Unknown features: ")?;
+                for (idx, (package_id, feature)) in features.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "'{}' '{}'", package_id, feature)?;
+                }
+                Ok(())
+            }
+            UnsupportedKeyValueCfg(name, value) => write!(
+                f,
+                "Key-value cfg options aren't supported: {}=\"{}\"",
+                name, value
+            ),
         }
     }
 }
@@ -59,7 +106,14 @@ impl error::Error for Error {
             PackageGraphConstructError(_) => None,
             UnknownPackageId(_) => None,
             UnknownFeatureId(_, _) => None,
+            UnknownWorkspaceFeature(_) => None,
             PackageGraphInternalError(_) => None,
+            JsonSerializeError(err) => Some(err),
+            MismatchedGraphs => None,
+            FeatureSpecError(_) => None,
+            UnreachableFeatureId(_, _) => None,
+            UnknownFeatures(_) => None,
+            UnsupportedKeyValueCfg(_, _) => None,
         }
     }
 }
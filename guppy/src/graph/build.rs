@@ -8,7 +8,8 @@ use crate::graph::{
 };
 use crate::sorted_set::SortedSet;
 use crate::{Error, Metadata, PackageId};
-use cargo_metadata::{Dependency, DependencyKind, NodeDep, Package, Resolve, Target};
+use cargo_metadata::{Dependency, DependencyKind, Node, NodeDep, Package, Resolve, Target};
+use indexmap::IndexMap;
 use once_cell::sync::OnceCell;
 use petgraph::prelude::*;
 use semver::Version;
@@ -39,7 +40,7 @@ impl PackageGraph {
             &workspace_members,
         );
 
-        let packages: HashMap<_, _> = metadata
+        let packages: IndexMap<_, _> = metadata
             .packages
             .into_iter()
             .map(|package| build_state.process_package(package))
@@ -52,7 +53,9 @@ impl PackageGraph {
         Ok(Self {
             dep_graph,
             sccs: OnceCell::new(),
+            condensation: OnceCell::new(),
             feature_graph: OnceCell::new(),
+            link_endpoints: OnceCell::new(),
             data: PackageGraphData {
                 packages,
                 workspace,
@@ -61,11 +64,77 @@ impl PackageGraph {
     }
 }
 
+/// ## Incremental updates
+impl PackageGraph {
+    /// Replaces the metadata for a single package that's already in this graph, and recomputes the
+    /// dependency links out of it.
+    ///
+    /// `package` and `node` are the new entries for this package in the `packages` and
+    /// `resolve.nodes` lists of `cargo metadata`. This is meant for use cases like editor
+    /// integrations, where a single `Cargo.toml` changes and re-running `cargo metadata` for the
+    /// entire workspace is too slow.
+    ///
+    /// Only changes that don't affect the rest of the resolution are supported: adding, removing
+    /// or editing features and dependencies, as long as every dependency resolves to a package
+    /// already in this graph. Returns an error, and leaves the graph unchanged, if:
+    /// * the package ID is unknown, or doesn't match the ID of `node`
+    /// * the name or version of the package has changed
+    /// * the package was moved within the workspace
+    /// * a dependency resolves to a package not in this graph.
+    ///
+    /// Links into this package are not affected, and the internal indexes of packages are
+    /// preserved. Cached data, including the feature graph and the set of dependency cycles, is
+    /// invalidated.
+    pub fn update_package(&mut self, package: Package, node: Node) -> Result<(), Error> {
+        let package_id = PackageId::from_metadata(package.id.clone());
+        let old = self
+            .data
+            .packages
+            .get(&package_id)
+            .ok_or_else(|| Error::UnknownPackageId(package_id.clone()))?;
+        if package.id != node.id {
+            return Err(Error::PackageGraphConstructError(format!(
+                "resolve node '{}' doesn't match package '{}'",
+                node.id, package_id
+            )));
+        }
+        if package.name != old.name || package.version != old.version {
+            return Err(Error::PackageGraphConstructError(format!(
+                "name or version changed for package '{}'",
+                package_id
+            )));
+        }
+
+        let workspace_members: HashSet<_> = self.workspace().member_ids().cloned().collect();
+        let mut build_state =
+            GraphBuildState::for_update(self, node, &self.data.workspace.root, &workspace_members);
+        let (_, metadata, edges) = build_state.package_impl(package)?;
+        if metadata.workspace_path != old.workspace_path {
+            return Err(Error::PackageGraphConstructError(format!(
+                "workspace member '{}' moved from {:?} to {:?}",
+                package_id, old.workspace_path, metadata.workspace_path,
+            )));
+        }
+
+        self.retain_edges(|_, link| *link.from().id() != package_id);
+        for (package_ix, dep_idx, edge) in edges {
+            self.dep_graph.update_edge(package_ix, dep_idx, edge);
+        }
+        *self
+            .data
+            .packages
+            .get_mut(&package_id)
+            .expect("package ID was checked above") = metadata;
+        self.invalidate_caches();
+        Ok(())
+    }
+}
+
 impl WorkspaceImpl {
     /// Indexes and creates a new workspace.
     fn new(
         workspace_root: impl Into<PathBuf>,
-        packages: &HashMap<PackageId, PackageMetadataImpl>,
+        packages: &IndexMap<PackageId, PackageMetadataImpl>,
         members: impl IntoIterator<Item = PackageId>,
     ) -> Result<Self, Error> {
         use std::collections::btree_map::Entry;
@@ -112,6 +181,13 @@ impl WorkspaceImpl {
     }
 }
 
+/// A package's ID and metadata, along with the edges out of it as `(from, to, link)` triples.
+type ProcessedPackage = (
+    PackageId,
+    PackageMetadataImpl,
+    Vec<(NodeIndex<PackageIx>, NodeIndex<PackageIx>, PackageLinkImpl)>,
+);
+
 /// Helper struct for building up dependency graph.
 struct GraphBuildState<'a> {
     dep_graph: Graph<PackageId, PackageLinkImpl, Directed, PackageIx>,
@@ -164,10 +240,62 @@ impl<'a> GraphBuildState<'a> {
         }
     }
 
+    /// Creates a build state for recomputing a single package in an existing graph.
+    ///
+    /// The dependency graph in the returned state is empty, so only `package_impl` should be
+    /// called on it.
+    fn for_update(
+        graph: &PackageGraph,
+        node: Node,
+        workspace_root: &'a Path,
+        workspace_members: &'a HashSet<PackageId>,
+    ) -> Self {
+        let package_data = graph
+            .data
+            .packages
+            .values()
+            .map(|package| {
+                (
+                    package.id.clone(),
+                    (
+                        package.package_ix,
+                        package.name.clone(),
+                        package.version.clone(),
+                    ),
+                )
+            })
+            .collect();
+        let mut resolve_data = HashMap::new();
+        resolve_data.insert(
+            PackageId::from_metadata(node.id),
+            (node.deps, node.features),
+        );
+
+        Self {
+            dep_graph: Graph::default(),
+            package_data,
+            resolve_data,
+            workspace_root,
+            workspace_members,
+        }
+    }
+
     fn process_package(
         &mut self,
         package: Package,
     ) -> Result<(PackageId, PackageMetadataImpl), Error> {
+        let (package_id, metadata, edges) = self.package_impl(package)?;
+        for (package_ix, dep_idx, edge) in edges {
+            // Use update_edge instead of add_edge to prevent multiple edges from being added
+            // between these two nodes.
+            // XXX maybe check for an existing edge?
+            self.dep_graph.update_edge(package_ix, dep_idx, edge);
+        }
+        Ok((package_id, metadata))
+    }
+
+    /// Computes the metadata and outgoing edges for this package, without modifying the graph.
+    fn package_impl(&mut self, package: Package) -> Result<ProcessedPackage, Error> {
         let package_id = PackageId::from_metadata(package.id);
         let (package_ix, _, _) = self.package_data(&package_id)?;
 
@@ -194,6 +322,7 @@ impl<'a> GraphBuildState<'a> {
         let dep_resolver =
             DependencyResolver::new(&package_id, &self.package_data, &package.dependencies);
 
+        let mut edges = Vec::with_capacity(resolved_deps.len());
         for NodeDep {
             name: resolved_name,
             pkg,
@@ -204,10 +333,7 @@ impl<'a> GraphBuildState<'a> {
             let (name, deps) = dep_resolver.resolve(resolved_name, &dep_id)?;
             let (dep_idx, _, _) = self.package_data(&dep_id)?;
             let edge = PackageLinkImpl::new(&package_id, name, resolved_name, deps)?;
-            // Use update_edge instead of add_edge to prevent multiple edges from being added
-            // between these two nodes.
-            // XXX maybe check for an existing edge?
-            self.dep_graph.update_edge(package_ix, dep_idx, edge);
+            edges.push((package_ix, dep_idx, edge));
         }
 
         let has_default_feature = package.features.contains_key("default");
@@ -265,15 +391,16 @@ impl<'a> GraphBuildState<'a> {
                 metadata_table: package.metadata,
                 links: package.links.map(|s| s.into()),
                 publish: package.publish,
+                source: package.source.map(|s| s.to_string().into()),
                 features,
 
                 package_ix,
                 workspace_path,
                 build_targets,
                 has_default_feature,
-                resolved_deps,
                 resolved_features,
             },
+            edges,
         ))
     }
 
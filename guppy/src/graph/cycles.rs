@@ -3,10 +3,13 @@
 
 //! Code for handling cycles in dependency graphs.
 
-use crate::graph::{PackageGraph, PackageIx};
+use crate::graph::{EnabledTernary, PackageGraph, PackageIx, PackageMetadata};
 use crate::petgraph_support::scc::Sccs;
 use crate::Error;
 use crate::PackageId;
+use crate::Platform;
+use petgraph::prelude::*;
+use petgraph::visit::EdgeFiltered;
 
 /// Contains information about dependency cycles.
 ///
@@ -43,4 +46,192 @@ impl<'g> Cycles<'g> {
             .multi_sccs()
             .map(move |scc| scc.iter().map(move |ix| &dep_graph[*ix]).collect())
     }
+
+    /// Returns all the cycles of 2 or more elements in this graph, optionally filtered down to
+    /// cycles that only exist because of dev-dependencies.
+    ///
+    /// If `dev_only` is true, only the cycles that would be broken if every dev-only link were
+    /// removed are returned. Cargo only permits such cycles, so this is normally the same as
+    /// `all_cycles`. If `dev_only` is false, this returns every cycle, same as `all_cycles`.
+    ///
+    /// The order returned within each cycle is arbitrary.
+    pub fn all_cycles_filtered(
+        &self,
+        dev_only: bool,
+    ) -> impl Iterator<Item = Vec<&'g PackageId>> + 'g {
+        let package_graph = self.package_graph;
+        let dep_graph = &package_graph.dep_graph;
+        let no_dev_sccs = if dev_only {
+            let filtered = EdgeFiltered::from_fn(dep_graph, |edge| {
+                !package_graph
+                    .edge_to_link(edge.source(), edge.target(), edge.id(), None)
+                    .dev_only()
+            });
+            Some(Sccs::new(&filtered))
+        } else {
+            None
+        };
+
+        let cycles: Vec<Vec<_>> = self
+            .sccs
+            .multi_sccs()
+            .filter(|scc| match &no_dev_sccs {
+                // This cycle is broken without dev-only links if its elements aren't all in the
+                // same SCC any more.
+                Some(no_dev_sccs) => !scc.iter().all(|ix| no_dev_sccs.is_same_scc(scc[0], *ix)),
+                None => true,
+            })
+            .map(|scc| scc.iter().map(|ix| &dep_graph[*ix]).collect())
+            .collect();
+        cycles.into_iter()
+    }
+
+    /// Returns all the cycles of 2 or more elements that exist on the given platform.
+    ///
+    /// Unlike `all_cycles`, which considers every link in the graph, this only considers links
+    /// that are enabled (as a normal, build or dev dependency) on this platform. Links whose
+    /// status on this platform is unknown are considered enabled.
+    ///
+    /// The SCCs are recomputed every time this method is called. The order returned within each
+    /// cycle is arbitrary.
+    pub fn cycles_on_platform(
+        &self,
+        platform: &Platform<'_>,
+    ) -> impl Iterator<Item = Vec<PackageMetadata<'g>>> + 'g {
+        let package_graph = self.package_graph;
+        let dep_graph = &package_graph.dep_graph;
+        let filtered = EdgeFiltered::from_fn(dep_graph, |edge| {
+            let link = package_graph.edge_to_link(edge.source(), edge.target(), edge.id(), None);
+            let enabled = link
+                .normal()
+                .status()
+                .enabled_on(platform)
+                .or(link.build().status().enabled_on(platform))
+                .or(link.dev().status().enabled_on(platform));
+            enabled != EnabledTernary::Disabled
+        });
+        let sccs = Sccs::new(&filtered);
+
+        let cycles: Vec<Vec<_>> = sccs
+            .multi_sccs()
+            .map(|scc| {
+                scc.iter()
+                    .map(|ix| {
+                        package_graph
+                            .metadata(&dep_graph[*ix])
+                            .expect("valid package ID")
+                    })
+                    .collect()
+            })
+            .collect();
+        cycles.into_iter()
+    }
+}
+
+/// The condensation of a package graph: the graph formed by collapsing every dependency cycle
+/// into a single component.
+///
+/// The condensation is always acyclic, so it can be used for topological processing that treats
+/// each cycle as one unit. For example, a build scheduler can use it to build mutually dependent
+/// crates together.
+///
+/// Components are identified by indexes from `0` to `component_count() - 1`, in reverse
+/// topological order: every component comes after all the components it depends on.
+///
+/// Constructed through `PackageGraph::condensation`.
+#[derive(Clone, Copy, Debug)]
+pub struct Condensation<'g> {
+    package_graph: &'g PackageGraph,
+    inner: &'g CondensationImpl,
+}
+
+impl<'g> Condensation<'g> {
+    pub(super) fn new(package_graph: &'g PackageGraph) -> Self {
+        Self {
+            package_graph,
+            inner: package_graph.condensation_impl(),
+        }
+    }
+
+    /// Returns the number of components in the condensation.
+    pub fn component_count(&self) -> usize {
+        self.package_graph.sccs().iter().len()
+    }
+
+    /// Returns the package IDs in each component, in reverse topological order.
+    ///
+    /// The order returned within each component is arbitrary.
+    pub fn components(&self) -> impl ExactSizeIterator<Item = Vec<&'g PackageId>> + 'g {
+        let dep_graph = &self.package_graph.dep_graph;
+        self.package_graph
+            .sccs()
+            .iter()
+            .map(move |scc| scc.iter().map(|ix| &dep_graph[*ix]).collect())
+    }
+
+    /// Returns the package IDs in the component with this index, or `None` if the index is out of
+    /// range.
+    ///
+    /// The order returned is arbitrary.
+    pub fn component(&self, idx: usize) -> Option<Vec<&'g PackageId>> {
+        let dep_graph = &self.package_graph.dep_graph;
+        let scc = self.package_graph.sccs().iter().nth(idx)?;
+        Some(scc.iter().map(|ix| &dep_graph[*ix]).collect())
+    }
+
+    /// Returns the index of the component this package is in.
+    ///
+    /// Returns `None` if the package ID is unknown.
+    pub fn component_for(&self, package_id: &PackageId) -> Option<usize> {
+        let package_ix = self.package_graph.package_ix(package_id)?;
+        Some(self.inner.node_components[package_ix.index()])
+    }
+
+    /// Returns the edges between components, as `(from, to)` pairs where the `from` component
+    /// depends on the `to` component.
+    ///
+    /// Edges are deduplicated and sorted. Since components are in reverse topological order,
+    /// `from` is always greater than `to`.
+    pub fn edges(&self) -> impl ExactSizeIterator<Item = (usize, usize)> + 'g {
+        self.inner.edges.iter().copied()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct CondensationImpl {
+    // The component for each package, indexed by package index.
+    node_components: Vec<usize>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl CondensationImpl {
+    pub(super) fn new(package_graph: &PackageGraph) -> Self {
+        let dep_graph = &package_graph.dep_graph;
+        let mut node_components = vec![0; dep_graph.node_count()];
+        for (idx, scc) in package_graph.sccs().iter().enumerate() {
+            for ix in scc {
+                node_components[ix.index()] = idx;
+            }
+        }
+
+        let mut edges: Vec<_> = dep_graph
+            .edge_references()
+            .filter_map(|edge| {
+                let from = node_components[edge.source().index()];
+                let to = node_components[edge.target().index()];
+                if from != to {
+                    Some((from, to))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        edges.sort_unstable();
+        edges.dedup();
+
+        Self {
+            node_components,
+            edges,
+        }
+    }
 }
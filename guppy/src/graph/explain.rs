@@ -0,0 +1,156 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Code for explaining why a package is present in a dependency graph.
+
+use crate::graph::{PackageGraph, PackageLink, PackageMetadata};
+use crate::{DependencyKind, Error, PackageId};
+use fixedbitset::FixedBitSet;
+use petgraph::prelude::*;
+use std::iter;
+
+impl PackageGraph {
+    /// Explains why a package is present in this graph, similar to `cargo tree -i`.
+    ///
+    /// The returned `Explanation` contains every chain of reverse dependencies from this package
+    /// up to a workspace member. Each chain starts with a link into `package_id` and ends with a
+    /// link out of a workspace member, and stops at the first workspace member encountered, so a
+    /// workspace member is explained through the other workspace members that depend on it.
+    ///
+    /// Chains don't contain any package more than once, so dependency cycles don't cause infinite
+    /// chains. The number of chains can grow exponentially with the size of the graph.
+    ///
+    /// Returns an error if the package ID is unknown.
+    pub fn explain(&self, package_id: &PackageId) -> Result<Explanation<'_>, Error> {
+        let package = self
+            .metadata(package_id)
+            .ok_or_else(|| Error::UnknownPackageId(package_id.clone()))?;
+        let package_count = self.dep_graph.node_count();
+
+        let mut members = FixedBitSet::with_capacity(package_count);
+        for (_, member) in self.workspace().members() {
+            members.insert(member.package_ix().index());
+        }
+
+        // Only packages that can be reached from a workspace member need to be visited.
+        let mut from_members = FixedBitSet::with_capacity(package_count);
+        let mut dfs = Dfs::empty(&self.dep_graph);
+        dfs.stack.extend(members.ones().map(NodeIndex::new));
+        while let Some(ix) = dfs.next(&self.dep_graph) {
+            from_members.insert(ix.index());
+        }
+
+        // The current chain, along with the reverse dependencies left to visit for the package at
+        // the end of it.
+        let mut chains = vec![];
+        let mut on_path = FixedBitSet::with_capacity(package_count);
+        on_path.insert(package.package_ix().index());
+        let mut chain: Vec<PackageLink<'_>> = vec![];
+        let mut stack = vec![package.reverse_direct_links()];
+        while let Some(links) = stack.last_mut() {
+            match links.next() {
+                Some(link) => {
+                    let from_ix = link.from().package_ix();
+                    if on_path.contains(from_ix.index()) {
+                        continue;
+                    }
+                    if members.contains(from_ix.index()) {
+                        chains.push(
+                            chain
+                                .iter()
+                                .chain(iter::once(&link))
+                                .map(|link| ExplanationLink::new(*link))
+                                .collect(),
+                        );
+                    } else if from_members.contains(from_ix.index()) {
+                        on_path.insert(from_ix.index());
+                        chain.push(link);
+                        stack.push(link.from().reverse_direct_links());
+                    }
+                }
+                None => {
+                    stack.pop();
+                    if let Some(link) = chain.pop() {
+                        on_path.set(link.from().package_ix().index(), false);
+                    }
+                }
+            }
+        }
+
+        Ok(Explanation { package, chains })
+    }
+}
+
+/// An explanation of why a package is present in a graph.
+///
+/// Returned by `PackageGraph::explain`.
+#[derive(Clone, Debug)]
+pub struct Explanation<'g> {
+    package: PackageMetadata<'g>,
+    chains: Vec<Vec<ExplanationLink<'g>>>,
+}
+
+impl<'g> Explanation<'g> {
+    /// Returns the package being explained.
+    pub fn package(&self) -> PackageMetadata<'g> {
+        self.package
+    }
+
+    /// Returns the chains of reverse dependencies from this package up to workspace members.
+    ///
+    /// For each chain, the first link's `to` is the package being explained, the `from` of each
+    /// link is the `to` of the next one, and the last link's `from` is a workspace member.
+    ///
+    /// This is empty if the package can't be reached from any other workspace member.
+    pub fn chains(&self) -> &[Vec<ExplanationLink<'g>>] {
+        &self.chains
+    }
+}
+
+/// A link in a chain of reverse dependencies, annotated with why it's present.
+///
+/// Returned as part of an `Explanation`.
+#[derive(Clone, Debug)]
+pub struct ExplanationLink<'g> {
+    link: PackageLink<'g>,
+    kinds: Vec<DependencyKind>,
+    optional: bool,
+}
+
+impl<'g> ExplanationLink<'g> {
+    fn new(link: PackageLink<'g>) -> Self {
+        let mut kinds = vec![];
+        let mut optional = false;
+        for kind in &[
+            DependencyKind::Normal,
+            DependencyKind::Build,
+            DependencyKind::Development,
+        ] {
+            let req = link.req_for_kind(*kind);
+            if req.is_present() {
+                kinds.push(*kind);
+                optional |= !req.status().optional_status().is_never();
+            }
+        }
+        Self {
+            link,
+            kinds,
+            optional,
+        }
+    }
+
+    /// Returns the underlying `PackageLink`.
+    pub fn link(&self) -> PackageLink<'g> {
+        self.link
+    }
+
+    /// Returns the kinds of dependency (normal, build and/or dev) this link is present for.
+    pub fn kinds(&self) -> &[DependencyKind] {
+        &self.kinds
+    }
+
+    /// Returns true if this dependency is marked optional for any of its kinds.
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+}
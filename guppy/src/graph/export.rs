@@ -0,0 +1,83 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::graph::{kind_str, DependencyDirection, PackageSet};
+use crate::{DependencyKind, Error};
+use serde::Serialize;
+
+/// The version of the JSON schema produced by `PackageSet::to_json`.
+///
+/// This is bumped every time the schema changes in a way that consumers need to be aware of.
+pub const PACKAGE_SET_JSON_SCHEMA_VERSION: u32 = 1;
+
+impl<'g> PackageSet<'g> {
+    /// Serializes this package set into JSON, with packages and links in the order specified by
+    /// `direction`.
+    ///
+    /// The output is an object with the following fields:
+    ///
+    /// * `schema_version`: currently `1`. See `PACKAGE_SET_JSON_SCHEMA_VERSION`.
+    /// * `packages`: a list of objects with `id`, `name`, `version` and `in_workspace` fields.
+    /// * `links`: a list of objects with `from` and `to` package IDs, and `kinds`, the dependency
+    ///   kinds (`"normal"`, `"build"` or `"dev"`) the link is present for.
+    ///
+    /// This is meant for interoperating with non-Rust tools, e.g. graph visualizers.
+    ///
+    /// Requires the `serde1` feature to be enabled.
+    pub fn to_json(&self, direction: DependencyDirection) -> Result<String, Error> {
+        let packages = self
+            .packages(direction)
+            .map(|package| PackageJson {
+                id: package.id().repr(),
+                name: package.name(),
+                version: package.version().to_string(),
+                in_workspace: package.in_workspace(),
+            })
+            .collect();
+        let links = self
+            .links(direction)
+            .map(|link| LinkJson {
+                from: link.from().id().repr(),
+                to: link.to().id().repr(),
+                kinds: [
+                    DependencyKind::Normal,
+                    DependencyKind::Build,
+                    DependencyKind::Development,
+                ]
+                .iter()
+                .filter(|kind| link.req_for_kind(**kind).is_present())
+                .map(|kind| kind_str(*kind))
+                .collect(),
+            })
+            .collect();
+
+        serde_json::to_string(&PackageSetJson {
+            schema_version: PACKAGE_SET_JSON_SCHEMA_VERSION,
+            packages,
+            links,
+        })
+        .map_err(Error::JsonSerializeError)
+    }
+}
+
+#[derive(Serialize)]
+struct PackageSetJson<'g> {
+    schema_version: u32,
+    packages: Vec<PackageJson<'g>>,
+    links: Vec<LinkJson<'g>>,
+}
+
+#[derive(Serialize)]
+struct PackageJson<'g> {
+    id: &'g str,
+    name: &'g str,
+    version: String,
+    in_workspace: bool,
+}
+
+#[derive(Serialize)]
+struct LinkJson<'g> {
+    from: &'g str,
+    to: &'g str,
+    kinds: Vec<&'static str>,
+}
@@ -16,12 +16,14 @@ use std::collections::HashMap;
 use std::iter;
 
 #[derive(Debug)]
-pub(super) struct FeatureGraphBuildState<'g> {
+pub(in crate::graph) struct FeatureGraphBuildState<'g> {
     package_graph: &'g PackageGraph,
     graph: Graph<FeatureNode, FeatureEdge, Directed, FeatureIx>,
     // Map from package ixs to the base (first) feature for each package.
     base_ixs: Vec<NodeIndex<FeatureIx>>,
     map: HashMap<FeatureNode, FeatureMetadataImpl>,
+    // Weak feature deps, along with the optional dependency each one is conditional on.
+    weak_edges: Vec<(FeatureNode, FeatureNode, NodeIndex<FeatureIx>)>,
     warnings: Vec<FeatureGraphWarning>,
 }
 
@@ -36,30 +38,43 @@ impl<'g> FeatureGraphBuildState<'g> {
             // the end.
             base_ixs: Vec::with_capacity(package_count + 1),
             map: HashMap::with_capacity(package_count),
+            weak_edges: vec![],
             warnings: vec![],
         }
     }
 
-    /// Add nodes for every feature in this package + the base package, and add edges from every
-    /// feature to the base package.
-    pub(super) fn add_nodes(&mut self, package: PackageMetadata<'g>) {
-        let base_node = FeatureNode::base(package.package_ix());
-        let base_ix = self.add_node(base_node, FeatureType::BasePackage);
-        self.base_ixs.push(base_ix);
-        FeatureNode::named_features(package).for_each(|feature_node| {
-            let feature_ix = self.add_node(feature_node, FeatureType::NamedFeature);
-            self.graph
-                .update_edge(feature_ix, base_ix, FeatureEdge::FeatureToBase);
-        });
+    /// Add nodes for every feature in these packages + their base packages, and add edges from
+    /// every feature to its base package.
+    ///
+    /// Packages must be passed in in order of their package ixs.
+    pub(super) fn add_nodes(&mut self, packages: &[PackageMetadata<'g>]) {
+        let package_nodes = par_map(packages, |package| Self::package_nodes(*package));
+        for (base_node, feature_nodes) in package_nodes {
+            let base_ix = self.add_node(base_node, FeatureType::BasePackage);
+            self.base_ixs.push(base_ix);
+            for (feature_node, feature_type) in feature_nodes {
+                let feature_ix = self.add_node(feature_node, feature_type);
+                self.graph
+                    .update_edge(feature_ix, base_ix, FeatureEdge::FeatureToBase);
+            }
+        }
+    }
 
-        package.optional_deps_full().for_each(|(n, _)| {
-            let dep_idx = self.add_node(
+    /// Returns the base node for this package, along with nodes for every named feature and
+    /// optional dependency.
+    fn package_nodes(package: PackageMetadata<'_>) -> PackageNodes {
+        let named_features = FeatureNode::named_features(package)
+            .map(|feature_node| (feature_node, FeatureType::NamedFeature));
+        let optional_deps = package.optional_deps_full().map(|(n, _)| {
+            (
                 FeatureNode::new(package.package_ix(), n),
                 FeatureType::OptionalDep,
-            );
-            self.graph
-                .update_edge(dep_idx, base_ix, FeatureEdge::FeatureToBase);
+            )
         });
+        (
+            FeatureNode::base(package.package_ix()),
+            named_features.chain(optional_deps).collect(),
+        )
     }
 
     /// Mark the end of adding nodes.
@@ -67,29 +82,74 @@ impl<'g> FeatureGraphBuildState<'g> {
         self.base_ixs.push(NodeIndex::new(self.graph.node_count()));
     }
 
-    pub(super) fn add_named_feature_edges(&mut self, metadata: PackageMetadata<'_>) {
+    pub(super) fn add_named_feature_edges(&mut self, packages: &[PackageMetadata<'g>]) {
+        let package_edges = par_map(packages, |metadata| self.named_feature_edges(*metadata));
+        for (feature_edges, warnings) in package_edges {
+            self.warnings.extend(warnings);
+            for (from_node, to_nodes_edges) in feature_edges {
+                let weak_to_nodes: Vec<_> = to_nodes_edges
+                    .iter()
+                    .filter_map(|(to_node, weak_dep_ix)| Some((*to_node, (*weak_dep_ix)?)))
+                    .collect();
+
+                // Don't create a map to the base 'from' node since it is already created in
+                // add_nodes.
+                self.add_edges(
+                    from_node,
+                    to_nodes_edges.into_iter().map(|(to_node, weak_dep_ix)| {
+                        let edge = match weak_dep_ix {
+                            Some(_) => FeatureEdge::WeakFeatureDependency,
+                            None => FeatureEdge::FeatureDependency,
+                        };
+                        (to_node, edge)
+                    }),
+                );
+                for (to_node, dep_ix) in weak_to_nodes {
+                    self.weak_edges.push((from_node, to_node, dep_ix));
+                }
+            }
+        }
+    }
+
+    /// Computes the edges from each named feature in this package, along with any warnings.
+    ///
+    /// This doesn't modify the graph, so it can be run for several packages in parallel.
+    fn named_feature_edges(
+        &self,
+        metadata: PackageMetadata<'_>,
+    ) -> (Vec<NamedFeatureEdges>, Vec<FeatureGraphWarning>) {
         let dep_name_to_metadata: HashMap<_, _> = metadata
             .direct_links()
             .map(|link| (link.dep_name(), link.to()))
             .collect();
 
-        metadata
+        let mut warnings = vec![];
+        let feature_edges = metadata
             .named_features_full()
-            .for_each(|(n, named_feature, feature_deps)| {
+            .map(|(n, named_feature, feature_deps)| {
                 let from_node = FeatureNode::new(metadata.package_ix(), n);
-                let to_nodes: Vec<_> = feature_deps
+                let to_nodes_edges: Vec<_> = feature_deps
                     .iter()
                     .filter_map(|feature_dep| {
-                        let (dep_name, to_feature_name) = Self::split_feature_dep(feature_dep);
+                        let (dep_name, to_feature_name, weak) =
+                            Self::split_feature_dep(feature_dep);
                         match dep_name {
                             Some(dep_name) => {
                                 match dep_name_to_metadata.get(dep_name) {
                                     Some(to_metadata) => {
                                         match to_metadata.get_feature_idx(to_feature_name) {
-                                            Some(to_feature_idx) => Some(FeatureNode::new(
-                                                to_metadata.package_ix(),
-                                                to_feature_idx,
-                                            )),
+                                            Some(to_feature_idx) => {
+                                                let to_node = FeatureNode::new(
+                                                    to_metadata.package_ix(),
+                                                    to_feature_idx,
+                                                );
+                                                let weak_dep_ix = if weak {
+                                                    self.weak_dep_ix(metadata, dep_name)
+                                                } else {
+                                                    None
+                                                };
+                                                Some((to_node, weak_dep_ix))
+                                            }
                                             None => {
                                                 // It is possible to specify a feature that doesn't
                                                 // actually exist, and cargo will accept that if the
@@ -102,7 +162,7 @@ impl<'g> FeatureGraphBuildState<'g> {
                                                 // Since these aren't fatal errors, it seems like
                                                 // the best we can do is to store such issues as
                                                 // warnings.
-                                                self.warnings
+                                                warnings
                                                     .push(FeatureGraphWarning::MissingFeature {
                                                     stage:
                                                         FeatureBuildStage::AddNamedFeatureEdges {
@@ -125,16 +185,42 @@ impl<'g> FeatureGraphBuildState<'g> {
                                     }
                                 }
                             }
+                            None if to_feature_name.starts_with("dep:") => {
+                                // "dep:foo" enables the optional dependency foo without creating
+                                // an implicit feature for it.
+                                let dep_name = &to_feature_name["dep:".len()..];
+                                if !dep_name_to_metadata.contains_key(dep_name) {
+                                    // This is an unresolved dependency, as above.
+                                    return None;
+                                }
+                                match metadata.get_feature_idx(dep_name) {
+                                    Some(dep_idx) => Some((
+                                        FeatureNode::new(metadata.package_ix(), dep_idx),
+                                        None,
+                                    )),
+                                    None => {
+                                        warnings.push(FeatureGraphWarning::MissingFeature {
+                                            stage: FeatureBuildStage::AddNamedFeatureEdges {
+                                                package_id: metadata.id().clone(),
+                                                from_feature: named_feature.to_string(),
+                                            },
+                                            package_id: metadata.id().clone(),
+                                            feature_name: to_feature_name.to_string(),
+                                        });
+                                        None
+                                    }
+                                }
+                            }
                             None => {
                                 match metadata.get_feature_idx(to_feature_name) {
-                                    Some(to_feature_idx) => Some(FeatureNode::new(
-                                        metadata.package_ix(),
-                                        to_feature_idx,
+                                    Some(to_feature_idx) => Some((
+                                        FeatureNode::new(metadata.package_ix(), to_feature_idx),
+                                        None,
                                     )),
                                     None => {
                                         // See blurb above, though maybe this should be tightened a
                                         // bit (errors and not warning?)
-                                        self.warnings.push(FeatureGraphWarning::MissingFeature {
+                                        warnings.push(FeatureGraphWarning::MissingFeature {
                                             stage: FeatureBuildStage::AddNamedFeatureEdges {
                                                 package_id: metadata.id().clone(),
                                                 from_feature: named_feature.to_string(),
@@ -148,36 +234,67 @@ impl<'g> FeatureGraphBuildState<'g> {
                             }
                         }
                     })
-                    // The filter_map above holds an &mut reference to self, which is why it needs to be
-                    // collected.
+                    // The filter_map above holds an &mut reference to warnings, which is why it needs
+                    // to be collected.
                     .collect();
 
-                // Don't create a map to the base 'from' node since it is already created in
-                // add_nodes.
-                self.add_edges(
-                    from_node,
-                    to_nodes
-                        .into_iter()
-                        .map(|to_node| (to_node, FeatureEdge::FeatureDependency)),
-                );
+                (from_node, to_nodes_edges)
             })
+            .collect();
+
+        (feature_edges, warnings)
+    }
+
+    /// Returns the optional dependency node for a weak feature dep like "dep?/foo" in this
+    /// package.
+    ///
+    /// If `dep` is optional, the edge is only followed if the optional dependency is enabled.
+    /// Otherwise, `dep` is always enabled, so this returns `None` and the feature dep is treated
+    /// the same as "dep/foo".
+    fn weak_dep_ix(
+        &self,
+        metadata: PackageMetadata<'_>,
+        dep_name: &str,
+    ) -> Option<NodeIndex<FeatureIx>> {
+        let dep_idx = metadata.get_feature_idx(dep_name)?;
+        self.lookup_node(&FeatureNode::new(metadata.package_ix(), dep_idx))
     }
 
-    /// Split a feature dep into package and feature names.
+    /// Split a feature dep into package and feature names, and whether the dep is weak.
     ///
-    /// "foo" -> (None, "foo")
-    /// "dep/foo" -> (Some("dep"), "foo")
-    fn split_feature_dep(feature_dep: &str) -> (Option<&str>, &str) {
+    /// "foo" -> (None, "foo", false)
+    /// "dep:foo" -> (None, "dep:foo", false)
+    /// "dep/foo" -> (Some("dep"), "foo", false)
+    /// "dep?/foo" -> (Some("dep"), "foo", true)
+    pub(in crate::graph) fn split_feature_dep(feature_dep: &str) -> (Option<&str>, &str, bool) {
         let mut rsplit = feature_dep.rsplitn(2, '/');
         let to_feature_name = rsplit
             .next()
             .expect("rsplitn should return at least one element");
-        let dep_name = rsplit.next();
+        match rsplit.next() {
+            Some(dep_name) if dep_name.ends_with('?') => {
+                (Some(&dep_name[..dep_name.len() - 1]), to_feature_name, true)
+            }
+            dep_name => (dep_name, to_feature_name, false),
+        }
+    }
 
-        (dep_name, to_feature_name)
+    pub(super) fn add_dependency_edges(&mut self, links: &[PackageLink<'g>]) {
+        let link_edges = par_map(links, |link| Self::dependency_edges(*link));
+        for (dependency_edges, warnings) in link_edges {
+            self.warnings.extend(warnings);
+            for (from_node, to_nodes_edges) in dependency_edges {
+                self.add_edges(from_node, to_nodes_edges);
+            }
+        }
     }
 
-    pub(super) fn add_dependency_edges(&mut self, link: PackageLink<'_>) {
+    /// Computes the edges for this link, along with any warnings.
+    ///
+    /// This doesn't modify the graph, so it can be run for several links in parallel.
+    pub(super) fn dependency_edges(
+        link: PackageLink<'_>,
+    ) -> (Vec<DependencyEdges>, Vec<FeatureGraphWarning>) {
         let from = link.from();
 
         // Sometimes the same package is depended on separately in different sections like so:
@@ -224,15 +341,19 @@ impl<'g> FeatureGraphBuildState<'g> {
             .chain(iter::once((DependencyKind::Build, link.build())))
             .chain(iter::once((DependencyKind::Development, link.dev())));
 
+        let mut warnings = vec![];
         let mut required_req = FeatureReq::new(link);
         let mut optional_req = FeatureReq::new(link);
         for (kind, dependency_req) in unified_metadata {
-            required_req.add_features(kind, &dependency_req.inner.required, &mut self.warnings);
-            optional_req.add_features(kind, &dependency_req.inner.optional, &mut self.warnings);
+            required_req.add_features(kind, &dependency_req.inner.required, &mut warnings);
+            optional_req.add_features(kind, &dependency_req.inner.optional, &mut warnings);
         }
 
         // Add the required edges (base -> features).
-        self.add_edges(FeatureNode::base(from.package_ix()), required_req.finish());
+        let mut dependency_edges = vec![(
+            FeatureNode::base(from.package_ix()),
+            required_req.finish().collect(),
+        )];
 
         if !optional_req.is_empty() {
             // This means that there is at least one instance of this dependency with optional =
@@ -248,8 +369,10 @@ impl<'g> FeatureGraphBuildState<'g> {
                     );
                 }),
             );
-            self.add_edges(from_node, optional_req.finish());
+            dependency_edges.push((from_node, optional_req.finish().collect()));
         }
+
+        (dependency_edges, warnings)
     }
 
     fn add_node(
@@ -293,12 +416,29 @@ impl<'g> FeatureGraphBuildState<'g> {
     }
 
     pub(super) fn build(self) -> FeatureGraphImpl {
+        let graph = &self.graph;
+        let lookup_node = |node| self.lookup_node(node).expect("weak edge nodes are known");
+        let weak_edges = self
+            .weak_edges
+            .iter()
+            .filter_map(|(from_node, to_node, dep_ix)| {
+                let edge_ix = graph.find_edge(lookup_node(from_node), lookup_node(to_node))?;
+                // A later feature dep for the same pair of features may have replaced this edge.
+                match graph[edge_ix] {
+                    FeatureEdge::WeakFeatureDependency => Some((edge_ix, *dep_ix)),
+                    _ => None,
+                }
+            })
+            .collect();
         FeatureGraphImpl {
             graph: self.graph,
             base_ixs: self.base_ixs,
             map: self.map,
             warnings: self.warnings,
+            weak_edges,
             sccs: OnceCell::new(),
+            workspace_reachable: OnceCell::new(),
+            reachability: OnceCell::new(),
         }
     }
 }
@@ -418,3 +558,36 @@ impl DependencyBuildState {
         }
     }
 }
+
+// The base node for a package, along with its feature nodes.
+type PackageNodes = (FeatureNode, Vec<(FeatureNode, FeatureType)>);
+
+// The edges from a named feature, along with the optional dependency each edge is conditional on
+// for weak feature deps.
+type NamedFeatureEdges = (
+    FeatureNode,
+    Vec<(FeatureNode, Option<NodeIndex<FeatureIx>>)>,
+);
+
+// The edges from a feature to features in a dependency.
+pub(super) type DependencyEdges = (FeatureNode, Vec<(FeatureNode, FeatureEdge)>);
+
+/// Maps `f` over `items`, in parallel if the `rayon` feature is enabled.
+///
+/// The results are returned in the same order as `items`, so the graph is built identically either
+/// way.
+#[cfg(feature = "rayon")]
+fn par_map<T: Sync, U: Send>(items: &[T], f: impl Fn(&T) -> U + Send + Sync) -> Vec<U> {
+    use rayon::prelude::*;
+
+    items.par_iter().map(f).collect()
+}
+
+/// Maps `f` over `items`, in parallel if the `rayon` feature is enabled.
+///
+/// The results are returned in the same order as `items`, so the graph is built identically either
+/// way.
+#[cfg(not(feature = "rayon"))]
+fn par_map<T, U>(items: &[T], f: impl Fn(&T) -> U) -> Vec<U> {
+    items.iter().map(f).collect()
+}
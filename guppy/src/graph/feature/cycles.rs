@@ -3,10 +3,11 @@
 
 //! Code for handling cycles in feature graphs.
 
-use crate::graph::feature::{FeatureGraph, FeatureId};
+use crate::graph::feature::{FeatureEdge, FeatureGraph, FeatureId};
 use crate::graph::FeatureIx;
 use crate::petgraph_support::scc::Sccs;
 use crate::Error;
+use petgraph::visit::EdgeFiltered;
 
 /// Contains information about dependency cycles in feature graphs.
 ///
@@ -53,4 +54,93 @@ impl<'g> Cycles<'g> {
                 .collect()
         })
     }
+
+    /// Returns all the cycles of 2 or more elements in this graph, optionally filtered down to
+    /// cycles that only exist because of dev-dependencies.
+    ///
+    /// If `dev_only` is true, only the cycles that would be broken if every link only present
+    /// through dev-dependencies were removed are returned. If `dev_only` is false, this returns
+    /// every cycle, same as `all_cycles`.
+    ///
+    /// The order returned within each cycle is arbitrary.
+    pub fn all_cycles_filtered(
+        &self,
+        dev_only: bool,
+    ) -> impl Iterator<Item = Vec<FeatureId<'g>>> + 'g {
+        let dep_graph = self.feature_graph.dep_graph();
+        let package_graph = self.feature_graph.package_graph;
+        let no_dev_sccs = if dev_only {
+            let filtered = EdgeFiltered::from_fn(dep_graph, |edge| match edge.weight() {
+                FeatureEdge::FeatureToBase
+                | FeatureEdge::FeatureDependency
+                | FeatureEdge::WeakFeatureDependency => true,
+                FeatureEdge::Dependency { normal, build, .. } => {
+                    !normal.is_never() || !build.is_never()
+                }
+            });
+            Some(Sccs::new(&filtered))
+        } else {
+            None
+        };
+
+        let cycles: Vec<Vec<_>> = self
+            .sccs
+            .multi_sccs()
+            .filter(|class| match &no_dev_sccs {
+                // This cycle is broken without dev-only links if its elements aren't all in the
+                // same SCC any more.
+                Some(no_dev_sccs) => !class
+                    .iter()
+                    .all(|feature_ix| no_dev_sccs.is_same_scc(class[0], *feature_ix)),
+                None => true,
+            })
+            .map(|class| {
+                class
+                    .iter()
+                    .map(|feature_ix| FeatureId::from_node(package_graph, &dep_graph[*feature_ix]))
+                    .collect()
+            })
+            .collect();
+        cycles.into_iter()
+    }
+
+    /// Returns the cycles of 2 or more elements that only exist at the feature level: the
+    /// packages the features belong to don't form a cycle in the package graph.
+    ///
+    /// A feature can only depend on features of its own package, or those of its dependencies.
+    /// This means that a feature cycle spanning several packages always has a matching package
+    /// cycle, so in practice the cycles returned here are between features of a single package
+    /// that enable each other. Such cycles are usually harmless, but can be surprising.
+    ///
+    /// The order returned within each cycle is arbitrary.
+    pub fn feature_only_cycles(&self) -> impl Iterator<Item = Vec<FeatureId<'g>>> + 'g {
+        let dep_graph = self.feature_graph.dep_graph();
+        let package_graph = self.feature_graph.package_graph;
+        let package_sccs = package_graph.sccs();
+
+        let cycles: Vec<Vec<_>> = self
+            .sccs
+            .multi_sccs()
+            .filter(|class| {
+                // The packages form a cycle if there are several of them, all in the same SCC.
+                let first_package_ix = dep_graph[class[0]].package_ix();
+                let mut package_ixs = class
+                    .iter()
+                    .map(|feature_ix| dep_graph[*feature_ix].package_ix());
+                let single_package = package_ixs
+                    .clone()
+                    .all(|package_ix| package_ix == first_package_ix);
+                single_package
+                    || !package_ixs
+                        .all(|package_ix| package_sccs.is_same_scc(first_package_ix, package_ix))
+            })
+            .map(|class| {
+                class
+                    .iter()
+                    .map(|feature_ix| FeatureId::from_node(package_graph, &dep_graph[*feature_ix]))
+                    .collect()
+            })
+            .collect();
+        cycles.into_iter()
+    }
 }
@@ -3,17 +3,21 @@
 
 use crate::errors::FeatureGraphWarning;
 use crate::graph::feature::build::FeatureGraphBuildState;
-use crate::graph::feature::{Cycles, FeatureFilter};
+use crate::graph::feature::{default_filter, Cycles, FeatureFilter, FeatureSet};
+use crate::graph::query_core::QueryParams;
 use crate::graph::{
-    DependencyDirection, FeatureIx, PackageGraph, PackageIx, PackageMetadata, PlatformStatusImpl,
+    DependencyDirection, EnabledTernary, FeatureIx, PackageGraph, PackageIx, PackageLink,
+    PackageMetadata, PlatformStatus, PlatformStatusImpl,
 };
+use crate::petgraph_support::reachability::Reachability;
 use crate::petgraph_support::scc::Sccs;
-use crate::{Error, PackageId};
+use crate::{Error, PackageId, Platform};
+use fixedbitset::FixedBitSet;
 use once_cell::sync::OnceCell;
 use petgraph::algo::has_path_connecting;
 use petgraph::prelude::*;
 use petgraph::visit::IntoNodeReferences;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::iter;
 use std::iter::FromIterator;
 
@@ -45,6 +49,9 @@ impl PackageGraph {
     ///
     /// The feature graph is constructed the first time this method is called. The graph is cached
     /// so that repeated calls to this method are cheap.
+    ///
+    /// With the `rayon` feature enabled, the per-package parts of construction are run in
+    /// parallel. The resulting graph is the same either way.
     #[doc(hidden)]
     pub fn feature_graph(&self) -> FeatureGraph {
         let inner = self.get_feature_graph();
@@ -54,6 +61,73 @@ impl PackageGraph {
         }
     }
 
+    /// Returns a derived graph representing every feature of every package, or the warnings
+    /// encountered while constructing it.
+    ///
+    /// This is a strict version of `feature_graph`: it returns `Err` with every warning in
+    /// `FeatureGraph::build_warnings` if there were any. This is useful for catching references
+    /// to features that don't exist, e.g. in CI.
+    ///
+    /// The feature graph is cached in the same way as `feature_graph`.
+    pub fn build_feature_graph_strict(&self) -> Result<FeatureGraph<'_>, Vec<FeatureGraphWarning>> {
+        let feature_graph = self.feature_graph();
+        let warnings = feature_graph.build_warnings();
+        if warnings.is_empty() {
+            Ok(feature_graph)
+        } else {
+            Err(warnings.to_vec())
+        }
+    }
+
+    /// Resolves the given packages and features, along with their transitive dependencies.
+    ///
+    /// This is the equivalent of a build with `-p` for each package in `roots` and `--features`
+    /// for each entry in `features`, with `--no-default-features` if `default_features` is false.
+    /// Each feature string is parsed with `FeatureGraph::feature_id_from_str`, so it's either a
+    /// feature name like `"full"` or a package name followed by a feature name like
+    /// `"tokio/full"`. Packages listed in `features` are enabled even if they aren't in `roots`.
+    ///
+    /// Returns an `UnknownFeatures` error listing every feature that wasn't found. Returns an
+    /// `UnknownPackageId` error if a package ID is unknown, and a `FeatureSpecError` if a feature
+    /// string couldn't be parsed.
+    pub fn resolve_with_features(
+        &self,
+        roots: &[PackageId],
+        features: &[(PackageId, Vec<String>)],
+        default_features: bool,
+    ) -> Result<FeatureSet<'_>, Error> {
+        let feature_graph = self.feature_graph();
+
+        let mut initials = Vec::with_capacity(roots.len() * 2);
+        for package_id in roots {
+            let metadata = self
+                .metadata(package_id)
+                .ok_or_else(|| Error::UnknownPackageId(package_id.clone()))?;
+            initials.push(FeatureId::base(metadata.id()));
+            if default_features {
+                initials.push(metadata.default_feature_id());
+            }
+        }
+
+        let mut unknown = vec![];
+        for (package_id, feature_names) in features {
+            for feature_name in feature_names {
+                match feature_graph.feature_id_from_str(package_id, feature_name) {
+                    Ok(feature_id) => initials.push(feature_id),
+                    Err(Error::UnknownFeatureId(package_id, Some(feature))) => {
+                        unknown.push((package_id, feature))
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        if !unknown.is_empty() {
+            return Err(Error::UnknownFeatures(unknown));
+        }
+
+        Ok(feature_graph.query_forward(initials)?.resolve())
+    }
+
     pub(super) fn get_feature_graph(&self) -> &FeatureGraphImpl {
         self.feature_graph
             .get_or_init(|| FeatureGraphImpl::new(self))
@@ -87,11 +161,46 @@ impl<'g> FeatureGraph<'g> {
         self.dep_graph().node_count()
     }
 
+    /// Returns the number of features in this graph for the given package: its named features,
+    /// its optional dependencies, and its base feature.
+    ///
+    /// Returns `None` if the package ID is unknown.
+    pub fn feature_count_for(&self, package_id: &PackageId) -> Option<usize> {
+        let package_ix = self.package_graph.package_ix(package_id)?.index();
+        let base_ix = self.inner.base_ixs[package_ix].index();
+        // base_ixs has (package count + 1) elements so this access is valid.
+        let next_base_ix = self.inner.base_ixs[package_ix + 1].index();
+        Some(next_base_ix - base_ix)
+    }
+
     /// Returns the number of links in this graph.
     pub fn link_count(&self) -> usize {
         self.dep_graph().edge_count()
     }
 
+    /// Iterates over every link in this graph, along with the kind of each link.
+    ///
+    /// This is unfiltered: every link is returned regardless of platform or dependency kind.
+    ///
+    /// Links are returned as `(from, to, kind)`. If `direction` is `Reverse`, the endpoints are
+    /// swapped, so that `from` is the feature that is depended on.
+    pub fn all_links(
+        &self,
+        direction: DependencyDirection,
+    ) -> impl Iterator<Item = (FeatureId<'g>, FeatureId<'g>, FeatureEdgeKind)> + 'g {
+        let package_graph = self.package_graph;
+        let dep_graph = self.dep_graph();
+        dep_graph.edge_references().map(move |edge| {
+            let source = FeatureId::from_node(package_graph, &dep_graph[edge.source()]);
+            let target = FeatureId::from_node(package_graph, &dep_graph[edge.target()]);
+            let kind = edge.weight().kind();
+            match direction {
+                DependencyDirection::Forward => (source, target, kind),
+                DependencyDirection::Reverse => (target, source, kind),
+            }
+        })
+    }
+
     /// Returns metadata for the given feature ID, or `None` if the feature wasn't found.
     pub fn metadata(&self, feature_id: impl Into<FeatureId<'g>>) -> Option<FeatureMetadata<'g>> {
         let feature_id = feature_id.into();
@@ -99,6 +208,97 @@ impl<'g> FeatureGraph<'g> {
         Some(FeatureMetadata { feature_id, inner })
     }
 
+    /// Returns metadata for the base feature of the given package, or `None` if the package
+    /// wasn't found.
+    ///
+    /// Unlike `FeatureId::base`, this checks that the package is in this graph. The returned
+    /// feature has the type `FeatureType::BasePackage`.
+    pub fn base_feature(&self, package_id: &PackageId) -> Option<FeatureMetadata<'g>> {
+        let metadata = self.package_graph.metadata(package_id)?;
+        self.metadata(FeatureId::base(metadata.id()))
+    }
+
+    /// Parses a feature ID for the given package from a string.
+    ///
+    /// The string is either the name of a feature, e.g. `"full"`, or the package name followed by
+    /// the feature name, e.g. `"tokio/full"`. In the latter case, the package name must match the
+    /// name of the package with this ID.
+    ///
+    /// Returns an `UnknownPackageId` or `UnknownFeatureId` error if the package or the feature
+    /// weren't found, and a `FeatureSpecError` if the string couldn't be parsed.
+    pub fn feature_id_from_str(
+        &self,
+        package_id: &PackageId,
+        s: &str,
+    ) -> Result<FeatureId<'g>, Error> {
+        let metadata = self
+            .package_graph
+            .metadata(package_id)
+            .ok_or_else(|| Error::UnknownPackageId(package_id.clone()))?;
+        let (package_name, feature_name) = parse_feature_spec(s)?;
+        if let Some(package_name) = package_name {
+            if package_name != metadata.name() {
+                return Err(Error::FeatureSpecError(format!(
+                    "package name '{}' in '{}' doesn't match '{}'",
+                    package_name,
+                    s,
+                    metadata.name()
+                )));
+            }
+        }
+        self.named_feature_id(metadata, feature_name)
+    }
+
+    /// Resolves a feature spec against the packages in this graph, looking packages up by name.
+    ///
+    /// The spec is either a package name, e.g. `"serde"`, which resolves to the base feature for
+    /// that package, or a package name followed by a feature name, e.g. `"tokio/full"`.
+    ///
+    /// Returns a `FeatureSpecError` if the string couldn't be parsed, if no package has that name,
+    /// or if several packages (e.g. different versions of the same crate) have that name. Returns
+    /// an `UnknownFeatureId` error if the package doesn't have the feature.
+    pub fn resolve_feature_spec(&self, s: &str) -> Result<FeatureId<'g>, Error> {
+        let (package_name, feature_name) = parse_feature_spec(s)?;
+        // A bare name refers to a package.
+        let (package_name, feature_name) = match package_name {
+            Some(package_name) => (package_name, Some(feature_name)),
+            None => (feature_name, None),
+        };
+
+        let mut candidates = self
+            .package_graph
+            .packages()
+            .filter(|metadata| metadata.name() == package_name);
+        let metadata = match (candidates.next(), candidates.next()) {
+            (Some(metadata), None) => metadata,
+            (None, _) => {
+                return Err(Error::FeatureSpecError(format!(
+                    "no package named '{}' in '{}'",
+                    package_name, s
+                )))
+            }
+            (Some(first), Some(second)) => {
+                let mut ids: Vec<_> = iter::once(first)
+                    .chain(iter::once(second))
+                    .chain(candidates)
+                    .map(|metadata| metadata.id().repr())
+                    .collect();
+                ids.sort_unstable();
+                return Err(Error::FeatureSpecError(format!(
+                    "multiple packages named '{}' in '{}': {}",
+                    package_name,
+                    s,
+                    ids.join(", ")
+                )));
+            }
+        };
+
+        match feature_name {
+            Some(feature_name) => self.named_feature_id(metadata, feature_name),
+            None => Ok(FeatureId::base(metadata.id())),
+        }
+    }
+
     /// Returns true if this feature is included in a package's build by default.
     ///
     /// This includes transitive dependencies of the default feature.
@@ -115,12 +315,151 @@ impl<'g> FeatureGraph<'g> {
         Some(self.feature_ix_depends_on(default_ix, feature_ix))
     }
 
+    /// Returns the "top" named features for this package: the ones that aren't enabled by any
+    /// other feature of the same package.
+    ///
+    /// These are typically the user-facing toggles for a package, e.g. `default` or `full`.
+    ///
+    /// Returns `None` if the package ID is unknown.
+    pub fn top_features<'a>(
+        &self,
+        package_id: &'a PackageId,
+    ) -> Option<impl Iterator<Item = FeatureId<'g>> + 'a>
+    where
+        'g: 'a,
+    {
+        let package_ix = self.package_graph.package_ix(package_id)?;
+        let this = *self;
+        let dep_graph = self.dep_graph();
+        Some(
+            self.feature_ixs_for_package_ix(package_ix)
+                .filter(move |&feature_ix| {
+                    let is_named = matches!(
+                        this.metadata_for_node(&dep_graph[feature_ix]),
+                        Some(metadata) if metadata.feature_type() == FeatureType::NamedFeature
+                    );
+                    is_named
+                        && !dep_graph.edges_directed(feature_ix, Incoming).any(|edge| {
+                            match edge.weight() {
+                                FeatureEdge::FeatureDependency => {
+                                    dep_graph[edge.source()].package_ix() == package_ix
+                                }
+                                _ => false,
+                            }
+                        })
+                })
+                .map(move |feature_ix| {
+                    FeatureId::from_node(this.package_graph, &dep_graph[feature_ix])
+                }),
+        )
+    }
+
+    /// Returns the features of this package that, when enabled, bring in at least one package
+    /// that isn't already pulled in by the package's base feature.
+    ///
+    /// These are the "load-bearing" features of a package: for example, features that enable an
+    /// optional dependency, as opposed to features that only gate code within the package or
+    /// enable features in dependencies that are already present.
+    ///
+    /// All links are followed, regardless of platform or dependency kind, except for weak
+    /// dependency edges ("foo?/c"), which are only followed if the optional dependency is enabled.
+    ///
+    /// Returns `None` if the package ID is unknown.
+    pub fn package_adding_features(
+        &self,
+        package_id: &PackageId,
+    ) -> Option<impl Iterator<Item = FeatureId<'g>> + 'g> {
+        let package_ix = self.package_graph.package_ix(package_id)?;
+        let dep_graph = self.dep_graph();
+        let package_count = self.package_graph.package_count();
+
+        let closure_packages = |feature_ix| {
+            let mut packages = FixedBitSet::with_capacity(package_count);
+            for ix in self.forward_closure(feature_ix).ones() {
+                packages.insert(dep_graph[NodeIndex::new(ix)].package_ix().index());
+            }
+            packages
+        };
+
+        let base_packages = closure_packages(self.inner.base_ixs[package_ix.index()]);
+        let package_graph = self.package_graph;
+        let features: Vec<_> = self
+            .feature_ixs_for_package_ix(package_ix)
+            .filter(|&feature_ix| {
+                dep_graph[feature_ix].feature_idx.is_some()
+                    && closure_packages(feature_ix)
+                        .difference(&base_packages)
+                        .next()
+                        .is_some()
+            })
+            .map(|feature_ix| FeatureId::from_node(package_graph, &dep_graph[feature_ix]))
+            .collect();
+        Some(features.into_iter())
+    }
+
+    /// Compares the named features of packages called `name` across two feature graphs.
+    ///
+    /// Packages are matched by name rather than by ID, since the versions are expected to differ
+    /// -- for example, `old` and `new` may be feature graphs from before and after a dependency
+    /// was upgraded. If several packages in a graph share the same name, their named features are
+    /// combined. A package that isn't present in a graph is treated as having no named features.
+    pub fn package_feature_diff(
+        old: &FeatureGraph<'_>,
+        new: &FeatureGraph<'_>,
+        name: &str,
+    ) -> FeatureNameDiff {
+        fn named_features<'a>(graph: &FeatureGraph<'a>, name: &str) -> BTreeSet<&'a str> {
+            graph
+                .package_graph
+                .packages()
+                .filter(|package| package.name() == name)
+                .flat_map(|package| package.named_features())
+                .collect()
+        }
+
+        let old_features = named_features(old, name);
+        let new_features = named_features(new, name);
+        FeatureNameDiff {
+            added: new_features
+                .difference(&old_features)
+                .map(|feature| feature.to_string())
+                .collect(),
+            removed: old_features
+                .difference(&new_features)
+                .map(|feature| feature.to_string())
+                .collect(),
+        }
+    }
+
+    /// Returns true if this feature is reachable from the default features of any workspace
+    /// member, i.e. if it is enabled by a standard `cargo build` of the whole workspace.
+    ///
+    /// The set of reachable features is computed the first time this method is called, and cached
+    /// so that repeated calls are cheap.
+    ///
+    /// Returns an error if this feature ID is unknown.
+    pub fn reachable_from_workspace<'a>(
+        &self,
+        feature_id: impl Into<FeatureId<'a>>,
+    ) -> Result<bool, Error> {
+        let feature_ix = self.feature_ix_err(feature_id.into())?;
+        let reachable = self.inner.workspace_reachable.get_or_init(|| {
+            self.query_workspace(default_filter())
+                .resolve()
+                .into_included()
+        });
+        Ok(reachable.contains(feature_ix.index()))
+    }
+
     /// Returns true if `feature_a` depends (directly or indirectly) on `feature_b`.
     ///
     /// In other words, this returns true if `feature_b` is a (possibly transitive) dependency of
     /// `feature_a`.
     ///
     /// This also returns true if `feature_a` is the same as `feature_b`.
+    ///
+    /// This performs a graph traversal for every call, unless `reachability` has been called on
+    /// this graph before. In that case, the precomputed reachability matrix is used instead.
     pub fn depends_on<'a>(
         &self,
         feature_a: impl Into<FeatureId<'a>>,
@@ -135,7 +474,7 @@ impl<'g> FeatureGraph<'g> {
 
     /// Returns true if `feature_a` directly depends on `feature_b`.
     ///
-    /// In other words, this returns true if `feature_a` is a direct dependency of `feature_b`.
+    /// In other words, this returns true if `feature_b` is a direct dependency of `feature_a`.
     ///
     /// This returns false if `feature_a` is the same as `feature_b`.
     pub fn directly_depends_on<'a>(
@@ -150,6 +489,219 @@ impl<'g> FeatureGraph<'g> {
         Ok(self.dep_graph().contains_edge(a_ix, b_ix))
     }
 
+    /// Returns the direct link from `feature_a` to `feature_b`, or `None` if `feature_a` doesn't
+    /// directly depend on `feature_b`.
+    ///
+    /// The link describes why `feature_a` depends on `feature_b`.
+    ///
+    /// Returns an error if either feature ID is unknown.
+    pub fn link<'a>(
+        &self,
+        feature_a: impl Into<FeatureId<'a>>,
+        feature_b: impl Into<FeatureId<'a>>,
+    ) -> Result<Option<FeatureLink<'g>>, Error> {
+        let a_ix = self.feature_ix_err(feature_a.into())?;
+        let b_ix = self.feature_ix_err(feature_b.into())?;
+        Ok(self
+            .dep_graph()
+            .find_edge(a_ix, b_ix)
+            .map(|edge_ix| self.edge_to_link(a_ix, b_ix, edge_ix)))
+    }
+
+    /// Returns the features that directly depend on this feature.
+    ///
+    /// If `exclude_base_links` is true, links from a package's features to its own base feature
+    /// are skipped. Every feature of a package depends on its base, so these links are rarely
+    /// interesting.
+    ///
+    /// Returns an error if the feature ID is unknown.
+    pub fn direct_dependents<'a>(
+        &self,
+        feature_id: impl Into<FeatureId<'a>>,
+        exclude_base_links: bool,
+    ) -> Result<Vec<FeatureId<'g>>, Error> {
+        let feature_ix = self.feature_ix_err(feature_id.into())?;
+        let dep_graph = self.dep_graph();
+        Ok(dep_graph
+            .edges_directed(feature_ix, Incoming)
+            .filter(|edge| {
+                !(exclude_base_links && matches!(edge.weight(), FeatureEdge::FeatureToBase))
+            })
+            .map(|edge| FeatureId::from_node(self.package_graph, &dep_graph[edge.source()]))
+            .collect())
+    }
+
+    /// Returns one of the shortest chains of features through which `from` depends on `to`.
+    ///
+    /// The returned path starts with `from` and ends with `to`. If `from` is the same as `to`, the
+    /// path consists of just that feature.
+    ///
+    /// Returns `Ok(None)` if `from` doesn't depend on `to`, and an error if either feature ID is
+    /// unknown.
+    pub fn shortest_path<'a>(
+        &self,
+        from: impl Into<FeatureId<'a>>,
+        to: impl Into<FeatureId<'a>>,
+    ) -> Result<Option<Vec<FeatureId<'g>>>, Error> {
+        let from_ix = self.feature_ix_err(from.into())?;
+        let to_ix = self.feature_ix_err(to.into())?;
+        let dep_graph = self.dep_graph();
+
+        // Breadth-first search, recording the node each feature was first reached from. Each node
+        // is visited at most once, so cycles are handled.
+        let mut predecessors = HashMap::new();
+        let mut visited = FixedBitSet::with_capacity(dep_graph.node_count());
+        let mut queue = VecDeque::new();
+        visited.insert(from_ix.index());
+        queue.push_back(from_ix);
+        while let Some(ix) = queue.pop_front() {
+            if ix == to_ix {
+                let mut path = vec![ix];
+                let mut current = ix;
+                while let Some(&prev) = predecessors.get(&current) {
+                    path.push(prev);
+                    current = prev;
+                }
+                path.reverse();
+                return Ok(Some(
+                    path.into_iter()
+                        .map(|ix| FeatureId::from_node(self.package_graph, &dep_graph[ix]))
+                        .collect(),
+                ));
+            }
+            for neighbor in dep_graph.neighbors_directed(ix, Outgoing) {
+                if !visited.put(neighbor.index()) {
+                    predecessors.insert(neighbor, ix);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the links in this feature graph that were added because of the given dependency
+    /// link.
+    ///
+    /// These are the links from the base feature of `link.from()` to each feature of `link.to()`
+    /// that's always enabled by this dependency, and, if the dependency is optional, from the
+    /// corresponding optional dependency feature to each feature it enables. Links added through
+    /// the `[features]` section, e.g. with `"foo/a"` syntax, aren't included.
+    ///
+    /// `link` must be from the package graph this feature graph was built from.
+    pub fn edges_for_link(&self, link: &PackageLink<'g>) -> Vec<FeatureLink<'g>> {
+        let (dependency_edges, _) = FeatureGraphBuildState::dependency_edges(*link);
+        let dep_graph = self.dep_graph();
+        dependency_edges
+            .into_iter()
+            .flat_map(|(from_node, to_nodes_edges)| {
+                to_nodes_edges
+                    .into_iter()
+                    .map(move |(to_node, _)| (from_node, to_node))
+            })
+            .map(|(from_node, to_node)| {
+                let known = "feature nodes for a link should be known";
+                let from_ix = self.inner.map.get(&from_node).expect(known).feature_ix;
+                let to_ix = self.inner.map.get(&to_node).expect(known).feature_ix;
+                let edge_ix = dep_graph
+                    .find_edge(from_ix, to_ix)
+                    .expect("feature edges for a link should be present");
+                self.edge_to_link(from_ix, to_ix, edge_ix)
+            })
+            .collect()
+    }
+
+    /// Returns metrics about how much of the graph the given feature pulls in.
+    ///
+    /// The metrics are computed from the forward closure of this feature: the feature itself, plus
+    /// every feature it depends on, directly or indirectly. As with `query_forward`, weak
+    /// dependency edges ("foo?/c") are only followed if the optional dependency is enabled.
+    ///
+    /// Returns an error if the feature ID is unknown.
+    pub fn feature_impact<'a>(
+        &self,
+        feature_id: impl Into<FeatureId<'a>>,
+    ) -> Result<FeatureImpact, Error> {
+        let feature_ix = self.feature_ix_err(feature_id.into())?;
+        let dep_graph = self.dep_graph();
+
+        let closure = self.forward_closure(feature_ix);
+        let mut reachable_features = 0;
+        let mut added_edges = 0;
+        let mut packages = FixedBitSet::with_capacity(self.package_graph.package_count());
+        for ix in closure.ones().map(NodeIndex::new) {
+            reachable_features += 1;
+            // Weak dependency edges may end outside the closure, so only count edges within it.
+            added_edges += dep_graph
+                .edges(ix)
+                .filter(|edge| closure.contains(edge.target().index()))
+                .count();
+            packages.insert(dep_graph[ix].package_ix().index());
+        }
+
+        Ok(FeatureImpact {
+            reachable_features,
+            reachable_packages: packages.count_ones(..),
+            added_edges,
+        })
+    }
+
+    /// Returns the number of features of each type in this graph.
+    ///
+    /// This counts every feature in the graph, not just the features in a particular resolved set.
+    pub fn feature_type_totals(&self) -> FeatureTypeCounts {
+        let mut counts = FeatureTypeCounts::default();
+        for metadata in self.inner.map.values() {
+            match metadata.feature_type {
+                FeatureType::NamedFeature => counts.named_features += 1,
+                FeatureType::OptionalDep => counts.optional_deps += 1,
+                FeatureType::BasePackage => counts.base_packages += 1,
+            }
+        }
+        counts
+    }
+
+    /// Iterates over all features of the given type in this graph.
+    ///
+    /// For example, `FeatureType::OptionalDep` returns every optional dependency of every package,
+    /// and `FeatureType::BasePackage` returns the base feature of every package.
+    pub fn features_by_type(
+        &self,
+        feature_type: FeatureType,
+    ) -> impl Iterator<Item = FeatureId<'g>> + 'g {
+        let package_graph = self.package_graph;
+        let inner = self.inner;
+        self.dep_graph()
+            .node_references()
+            .filter(move |(_, feature_node)| {
+                inner
+                    .map
+                    .get(feature_node)
+                    .map(|metadata| metadata.feature_type)
+                    == Some(feature_type)
+            })
+            .map(move |(_, feature_node)| FeatureId::from_node(package_graph, feature_node))
+    }
+
+    /// Returns a precomputed transitive closure of this feature graph.
+    ///
+    /// The closure is computed the first time this method is called, and is then cached. Once it
+    /// has been computed, `depends_on` uses it as well, making each query constant-time. This is
+    /// worth doing if many `depends_on` queries are going to be made.
+    ///
+    /// The closure is computed over the strongly connected components of the graph. With `N`
+    /// components (at most `feature_count()`), it takes up roughly `N * N / 8` bytes of memory --
+    /// for a graph with 50,000 features, this can be up to around 300MB.
+    pub fn reachability(&self) -> FeatureReachability<'g> {
+        let inner = self
+            .inner
+            .reachability
+            .get_or_init(|| Reachability::new(self.dep_graph(), self.sccs()));
+        FeatureReachability {
+            graph: *self,
+            inner,
+        }
+    }
+
     /// Returns information about dependency cycles.
     ///
     /// For more information, see the documentation for `Cycles`.
@@ -157,6 +709,14 @@ impl<'g> FeatureGraph<'g> {
         Cycles::new(*self)
     }
 
+    /// Returns true if this feature graph has any cycles, including self-loops.
+    ///
+    /// This uses the cached strongly connected components of this graph, so it is cheaper than
+    /// checking whether `cycles().all_cycles()` is empty.
+    pub fn is_cyclic(&self) -> bool {
+        self.sccs().is_cyclic()
+    }
+
     // ---
     // Helper methods
     // ---
@@ -187,12 +747,40 @@ impl<'g> FeatureGraph<'g> {
         &self.inner.graph
     }
 
+    pub(super) fn edge_to_link(
+        &self,
+        source: NodeIndex<FeatureIx>,
+        target: NodeIndex<FeatureIx>,
+        edge_ix: EdgeIndex<FeatureIx>,
+    ) -> FeatureLink<'g> {
+        let dep_graph = self.dep_graph();
+        FeatureLink {
+            from: FeatureId::from_node(self.package_graph, &dep_graph[source]),
+            to: FeatureId::from_node(self.package_graph, &dep_graph[target]),
+            link_type: dep_graph[edge_ix].link_type(),
+        }
+    }
+
     fn feature_ix_depends_on(
         &self,
         a_ix: NodeIndex<FeatureIx>,
         b_ix: NodeIndex<FeatureIx>,
     ) -> bool {
-        has_path_connecting(self.dep_graph(), a_ix, b_ix, None)
+        match self.inner.reachability.get() {
+            Some(reachability) => reachability.is_reachable(a_ix, b_ix),
+            None => has_path_connecting(self.dep_graph(), a_ix, b_ix, None),
+        }
+    }
+
+    /// Returns the features reachable from `feature_ix`, following weak dependency edges the same
+    /// way a resolve does.
+    fn forward_closure(&self, feature_ix: NodeIndex<FeatureIx>) -> FixedBitSet {
+        FeatureSet::resolve_core(
+            *self,
+            QueryParams::Forward(iter::once(feature_ix).collect()),
+            |_, _, _| true,
+        )
+        .included
     }
 
     pub(super) fn feature_ixs_for_package_ix(
@@ -259,6 +847,22 @@ impl<'g> FeatureGraph<'g> {
         Some(metadata.feature_ix)
     }
 
+    /// Returns the feature ID for this feature name, borrowing the name from the graph.
+    fn named_feature_id(
+        &self,
+        metadata: PackageMetadata<'g>,
+        feature_name: &str,
+    ) -> Result<FeatureId<'g>, Error> {
+        let unknown = || Error::UnknownFeatureId(metadata.id().clone(), Some(feature_name.into()));
+        let feature_idx = metadata.get_feature_idx(feature_name).ok_or_else(unknown)?;
+        let feature_name = metadata
+            .feature_idx_to_name(feature_idx)
+            .ok_or_else(unknown)?;
+        let feature_id = FeatureId::new(metadata.id(), feature_name);
+        self.feature_ix_err(feature_id)?;
+        Ok(feature_id)
+    }
+
     pub(super) fn feature_ix_err(
         &self,
         feature_id: FeatureId<'g>,
@@ -270,6 +874,21 @@ impl<'g> FeatureGraph<'g> {
     }
 }
 
+/// Splits a feature spec into an optional package name and a feature name.
+fn parse_feature_spec(s: &str) -> Result<(Option<&str>, &str), Error> {
+    let (package_name, feature_name, weak) = FeatureGraphBuildState::split_feature_dep(s);
+    if weak {
+        return Err(Error::FeatureSpecError(format!(
+            "weak dependency syntax isn't supported in '{}'",
+            s
+        )));
+    }
+    if feature_name.is_empty() || package_name == Some("") {
+        return Err(Error::FeatureSpecError(format!("empty name in '{}'", s)));
+    }
+    Ok((package_name, feature_name))
+}
+
 /// An identifier for a (package, feature) pair in a feature graph.
 ///
 /// Returned by various methods on `FeatureGraph` and `FeatureQuery`.
@@ -359,6 +978,74 @@ impl<'g> From<FeatureId<'g>> for (PackageId, Option<String>) {
     }
 }
 
+/// An owned version of `FeatureId`, which doesn't borrow from a feature graph.
+///
+/// With the `serde1` feature enabled, this can be serialized and deserialized, e.g. to store the
+/// results of an analysis and reload them later.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct FeatureIdBuf {
+    package_id: PackageId,
+    feature: Option<String>,
+}
+
+impl FeatureIdBuf {
+    /// Creates a new `FeatureIdBuf`. `None` for the feature represents the "base" feature.
+    pub fn new(package_id: PackageId, feature: Option<String>) -> Self {
+        Self {
+            package_id,
+            feature,
+        }
+    }
+
+    /// Returns the package ID.
+    pub fn package_id(&self) -> &PackageId {
+        &self.package_id
+    }
+
+    /// Returns the name of the feature, or `None` if this is the "base" feature for this package.
+    pub fn feature(&self) -> Option<&str> {
+        self.feature.as_deref()
+    }
+
+    /// Returns true if this is the "base" feature for the package.
+    pub fn is_base(&self) -> bool {
+        self.feature.is_none()
+    }
+
+    /// Looks up the `FeatureId` corresponding to this ID in the given feature graph.
+    ///
+    /// Returns an `UnknownPackageId` or `UnknownFeatureId` error if the package or the feature
+    /// aren't present in this graph.
+    pub fn to_feature_id<'g>(
+        &self,
+        feature_graph: &FeatureGraph<'g>,
+    ) -> Result<FeatureId<'g>, Error> {
+        let metadata = feature_graph
+            .package_graph
+            .metadata(&self.package_id)
+            .ok_or_else(|| Error::UnknownPackageId(self.package_id.clone()))?;
+        match &self.feature {
+            Some(feature) => feature_graph.named_feature_id(metadata, feature),
+            None => {
+                let feature_id = FeatureId::base(metadata.id());
+                feature_graph.feature_ix_err(feature_id)?;
+                Ok(feature_id)
+            }
+        }
+    }
+}
+
+impl<'g> From<FeatureId<'g>> for FeatureIdBuf {
+    fn from(feature_id: FeatureId<'g>) -> Self {
+        let (package_id, feature) = feature_id.into();
+        Self {
+            package_id,
+            feature,
+        }
+    }
+}
+
 /// Metadata for a feature within a package.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct FeatureMetadata<'g> {
@@ -378,6 +1065,124 @@ impl<'g> FeatureMetadata<'g> {
     }
 }
 
+/// A precomputed transitive closure of a feature graph.
+///
+/// Returned by `FeatureGraph::reachability`.
+#[derive(Clone, Copy, Debug)]
+pub struct FeatureReachability<'g> {
+    graph: FeatureGraph<'g>,
+    inner: &'g Reachability,
+}
+
+impl<'g> FeatureReachability<'g> {
+    /// Returns true if `feature_a` depends (directly or indirectly) on `feature_b`.
+    ///
+    /// This is the same as `FeatureGraph::depends_on`, but is always constant-time.
+    pub fn depends_on<'a>(
+        &self,
+        feature_a: impl Into<FeatureId<'a>>,
+        feature_b: impl Into<FeatureId<'a>>,
+    ) -> Result<bool, Error> {
+        let a_ix = self.graph.feature_ix_err(feature_a.into())?;
+        let b_ix = self.graph.feature_ix_err(feature_b.into())?;
+        Ok(self.inner.is_reachable(a_ix, b_ix))
+    }
+
+    /// Returns the number of strongly connected components the closure was computed over.
+    ///
+    /// The closure takes up roughly `scc_count() * scc_count() / 8` bytes of memory.
+    pub fn scc_count(&self) -> usize {
+        self.inner.scc_count()
+    }
+}
+
+/// Metrics about the part of a feature graph pulled in by a single feature.
+///
+/// Returned by `FeatureGraph::feature_impact`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FeatureImpact {
+    reachable_features: usize,
+    reachable_packages: usize,
+    added_edges: usize,
+}
+
+impl FeatureImpact {
+    /// Returns the number of features reachable from this feature, including the feature itself.
+    pub fn reachable_features(&self) -> usize {
+        self.reachable_features
+    }
+
+    /// Returns the number of distinct packages that the reachable features belong to.
+    pub fn reachable_packages(&self) -> usize {
+        self.reachable_packages
+    }
+
+    /// Returns the number of edges between the reachable features.
+    pub fn added_edges(&self) -> usize {
+        self.added_edges
+    }
+}
+
+/// The number of features of each type in a feature graph.
+///
+/// Returned by `FeatureGraph::feature_type_totals`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct FeatureTypeCounts {
+    named_features: usize,
+    optional_deps: usize,
+    base_packages: usize,
+}
+
+impl FeatureTypeCounts {
+    /// Returns the number of named features, i.e. features in the `[features]` section.
+    pub fn named_features(&self) -> usize {
+        self.named_features
+    }
+
+    /// Returns the number of optional dependencies.
+    pub fn optional_deps(&self) -> usize {
+        self.optional_deps
+    }
+
+    /// Returns the number of "base" packages. There is one for every package in the graph.
+    pub fn base_packages(&self) -> usize {
+        self.base_packages
+    }
+
+    /// Returns the total number of features across all types.
+    pub fn total(&self) -> usize {
+        self.named_features + self.optional_deps + self.base_packages
+    }
+}
+
+/// The named features added and removed between two versions of a package.
+///
+/// Returned by `FeatureGraph::package_feature_diff`.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct FeatureNameDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+impl FeatureNameDiff {
+    /// Returns the names of features present in the new graph but not the old one, in sorted
+    /// order.
+    pub fn added(&self) -> &[String] {
+        &self.added
+    }
+
+    /// Returns the names of features present in the old graph but not the new one, in sorted
+    /// order.
+    pub fn removed(&self) -> &[String] {
+        &self.removed
+    }
+
+    /// Returns true if no features were added or removed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
 /// A graph representing every possible feature of every package, and the connections between them.
 #[derive(Clone, Debug)]
 pub(in crate::graph) struct FeatureGraphImpl {
@@ -386,8 +1191,15 @@ pub(in crate::graph) struct FeatureGraphImpl {
     pub(super) base_ixs: Vec<NodeIndex<FeatureIx>>,
     pub(super) map: HashMap<FeatureNode, FeatureMetadataImpl>,
     pub(super) warnings: Vec<FeatureGraphWarning>,
+    // Edges for weak dependency features ("foo?/c"), along with the optional dependency each one
+    // is conditional on. These need special handling during resolution.
+    pub(super) weak_edges: Vec<(EdgeIndex<FeatureIx>, NodeIndex<FeatureIx>)>,
     // The strongly connected components of the feature graph. Computed on demand.
     pub(super) sccs: OnceCell<Sccs<FeatureIx>>,
+    // The features reachable from the default features of workspace members. Computed on demand.
+    pub(super) workspace_reachable: OnceCell<FixedBitSet>,
+    // The transitive closure of the feature graph. Computed on demand.
+    pub(super) reachability: OnceCell<Reachability>,
 }
 
 impl FeatureGraphImpl {
@@ -397,34 +1209,36 @@ impl FeatureGraphImpl {
 
         // Graph returns its node references in order -- check this in debug builds.
         let mut prev_ix = None;
-        for (package_ix, package_id) in package_graph.dep_graph.node_references() {
-            if let Some(prev_ix) = prev_ix {
-                debug_assert_eq!(package_ix.index(), prev_ix + 1, "package ixs are in order");
-            }
-            prev_ix = Some(package_ix.index());
+        let packages: Vec<_> = package_graph
+            .dep_graph
+            .node_references()
+            .map(|(package_ix, package_id)| {
+                if let Some(prev_ix) = prev_ix {
+                    debug_assert_eq!(package_ix.index(), prev_ix + 1, "package ixs are in order");
+                }
+                prev_ix = Some(package_ix.index());
 
-            let metadata = package_graph
-                .metadata(package_id)
-                .expect("valid package ID");
-            build_state.add_nodes(metadata);
-        }
+                package_graph
+                    .metadata(package_id)
+                    .expect("valid package ID")
+            })
+            .collect();
+        build_state.add_nodes(&packages);
 
         build_state.end_nodes();
 
         // The choice of bottom-up for this loop and the next is pretty arbitrary.
-        for metadata in package_graph
+        let packages: Vec<_> = package_graph
             .resolve_all()
             .packages(DependencyDirection::Reverse)
-        {
-            build_state.add_named_feature_edges(metadata);
-        }
+            .collect();
+        build_state.add_named_feature_edges(&packages);
 
-        for link in package_graph
+        let links: Vec<_> = package_graph
             .resolve_all()
             .links(DependencyDirection::Reverse)
-        {
-            build_state.add_dependency_edges(link);
-        }
+            .collect();
+        build_state.add_dependency_edges(&links);
 
         build_state.build()
     }
@@ -514,6 +1328,149 @@ pub(crate) enum FeatureEdge {
     /// "a" = ["b", "foo/c"]
     /// ```
     FeatureDependency,
+    /// This edge is from a feature to a feature in an optional dependency, through a weak
+    /// dependency feature:
+    ///
+    /// ```toml
+    /// [features]
+    /// "a" = ["foo?/c"]
+    /// ```
+    ///
+    /// During forward resolution, this edge is only followed if the optional dependency `foo` is
+    /// enabled through some other means.
+    WeakFeatureDependency,
+}
+
+impl FeatureEdge {
+    /// Returns the public kind of this edge.
+    pub(super) fn kind(&self) -> FeatureEdgeKind {
+        match self {
+            FeatureEdge::FeatureToBase => FeatureEdgeKind::FeatureToBase,
+            FeatureEdge::Dependency { .. } => FeatureEdgeKind::Dependency,
+            FeatureEdge::FeatureDependency => FeatureEdgeKind::FeatureDependency,
+            FeatureEdge::WeakFeatureDependency => FeatureEdgeKind::WeakFeatureDependency,
+        }
+    }
+
+    /// Returns the public representation of this edge.
+    pub(super) fn link_type(&self) -> FeatureLinkType<'_> {
+        match self {
+            FeatureEdge::FeatureToBase => FeatureLinkType::FeatureToBase,
+            FeatureEdge::Dependency { normal, build, dev } => FeatureLinkType::Dependency {
+                normal: PlatformStatus::new(normal),
+                build: PlatformStatus::new(build),
+                dev: PlatformStatus::new(dev),
+            },
+            FeatureEdge::FeatureDependency => FeatureLinkType::FeatureDependency,
+            FeatureEdge::WeakFeatureDependency => FeatureLinkType::WeakFeatureDependency,
+        }
+    }
+
+    /// Evaluates whether this edge is followed on the given platform.
+    ///
+    /// Edges within a package are always followed, while dependency edges are followed if any of
+    /// the normal, build or dev dependencies are enabled.
+    pub(super) fn enabled_on(&self, platform: &Platform<'_>) -> EnabledTernary {
+        match self {
+            FeatureEdge::FeatureToBase
+            | FeatureEdge::FeatureDependency
+            | FeatureEdge::WeakFeatureDependency => EnabledTernary::Enabled,
+            FeatureEdge::Dependency { normal, build, dev } => normal
+                .enabled_on(platform)
+                .or(build.enabled_on(platform))
+                .or(dev.enabled_on(platform)),
+        }
+    }
+}
+
+/// The kind of a link between two features.
+///
+/// Returned by `FeatureGraph::all_links`.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum FeatureEdgeKind {
+    /// This link is from a feature to its base package.
+    FeatureToBase,
+    /// This link is present because a feature is enabled in a dependency.
+    Dependency,
+    /// This link is from a feature depending on other features within the same package, or on
+    /// features in a dependency through `"foo/c"` syntax.
+    FeatureDependency,
+    /// This link is from a feature depending on a feature in an optional dependency through
+    /// `"foo?/c"` syntax. It is only followed if `foo` is enabled through some other means.
+    WeakFeatureDependency,
+}
+
+/// A direct link between two features.
+///
+/// Returned by `FeatureGraph::link`.
+#[derive(Copy, Clone, Debug)]
+pub struct FeatureLink<'g> {
+    from: FeatureId<'g>,
+    to: FeatureId<'g>,
+    link_type: FeatureLinkType<'g>,
+}
+
+impl<'g> FeatureLink<'g> {
+    /// Returns the feature this link is from.
+    pub fn from(&self) -> FeatureId<'g> {
+        self.from
+    }
+
+    /// Returns the feature this link is to.
+    pub fn to(&self) -> FeatureId<'g> {
+        self.to
+    }
+
+    /// Returns the type of this link, describing why `from` depends on `to`.
+    pub fn link_type(&self) -> FeatureLinkType<'g> {
+        self.link_type
+    }
+
+    /// Returns the kind of this link.
+    pub fn kind(&self) -> FeatureEdgeKind {
+        self.link_type.kind()
+    }
+}
+
+/// The type of a link between two features, along with any information associated with it.
+///
+/// Part of a `FeatureLink`.
+#[derive(Copy, Clone, Debug)]
+pub enum FeatureLinkType<'g> {
+    /// This link is from a feature to its base package.
+    FeatureToBase,
+    /// This link is present because a feature is enabled in a dependency, e.g. through:
+    ///
+    /// ```toml
+    /// [dependencies]
+    /// foo = { version = "1", features = ["a", "b"] }
+    /// ```
+    Dependency {
+        /// Whether this link is followed for normal dependencies.
+        normal: PlatformStatus<'g>,
+        /// Whether this link is followed for build dependencies.
+        build: PlatformStatus<'g>,
+        /// Whether this link is followed for dev-dependencies.
+        dev: PlatformStatus<'g>,
+    },
+    /// This link is from a feature depending on other features within the same package, or on
+    /// features in a dependency through `"foo/c"` syntax.
+    FeatureDependency,
+    /// This link is from a feature depending on a feature in an optional dependency through
+    /// `"foo?/c"` syntax. It is only followed if `foo` is enabled through some other means.
+    WeakFeatureDependency,
+}
+
+impl<'g> FeatureLinkType<'g> {
+    /// Returns the kind of this link.
+    pub fn kind(&self) -> FeatureEdgeKind {
+        match self {
+            FeatureLinkType::FeatureToBase => FeatureEdgeKind::FeatureToBase,
+            FeatureLinkType::Dependency { .. } => FeatureEdgeKind::Dependency,
+            FeatureLinkType::FeatureDependency => FeatureEdgeKind::FeatureDependency,
+            FeatureLinkType::WeakFeatureDependency => FeatureEdgeKind::WeakFeatureDependency,
+        }
+    }
 }
 
 /// Metadata for a particular feature node.
@@ -527,6 +1484,7 @@ pub(super) struct FeatureMetadataImpl {
 ///
 /// Returned by `FeatureMetadata`.
 #[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub enum FeatureType {
     /// This is a named feature in the `[features]` section.
     NamedFeature,
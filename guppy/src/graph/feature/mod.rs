@@ -13,9 +13,12 @@ mod graph_impl;
 #[cfg(feature = "proptest09")]
 mod proptest09;
 mod query;
+mod report;
 mod resolve;
 
+pub(in crate::graph) use build::FeatureGraphBuildState;
 pub use cycles::*;
 pub use graph_impl::*;
 pub use query::*;
+pub use report::*;
 pub use resolve::*;
@@ -1,10 +1,13 @@
 // Copyright (c) The cargo-guppy Contributors
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::graph::feature::{FeatureGraph, FeatureId, FeatureSet};
+use crate::graph::feature::resolve::FeatureResolverFn;
+use crate::graph::feature::{FeatureGraph, FeatureId, FeatureLink, FeatureResolver, FeatureSet};
 use crate::graph::query_core::QueryParams;
 use crate::graph::{DependencyDirection, PackageQuery};
 use crate::Error;
+use fixedbitset::FixedBitSet;
+use petgraph::visit::IntoNodeReferences;
 use std::collections::HashSet;
 
 /// Trait representing whether a feature within a package should be selected.
@@ -92,13 +95,7 @@ pub fn none_filter<'g>() -> impl FeatureFilter<'g> {
 ///
 /// This is equivalent to a standard `cargo build`.
 pub fn default_filter<'g>() -> impl FeatureFilter<'g> {
-    FeatureFilterFn::new(|feature_graph, feature_id| {
-        // XXX it kinda sucks that we already know about the exact feature ixs but need to go
-        // through the feature ID over here. Might be worth reorganizing the code to not do that.
-        feature_graph
-            .is_default_feature(feature_id)
-            .expect("feature IDs should be valid")
-    })
+    DefaultFeaturesOnly::new()
 }
 
 /// Returns a `FeatureFilter` that selects everything from the base filter, plus these additional
@@ -147,6 +144,73 @@ pub fn feature_id_filter<'g: 'a, 'a>(
     })
 }
 
+/// A `FeatureFilter` that mirrors `--no-default-features`, optionally along with `--features`.
+///
+/// From each package the query starts from, this selects the base feature and any named features
+/// passed into `with_features`. In particular, the `default` feature is not selected unless it's
+/// passed in explicitly.
+///
+/// Only the default features of the initial packages are suppressed. If another package depends on
+/// an initial package with default features enabled, its `default` feature is still enabled by that
+/// dependency, just like in Cargo. Dependencies of the initial packages get their default features
+/// based on their dependency specs, as usual.
+#[derive(Clone, Debug, Default)]
+pub struct NoDefaultFeatures<'a> {
+    features: HashSet<&'a str>,
+}
+
+impl<'a> NoDefaultFeatures<'a> {
+    /// Creates a new filter that only selects the base feature.
+    ///
+    /// This is equivalent to a build with `--no-default-features`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new filter that selects the base feature, plus these named features -- regardless
+    /// of what package they are in.
+    ///
+    /// This is equivalent to a build with `--no-default-features --features`.
+    pub fn with_features(features: impl IntoIterator<Item = &'a str>) -> Self {
+        Self {
+            features: features.into_iter().collect(),
+        }
+    }
+}
+
+impl<'g, 'a> FeatureFilter<'g> for NoDefaultFeatures<'a> {
+    fn accept(&mut self, _graph: &FeatureGraph<'g>, feature_id: FeatureId<'g>) -> bool {
+        match feature_id.feature() {
+            Some(feature) => self.features.contains(feature),
+            None => true,
+        }
+    }
+}
+
+/// A `FeatureFilter` that only selects features enabled by default.
+///
+/// A feature is accepted if `FeatureGraph::is_default_feature` returns true for it. This is
+/// equivalent to a standard `cargo build`. `default_filter` returns an instance of this filter.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultFeaturesOnly;
+
+impl DefaultFeaturesOnly {
+    /// Creates a new instance of this filter.
+    pub fn new() -> Self {
+        DefaultFeaturesOnly
+    }
+}
+
+impl<'g> FeatureFilter<'g> for DefaultFeaturesOnly {
+    fn accept(&mut self, graph: &FeatureGraph<'g>, feature_id: FeatureId<'g>) -> bool {
+        // XXX it kinda sucks that we already know about the exact feature ixs but need to go
+        // through the feature ID over here. Might be worth reorganizing the code to not do that.
+        graph
+            .is_default_feature(feature_id)
+            .expect("feature IDs should be valid")
+    }
+}
+
 /// A query over a feature graph.
 ///
 /// This is the entry point for iterators overs IDs and dependency links, and dot graph presentation.
@@ -155,6 +219,8 @@ pub fn feature_id_filter<'g: 'a, 'a>(
 pub struct FeatureQuery<'g> {
     pub(super) graph: FeatureGraph<'g>,
     pub(super) params: QueryParams<FeatureGraph<'g>>,
+    // If set, resolution only follows edges into features in this set.
+    pub(super) accepted: Option<FixedBitSet>,
 }
 
 /// ## Queries
@@ -189,6 +255,7 @@ impl<'g> FeatureGraph<'g> {
         FeatureQuery {
             graph: *self,
             params,
+            accepted: None,
         }
     }
 
@@ -218,6 +285,40 @@ impl<'g> FeatureGraph<'g> {
         Ok(FeatureQuery {
             graph: *self,
             params: QueryParams::Forward(self.feature_ixs(feature_ids)?),
+            accepted: None,
+        })
+    }
+
+    /// Creates a new query that returns transitive dependencies of the given feature IDs, only
+    /// traversing into features accepted by the filter.
+    ///
+    /// Unlike filtering a resolved `FeatureSet` after the fact, features rejected by the filter
+    /// cut off traversal, so their dependencies are only included if they're reachable through
+    /// other means. The initial feature IDs are always included.
+    ///
+    /// The filter is evaluated once for every feature in the graph when this query is created.
+    ///
+    /// Returns an error if any feature IDs are unknown.
+    pub fn query_forward_with_filter<'a>(
+        &self,
+        feature_ids: impl IntoIterator<Item = impl Into<FeatureId<'a>>>,
+        filter: impl FeatureFilter<'g>,
+    ) -> Result<FeatureQuery<'g>, Error> {
+        let mut filter = filter;
+        let feature_ids = feature_ids.into_iter().map(|feature_id| feature_id.into());
+        let params = QueryParams::Forward(self.feature_ixs(feature_ids)?);
+
+        let dep_graph = self.dep_graph();
+        let mut accepted = FixedBitSet::with_capacity(dep_graph.node_count());
+        for (feature_ix, feature_node) in dep_graph.node_references() {
+            let feature_id = FeatureId::from_node(self.package_graph, feature_node);
+            accepted.set(feature_ix.index(), filter.accept(self, feature_id));
+        }
+
+        Ok(FeatureQuery {
+            graph: *self,
+            params,
+            accepted: Some(accepted),
         })
     }
 
@@ -232,6 +333,7 @@ impl<'g> FeatureGraph<'g> {
         Ok(FeatureQuery {
             graph: *self,
             params: QueryParams::Reverse(self.feature_ixs(feature_ids)?),
+            accepted: None,
         })
     }
 }
@@ -258,4 +360,19 @@ impl<'g> FeatureQuery<'g> {
     pub fn resolve(self) -> FeatureSet<'g> {
         FeatureSet::new(self)
     }
+
+    /// Resolves this query into a set of known feature IDs, using the provided resolver to
+    /// determine which links are followed.
+    pub fn resolve_with(self, resolver: impl FeatureResolver<'g>) -> FeatureSet<'g> {
+        FeatureSet::with_resolver(self, resolver)
+    }
+
+    /// Resolves this query into a set of known feature IDs, using the provided resolver function
+    /// to determine which links are followed.
+    pub fn resolve_with_fn(
+        self,
+        resolver_fn: impl FnMut(&FeatureQuery<'g>, FeatureLink<'g>) -> bool,
+    ) -> FeatureSet<'g> {
+        self.resolve_with(FeatureResolverFn(resolver_fn))
+    }
 }
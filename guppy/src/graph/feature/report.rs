@@ -0,0 +1,149 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::graph::feature::{FeatureEdge, FeatureGraph, FeatureId, FeatureType};
+use crate::PackageId;
+use petgraph::prelude::*;
+
+impl<'g> FeatureGraph<'g> {
+    /// Returns a report describing the features of this package: every named feature, what each
+    /// one enables, whether it is a default feature, and the package's optional dependencies.
+    ///
+    /// The report is serializable, and is meant to be displayed to users.
+    ///
+    /// Returns `None` if the package ID is unknown.
+    pub fn feature_report(&self, package_id: &PackageId) -> Option<FeatureReport> {
+        let package = self.package_graph.metadata(package_id)?;
+        let dep_graph = self.dep_graph();
+
+        let features = package
+            .named_features()
+            .map(|name| {
+                let feature_id = FeatureId::new(package.id(), name);
+                let feature_ix = self
+                    .feature_ix(feature_id)
+                    .expect("named features should be known");
+
+                let mut enables = vec![];
+                let mut enables_in_dependencies = vec![];
+                for edge in dep_graph.edges_directed(feature_ix, Outgoing) {
+                    let weak = match edge.weight() {
+                        FeatureEdge::FeatureDependency => false,
+                        FeatureEdge::WeakFeatureDependency => true,
+                        FeatureEdge::FeatureToBase | FeatureEdge::Dependency { .. } => continue,
+                    };
+                    let to_id = FeatureId::from_node(self.package_graph, &dep_graph[edge.target()]);
+                    if to_id.package_id() == package.id() {
+                        enables.extend(to_id.feature().map(|feature| feature.to_string()));
+                    } else {
+                        let to_name = self
+                            .package_graph
+                            .metadata(to_id.package_id())
+                            .expect("valid package ID")
+                            .name();
+                        let separator = if weak { "?/" } else { "/" };
+                        enables_in_dependencies.push(match to_id.feature() {
+                            Some(feature) => format!("{}{}{}", to_name, separator, feature),
+                            None => to_name.to_string(),
+                        });
+                    }
+                }
+                enables.sort();
+                enables_in_dependencies.sort();
+
+                NamedFeatureReport {
+                    name: name.to_string(),
+                    is_default: self
+                        .is_default_feature(feature_id)
+                        .expect("named features should be known"),
+                    enables,
+                    enables_in_dependencies,
+                }
+            })
+            .collect();
+
+        let optional_deps = self
+            .feature_ixs_for_package_ix(package.package_ix())
+            .filter_map(|feature_ix| {
+                let metadata = self.metadata_for_node(&dep_graph[feature_ix])?;
+                match metadata.feature_type() {
+                    FeatureType::OptionalDep => metadata.feature_id().feature(),
+                    FeatureType::NamedFeature | FeatureType::BasePackage => None,
+                }
+            })
+            .map(|feature| feature.to_string())
+            .collect();
+
+        Some(FeatureReport {
+            package_id: package.id().repr().to_string(),
+            features,
+            optional_deps,
+        })
+    }
+}
+
+/// A report describing the features of a package.
+///
+/// Returned by `FeatureGraph::feature_report`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize))]
+pub struct FeatureReport {
+    package_id: String,
+    features: Vec<NamedFeatureReport>,
+    optional_deps: Vec<String>,
+}
+
+impl FeatureReport {
+    /// Returns the package ID this report is for.
+    pub fn package_id(&self) -> &str {
+        &self.package_id
+    }
+
+    /// Returns reports for each named feature in this package, in the order they're defined.
+    pub fn features(&self) -> &[NamedFeatureReport] {
+        &self.features
+    }
+
+    /// Returns the names of the optional dependencies of this package.
+    pub fn optional_deps(&self) -> &[String] {
+        &self.optional_deps
+    }
+}
+
+/// A report describing a single named feature of a package.
+///
+/// Part of a `FeatureReport`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize))]
+pub struct NamedFeatureReport {
+    name: String,
+    is_default: bool,
+    enables: Vec<String>,
+    enables_in_dependencies: Vec<String>,
+}
+
+impl NamedFeatureReport {
+    /// Returns the name of this feature.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns true if this feature is enabled by default.
+    pub fn is_default(&self) -> bool {
+        self.is_default
+    }
+
+    /// Returns the features and optional dependencies within the same package that this feature
+    /// enables, in sorted order.
+    pub fn enables(&self) -> &[String] {
+        &self.enables
+    }
+
+    /// Returns the features in other packages that this feature enables, in sorted order.
+    ///
+    /// These are formatted as `"package-name/feature"`, or `"package-name"` for a package's base
+    /// feature. Weak dependency features are formatted as `"package-name?/feature"`.
+    pub fn enables_in_dependencies(&self) -> &[String] {
+        &self.enables_in_dependencies
+    }
+}
@@ -3,14 +3,24 @@
 
 use crate::debug_ignore::DebugIgnore;
 use crate::graph::feature::{
-    FeatureEdge, FeatureFilter, FeatureGraph, FeatureId, FeatureMetadata, FeatureQuery,
+    FeatureEdge, FeatureEdgeKind, FeatureFilter, FeatureGraph, FeatureId, FeatureLink,
+    FeatureLinkType, FeatureMetadata, FeatureNode, FeatureQuery, FeatureType,
 };
+use crate::graph::query_core::QueryParams;
 use crate::graph::resolve_core::ResolveCore;
-use crate::graph::{DependencyDirection, PackageMetadata, PackageSet};
+use crate::graph::{
+    DependencyDirection, DotWrite, EnabledTernary, FeatureIx, PackageMetadata, PackageSet,
+    PlatformStatus,
+};
+use crate::petgraph_support::dot::{DotFmt, DotVisitor};
 use crate::petgraph_support::IxBitSet;
-use crate::PackageId;
+use crate::{DependencyKind, Error, PackageId, Platform};
 use fixedbitset::FixedBitSet;
-use petgraph::graph::NodeIndex;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::{EdgeRef, NodeFiltered, NodeRef, VisitMap};
+use petgraph::Direction::Incoming;
+use std::collections::VecDeque;
+use std::fmt;
 use std::iter::FromIterator;
 
 impl<'g> FeatureGraph<'g> {
@@ -44,6 +54,434 @@ impl<'g> FeatureGraph<'g> {
             core: ResolveCore::from_included(included.0),
         }
     }
+
+    /// Creates a new `FeatureSet` consisting of all workspace packages with their default features
+    /// and the given features enabled, along with their transitive dependencies.
+    ///
+    /// This is equivalent to a build with `--workspace --features`. Each feature is enabled on
+    /// every workspace member that declares it, either as a named feature or as an optional
+    /// dependency. Features are then unified across the entire workspace.
+    ///
+    /// Returns an `UnknownWorkspaceFeature` error if a feature isn't declared by any workspace
+    /// member.
+    pub fn resolve_workspace_with_features(
+        &self,
+        features: &[String],
+    ) -> Result<FeatureSet<'g>, Error> {
+        let members: Vec<_> = self
+            .package_graph
+            .workspace()
+            .members()
+            .map(|(_, metadata)| metadata)
+            .collect();
+
+        let mut initials: Vec<_> = members
+            .iter()
+            .map(|metadata| {
+                self.feature_ix(metadata.default_feature_id())
+                    .expect("default feature should be known")
+            })
+            .collect();
+        for feature in features {
+            let len = initials.len();
+            initials.extend(
+                members
+                    .iter()
+                    .filter_map(|metadata| self.feature_ix(FeatureId::new(metadata.id(), feature))),
+            );
+            if initials.len() == len {
+                return Err(Error::UnknownWorkspaceFeature(feature.clone()));
+            }
+        }
+
+        Ok(FeatureSet::new(FeatureQuery {
+            graph: *self,
+            params: QueryParams::Forward(initials.into_iter().collect()),
+            accepted: None,
+        }))
+    }
+
+    /// Creates a new `FeatureSet` consisting of a single package with the given features enabled,
+    /// along with its transitive dependencies.
+    ///
+    /// This is equivalent to a build with `-p <package> --features`, with
+    /// `--no-default-features` if `default` is false. Unlike a workspace resolve, features are
+    /// only unified with those enabled by this package's own dependencies.
+    ///
+    /// Returns an `UnknownFeatureId` error if the package doesn't have one of the features, or an
+    /// error if the package ID is unknown.
+    pub fn resolve_package_features(
+        &self,
+        package_id: &PackageId,
+        features: &[&str],
+        default: bool,
+    ) -> Result<FeatureSet<'g>, Error> {
+        let metadata = self
+            .package_graph
+            .metadata(package_id)
+            .ok_or_else(|| Error::UnknownPackageId(package_id.clone()))?;
+
+        let mut initials = vec![self.feature_ix_err(FeatureId::base(metadata.id()))?];
+        if default {
+            initials.push(self.feature_ix_err(metadata.default_feature_id())?);
+        }
+        for feature in features {
+            initials.push(self.feature_ix_err(FeatureId::new(metadata.id(), feature))?);
+        }
+
+        Ok(FeatureSet::new(FeatureQuery {
+            graph: *self,
+            params: QueryParams::Forward(initials.into_iter().collect()),
+            accepted: None,
+        }))
+    }
+
+    /// Creates a new `FeatureSet` consisting of the given packages and their transitive
+    /// dependencies on the given platform, with some additional `cfg` options set.
+    ///
+    /// This is equivalent to a build with `--target` and `--cfg` flags passed in through
+    /// `RUSTFLAGS`. The features selected for `package_ids` are determined by the provided filter.
+    ///
+    /// Each element of `cfgs` is a `(name, value)` pair. For example, `("fuzzing", None)`
+    /// corresponds to `--cfg fuzzing`. A pair like `("foo", Some("bar"))`, corresponding to
+    /// `--cfg 'foo="bar"'`, is rejected with `Error::UnsupportedKeyValueCfg`.
+    ///
+    /// ## Notes
+    ///
+    /// * Dependencies that may or may not be enabled on this platform (for example, because the
+    ///   platform's target features are unknown) are followed.
+    /// * `target-spec` currently rejects unknown key-value `cfg` predicates while the package graph
+    ///   is being constructed, so only plain flags are supported.
+    ///
+    /// Returns an error if any package IDs are unknown, or if any key-value pairs are passed in to
+    /// `cfgs`.
+    pub fn resolve_for_platform_with_cfgs<'a>(
+        &self,
+        package_ids: impl IntoIterator<Item = &'a PackageId>,
+        filter: impl FeatureFilter<'g>,
+        platform: &Platform<'_>,
+        cfgs: &[(String, Option<String>)],
+    ) -> Result<FeatureSet<'g>, Error> {
+        let flags = cfgs
+            .iter()
+            .map(|(name, value)| match value {
+                Some(value) => Err(Error::UnsupportedKeyValueCfg(name.clone(), value.clone())),
+                None => Ok(name.as_str()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let package_ixs: Vec<_> = self.package_graph.package_ixs(package_ids)?;
+        let initials = self.feature_ixs_for_package_ixs_filtered(package_ixs, filter);
+
+        let mut platform = platform.clone();
+        platform.add_flags(&flags);
+
+        Ok(FeatureSet::with_platform(
+            *self,
+            QueryParams::Forward(initials),
+            &platform,
+        ))
+    }
+
+    /// Creates a new `FeatureSet` consisting of the given feature IDs and the features reachable
+    /// from them, only following dependency links of the given kind.
+    ///
+    /// Links between features within a package are always followed, while dependency links are
+    /// followed if they're present on any platform for this kind. Comparing the result against a
+    /// full resolve can be used to find features that are only enabled because of, say, build
+    /// dependencies.
+    ///
+    /// Returns an error if any feature IDs are unknown.
+    pub fn features_by_edge_kind<'a>(
+        &self,
+        feature_ids: impl IntoIterator<Item = impl Into<FeatureId<'a>>>,
+        kind: DependencyKind,
+    ) -> Result<FeatureSet<'g>, Error> {
+        Ok(self
+            .query_forward(feature_ids)?
+            .resolve_with_fn(|_, link| match link.link_type() {
+                FeatureLinkType::Dependency { normal, build, dev } => {
+                    let status = match kind {
+                        DependencyKind::Normal => normal,
+                        DependencyKind::Build => build,
+                        DependencyKind::Development => dev,
+                        _ => return false,
+                    };
+                    !status.is_never()
+                }
+                FeatureLinkType::FeatureToBase
+                | FeatureLinkType::FeatureDependency
+                | FeatureLinkType::WeakFeatureDependency => true,
+            }))
+    }
+
+    /// Returns a small set of features on the root packages that, together with the base features
+    /// of the roots, enables the target feature.
+    ///
+    /// This is meant for generating tight `--features` flags. The default feature of a root is
+    /// treated like any other named feature, so the result is intended to be used along with
+    /// `--no-default-features`. An empty list is returned if the target is enabled by the base
+    /// features alone.
+    ///
+    /// Finding an exactly minimal set is NP-hard in general, so this uses a greedy approach: root
+    /// features are found through a reverse breadth-first search from the target, and are tried
+    /// in order of distance, named features before optional dependencies. Features that aren't
+    /// needed once the target is enabled are then dropped. The result is never redundant, but a
+    /// smaller set may exist.
+    ///
+    /// Returns an error if any IDs are unknown, or if the target can't be enabled from the roots.
+    pub fn minimal_enabling_set<'a>(
+        &self,
+        target: impl Into<FeatureId<'a>>,
+        roots: &[PackageId],
+    ) -> Result<Vec<FeatureId<'g>>, Error> {
+        let target = target.into();
+        let target_ix = self.feature_ix_err(target)?;
+        let root_ixs: Vec<_> = self.package_graph.package_ixs(roots)?;
+        let base_ixs: Vec<_> = root_ixs
+            .iter()
+            .map(|package_ix| self.inner.base_ixs[package_ix.index()])
+            .collect();
+
+        let enables = |selected: &[NodeIndex<FeatureIx>]| {
+            let initials = base_ixs.iter().chain(selected).copied().collect();
+            let feature_set = FeatureSet::new(FeatureQuery {
+                graph: *self,
+                params: QueryParams::Forward(initials),
+                accepted: None,
+            });
+            feature_set.core.contains(target_ix)
+        };
+        if enables(&[]) {
+            return Ok(vec![]);
+        }
+
+        // Find features on the roots in order of distance from the target.
+        let dep_graph = self.dep_graph();
+        let mut candidates = vec![];
+        let mut visited = FixedBitSet::with_capacity(dep_graph.node_count());
+        let mut queue = VecDeque::new();
+        visited.put(target_ix.index());
+        queue.push_back(target_ix);
+        while let Some(feature_ix) = queue.pop_front() {
+            let feature_node = &dep_graph[feature_ix];
+            if root_ixs.contains(&feature_node.package_ix())
+                && !FeatureId::from_node(self.package_graph, feature_node).is_base()
+            {
+                candidates.push(feature_ix);
+            }
+            for from_ix in dep_graph.neighbors_directed(feature_ix, Incoming) {
+                if !visited.put(from_ix.index()) {
+                    queue.push_back(from_ix);
+                }
+            }
+        }
+        // This is a stable sort, so BFS order is retained within each type.
+        candidates.sort_by_key(|feature_ix| {
+            let feature_id = FeatureId::from_node(self.package_graph, &dep_graph[*feature_ix]);
+            let metadata = self
+                .metadata(feature_id)
+                .expect("feature ID should have valid metadata");
+            metadata.feature_type() != FeatureType::NamedFeature
+        });
+
+        let mut selected = vec![];
+        for candidate in candidates {
+            selected.push(candidate);
+            if enables(&selected) {
+                break;
+            }
+        }
+        if !enables(&selected) {
+            let (package_id, feature) = target.into();
+            return Err(Error::UnreachableFeatureId(package_id, feature));
+        }
+
+        // Drop any features that later ones made unnecessary.
+        let mut idx = selected.len();
+        while idx > 0 {
+            idx -= 1;
+            let removed = selected.remove(idx);
+            if !enables(&selected) {
+                selected.insert(idx, removed);
+            }
+        }
+
+        Ok(selected
+            .into_iter()
+            .map(|feature_ix| FeatureId::from_node(self.package_graph, &dep_graph[feature_ix]))
+            .collect())
+    }
+
+    /// Returns the features in this graph that can't be reached from the given set, in sorted
+    /// order.
+    ///
+    /// A feature is reachable if it is in `from`, or if a feature in `from` depends on it. Running
+    /// this against a resolved workspace is a way to find `[features]` entries that nothing
+    /// enables.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `from` was derived from a different package graph.
+    pub fn unreachable_features(&self, from: &FeatureSet<'g>) -> Vec<FeatureId<'g>> {
+        assert!(
+            ::std::ptr::eq(self.package_graph, from.graph.package_graph),
+            "package graphs passed into unreachable_features() match"
+        );
+        let reachable = from.closure(DependencyDirection::Forward);
+        let dep_graph = self.dep_graph();
+        let mut unreachable: Vec<_> = dep_graph
+            .node_indices()
+            .filter(|feature_ix| !reachable.core.contains(*feature_ix))
+            .map(|feature_ix| FeatureId::from_node(self.package_graph, &dep_graph[feature_ix]))
+            .collect();
+        unreachable.sort_unstable();
+        unreachable
+    }
+}
+
+/// Represents whether a particular link within a feature graph should be followed during a
+/// resolve operation.
+pub trait FeatureResolver<'g> {
+    /// Returns true if this link should be followed during a resolve operation.
+    ///
+    /// Returning false does not prevent the `to` feature (or `from` feature with `query_reverse`)
+    /// from being included if it's reachable through other means.
+    fn accept(&mut self, query: &FeatureQuery<'g>, link: FeatureLink<'g>) -> bool;
+}
+
+impl<'g, T> FeatureResolver<'g> for &mut T
+where
+    T: FeatureResolver<'g>,
+{
+    fn accept(&mut self, query: &FeatureQuery<'g>, link: FeatureLink<'g>) -> bool {
+        (**self).accept(query, link)
+    }
+}
+
+impl<'g, 'a> FeatureResolver<'g> for Box<dyn FeatureResolver<'g> + 'a> {
+    fn accept(&mut self, query: &FeatureQuery<'g>, link: FeatureLink<'g>) -> bool {
+        (**self).accept(query, link)
+    }
+}
+
+impl<'g> FeatureResolver<'g> for &mut dyn FeatureResolver<'g> {
+    fn accept(&mut self, query: &FeatureQuery<'g>, link: FeatureLink<'g>) -> bool {
+        (**self).accept(query, link)
+    }
+}
+
+pub(super) struct FeatureResolverFn<F>(pub(super) F);
+
+impl<'g, F> FeatureResolver<'g> for FeatureResolverFn<F>
+where
+    F: FnMut(&FeatureQuery<'g>, FeatureLink<'g>) -> bool,
+{
+    fn accept(&mut self, query: &FeatureQuery<'g>, link: FeatureLink<'g>) -> bool {
+        (self.0)(query, link)
+    }
+}
+
+/// Returns a `FeatureResolver` that doesn't follow dev-only dependency links.
+///
+/// By default, features enabled through normal, build and dev-dependencies are unified together,
+/// similar to `cargo test`. This resolver only unifies features through normal and build
+/// dependencies, so the resolved set matches a regular `cargo build` instead.
+///
+/// Links that are present as both a normal or build dependency and a dev-dependency are followed.
+pub fn no_dev_resolver<'g>() -> impl FeatureResolver<'g> {
+    FeatureResolverFn(
+        |_: &FeatureQuery<'g>, link: FeatureLink<'g>| match link.link_type() {
+            FeatureLinkType::Dependency { normal, build, .. } => {
+                !normal.is_never() || !build.is_never()
+            }
+            FeatureLinkType::FeatureToBase
+            | FeatureLinkType::FeatureDependency
+            | FeatureLinkType::WeakFeatureDependency => true,
+        },
+    )
+}
+
+/// A `FeatureResolver` that only follows dependency links that may be enabled on a platform.
+///
+/// This is equivalent to a build with `--target`. Links between features within a package are
+/// always followed. Dependency links are followed if they may be enabled for any of the selected
+/// dependency kinds: normal and dev-dependencies are evaluated against the target platform, and
+/// build dependencies are evaluated against the host platform.
+///
+/// Dependencies that are never enabled on a platform, such as ones behind `cfg(any())`, are not
+/// followed. Dependencies whose status is unknown (for example, because the platform's target
+/// features are unknown) are followed.
+#[derive(Clone, Debug)]
+pub struct PlatformFeatureResolver<'p> {
+    target: &'p Platform<'p>,
+    host: &'p Platform<'p>,
+    normal: bool,
+    build: bool,
+    dev: bool,
+}
+
+impl<'p> PlatformFeatureResolver<'p> {
+    /// Creates a new resolver for the given target platform, following the given dependency
+    /// kinds.
+    ///
+    /// The host platform defaults to the target platform. Use `with_host` to set it separately.
+    pub fn new(
+        platform: &'p Platform<'p>,
+        kinds: impl IntoIterator<Item = DependencyKind>,
+    ) -> Self {
+        let mut resolver = Self {
+            target: platform,
+            host: platform,
+            normal: false,
+            build: false,
+            dev: false,
+        };
+        for kind in kinds {
+            match kind {
+                DependencyKind::Normal => resolver.normal = true,
+                DependencyKind::Build => resolver.build = true,
+                DependencyKind::Development => resolver.dev = true,
+                // Unknown dependency kinds never show up in the feature graph.
+                _ => {}
+            }
+        }
+        resolver
+    }
+
+    /// Sets the host platform, which build dependencies are evaluated against.
+    pub fn with_host(mut self, host: &'p Platform<'p>) -> Self {
+        self.host = host;
+        self
+    }
+
+    /// Returns the target platform.
+    pub fn target(&self) -> &'p Platform<'p> {
+        self.target
+    }
+
+    /// Returns the host platform.
+    pub fn host(&self) -> &'p Platform<'p> {
+        self.host
+    }
+}
+
+impl<'g, 'p> FeatureResolver<'g> for PlatformFeatureResolver<'p> {
+    fn accept(&mut self, _query: &FeatureQuery<'g>, link: FeatureLink<'g>) -> bool {
+        match link.link_type() {
+            FeatureLinkType::Dependency { normal, build, dev } => {
+                let is_enabled = |status: PlatformStatus<'g>, platform: &Platform<'_>| {
+                    status.enabled_on(platform) != EnabledTernary::Disabled
+                };
+                (self.normal && is_enabled(normal, self.target))
+                    || (self.build && is_enabled(build, self.host))
+                    || (self.dev && is_enabled(dev, self.target))
+            }
+            FeatureLinkType::FeatureToBase
+            | FeatureLinkType::FeatureDependency
+            | FeatureLinkType::WeakFeatureDependency => true,
+        }
+    }
 }
 
 /// A set of resolved feature IDs in a feature graph.
@@ -58,9 +496,120 @@ pub struct FeatureSet<'g> {
 impl<'g> FeatureSet<'g> {
     pub(super) fn new(query: FeatureQuery<'g>) -> Self {
         let graph = query.graph;
+        let core = match query.accepted {
+            Some(accepted) => Self::resolve_core(graph, query.params, |_, target, _| {
+                accepted.contains(target.index())
+            }),
+            None => Self::resolve_core(graph, query.params, |_, _, _| true),
+        };
+        Self {
+            graph: DebugIgnore(graph),
+            core,
+        }
+    }
+
+    pub(super) fn with_resolver(
+        query: FeatureQuery<'g>,
+        mut resolver: impl FeatureResolver<'g>,
+    ) -> Self {
+        let graph = query.graph;
+        let params = query.params.clone();
+        let core = Self::resolve_core(graph, params, |source, target, edge_ix| {
+            if let Some(accepted) = &query.accepted {
+                if !accepted.contains(target.index()) {
+                    return false;
+                }
+            }
+            let link = graph.edge_to_link(source, target, edge_ix);
+            resolver.accept(&query, link)
+        });
         Self {
             graph: DebugIgnore(graph),
-            core: ResolveCore::new(graph.dep_graph(), query.params),
+            core,
+        }
+    }
+
+    /// Resolves the query, following edges accepted by the filter.
+    ///
+    /// For forward queries, weak dependency edges ("foo?/c") are only followed if the optional
+    /// dependency they're conditional on is part of the result. Reverse queries follow weak
+    /// dependency edges unconditionally.
+    pub(super) fn resolve_core(
+        graph: FeatureGraph<'g>,
+        params: QueryParams<FeatureGraph<'g>>,
+        mut edge_filter: impl FnMut(
+            NodeIndex<FeatureIx>,
+            NodeIndex<FeatureIx>,
+            EdgeIndex<FeatureIx>,
+        ) -> bool,
+    ) -> ResolveCore<FeatureGraph<'g>> {
+        let dep_graph = graph.dep_graph();
+        let weak_edges = &graph.inner.weak_edges;
+        if params.direction() == DependencyDirection::Reverse || weak_edges.is_empty() {
+            return ResolveCore::with_edge_filter(dep_graph, params, edge_filter);
+        }
+
+        let is_weak = |edge_ix| matches!(dep_graph[edge_ix], FeatureEdge::WeakFeatureDependency);
+        let mut core =
+            ResolveCore::with_edge_filter(dep_graph, params, |source, target, edge_ix| {
+                !is_weak(edge_ix) && edge_filter(source, target, edge_ix)
+            });
+        // Enabling a weak dependency's target may in turn enable other optional dependencies, so
+        // keep going until no more weak edges can be followed.
+        //
+        // Weak edges whose conditions haven't been met yet stay in `pending`. Once a weak edge's
+        // source and optional dependency are both included, the filter is checked exactly once.
+        let mut pending = weak_edges.clone();
+        loop {
+            let mut newly_enabled = vec![];
+            pending.retain(|&(edge_ix, dep_ix)| {
+                let (source, target) = dep_graph
+                    .edge_endpoints(edge_ix)
+                    .expect("weak edges are valid");
+                if core.contains(target) {
+                    // Already included through some other path.
+                    return false;
+                }
+                if !core.contains(source) || !core.contains(dep_ix) {
+                    // Might be followed in a later pass.
+                    return true;
+                }
+                if edge_filter(source, target, edge_ix) {
+                    newly_enabled.push(target);
+                }
+                false
+            });
+            if newly_enabled.is_empty() {
+                break core;
+            }
+
+            // Continue the traversal from the newly enabled nodes. Everything already in the core
+            // has had its outgoing edges visited, so there's no need to go through it again.
+            let new_core = ResolveCore::with_edge_filter(
+                dep_graph,
+                QueryParams::Forward(newly_enabled.into_iter().collect()),
+                |source, target, edge_ix| {
+                    !core.contains(target)
+                        && !is_weak(edge_ix)
+                        && edge_filter(source, target, edge_ix)
+                },
+            );
+            core.union_with(&new_core);
+        }
+    }
+
+    /// Resolves the query, only following edges that are enabled on this platform.
+    pub(super) fn with_platform(
+        graph: FeatureGraph<'g>,
+        params: QueryParams<FeatureGraph<'g>>,
+        platform: &Platform<'_>,
+    ) -> Self {
+        let dep_graph = graph.dep_graph();
+        Self {
+            graph: DebugIgnore(graph),
+            core: Self::resolve_core(graph, params, |_, _, edge_ix| {
+                dep_graph[edge_ix].enabled_on(platform) != EnabledTernary::Disabled
+            }),
         }
     }
 
@@ -72,6 +621,10 @@ impl<'g> FeatureSet<'g> {
         }
     }
 
+    pub(super) fn into_included(self) -> FixedBitSet {
+        self.core.included
+    }
+
     /// Returns the number of feature IDs in this set.
     pub fn len(&self) -> usize {
         self.core.len()
@@ -91,6 +644,43 @@ impl<'g> FeatureSet<'g> {
         )
     }
 
+    /// Adds a feature ID to this set, without adding any of its dependencies.
+    ///
+    /// Returns true if the feature ID wasn't already present, or an error if it is unknown.
+    ///
+    /// The resulting set may not be closed under dependencies: use `closure` to expand it.
+    pub fn insert<'a>(&mut self, feature_id: impl Into<FeatureId<'a>>) -> Result<bool, Error> {
+        let feature_ix = self.graph.feature_ix_err(feature_id.into())?;
+        Ok(self.core.insert(feature_ix))
+    }
+
+    /// Removes a feature ID from this set, without removing any of its dependencies.
+    ///
+    /// Returns true if the feature ID was present, or an error if it is unknown.
+    pub fn remove<'a>(&mut self, feature_id: impl Into<FeatureId<'a>>) -> Result<bool, Error> {
+        let feature_ix = self.graph.feature_ix_err(feature_id.into())?;
+        Ok(self.core.remove(feature_ix))
+    }
+
+    /// Returns the closure of this set in the given direction.
+    ///
+    /// * If direction is Forward, adds every feature that the features in this set depend on.
+    /// * If direction is Reverse, adds every feature that depends on the features in this set.
+    ///
+    /// This is useful for normalizing a set that was edited with `insert` or `remove`. The closure
+    /// only ever grows the set: features are never removed from it.
+    pub fn closure(&self, direction: DependencyDirection) -> FeatureSet<'g> {
+        let initials = self.core.included.ones().map(NodeIndex::new).collect();
+        let params = match direction {
+            DependencyDirection::Forward => QueryParams::Forward(initials),
+            DependencyDirection::Reverse => QueryParams::Reverse(initials),
+        };
+        Self {
+            graph: self.graph,
+            core: Self::resolve_core(*self.graph, params, |_, _, _| true),
+        }
+    }
+
     // ---
     // Set operations
     // ---
@@ -100,46 +690,68 @@ impl<'g> FeatureSet<'g> {
     ///
     /// ## Panics
     ///
-    /// Panics if the package graphs associated with `self` and `other` don't match.
+    /// Panics if the package graphs associated with `self` and `other` don't match. See
+    /// `try_union` for a version that returns an error instead.
     pub fn union(&self, other: &Self) -> Self {
-        assert!(
-            ::std::ptr::eq(self.graph.package_graph, self.graph.package_graph),
-            "package graphs passed into union() match"
-        );
+        self.try_union(other)
+            .unwrap_or_else(|_| panic!("package graphs passed into union() match"))
+    }
+
+    /// Returns a `FeatureSet` that contains all packages present in at least one of `self`
+    /// and `other`.
+    ///
+    /// Returns a `MismatchedGraphs` error if the package graphs associated with `self` and
+    /// `other` don't match.
+    pub fn try_union(&self, other: &Self) -> Result<Self, Error> {
+        self.check_same_graph(other)?;
         let mut res = self.clone();
         res.core.union_with(&other.core);
-        res
+        Ok(res)
     }
 
     /// Returns a `FeatureSet` that contains all packages present in both `self` and `other`.
     ///
     /// ## Panics
     ///
-    /// Panics if the package graphs associated with `self` and `other` don't match.
+    /// Panics if the package graphs associated with `self` and `other` don't match. See
+    /// `try_intersection` for a version that returns an error instead.
     pub fn intersection(&self, other: &Self) -> Self {
-        assert!(
-            ::std::ptr::eq(self.graph.package_graph, self.graph.package_graph),
-            "package graphs passed into intersection() match"
-        );
+        self.try_intersection(other)
+            .unwrap_or_else(|_| panic!("package graphs passed into intersection() match"))
+    }
+
+    /// Returns a `FeatureSet` that contains all packages present in both `self` and `other`.
+    ///
+    /// Returns a `MismatchedGraphs` error if the package graphs associated with `self` and
+    /// `other` don't match.
+    pub fn try_intersection(&self, other: &Self) -> Result<Self, Error> {
+        self.check_same_graph(other)?;
         let mut res = self.clone();
         res.core.intersect_with(&other.core);
-        res
+        Ok(res)
     }
 
     /// Returns a `FeatureSet` that contains all packages present in `self` but not `other`.
     ///
     /// ## Panics
     ///
-    /// Panics if the package graphs associated with `self` and `other` don't match.
+    /// Panics if the package graphs associated with `self` and `other` don't match. See
+    /// `try_difference` for a version that returns an error instead.
     pub fn difference(&self, other: &Self) -> Self {
-        assert!(
-            ::std::ptr::eq(self.graph.package_graph, self.graph.package_graph),
-            "package graphs passed into difference() match"
-        );
-        Self {
+        self.try_difference(other)
+            .unwrap_or_else(|_| panic!("package graphs passed into difference() match"))
+    }
+
+    /// Returns a `FeatureSet` that contains all packages present in `self` but not `other`.
+    ///
+    /// Returns a `MismatchedGraphs` error if the package graphs associated with `self` and
+    /// `other` don't match.
+    pub fn try_difference(&self, other: &Self) -> Result<Self, Error> {
+        self.check_same_graph(other)?;
+        Ok(Self {
             graph: self.graph,
             core: self.core.difference(&other.core),
-        }
+        })
     }
 
     /// Returns a `FeatureSet` that contains all packages present in exactly one of `self` and
@@ -147,15 +759,90 @@ impl<'g> FeatureSet<'g> {
     ///
     /// ## Panics
     ///
-    /// Panics if the package graphs associated with `self` and `other` don't match.
+    /// Panics if the package graphs associated with `self` and `other` don't match. See
+    /// `try_symmetric_difference` for a version that returns an error instead.
     pub fn symmetric_difference(&self, other: &Self) -> Self {
-        assert!(
-            ::std::ptr::eq(self.graph.package_graph, self.graph.package_graph),
-            "package graphs passed into symmetric_difference() match"
-        );
+        self.try_symmetric_difference(other)
+            .unwrap_or_else(|_| panic!("package graphs passed into symmetric_difference() match"))
+    }
+
+    /// Returns a `FeatureSet` that contains all packages present in exactly one of `self` and
+    /// `other`.
+    ///
+    /// Returns a `MismatchedGraphs` error if the package graphs associated with `self` and
+    /// `other` don't match.
+    pub fn try_symmetric_difference(&self, other: &Self) -> Result<Self, Error> {
+        self.check_same_graph(other)?;
         let mut res = self.clone();
         res.core.symmetric_difference_with(&other.core);
-        res
+        Ok(res)
+    }
+
+    fn check_same_graph(&self, other: &Self) -> Result<(), Error> {
+        if ::std::ptr::eq(self.graph.package_graph, other.graph.package_graph) {
+            Ok(())
+        } else {
+            Err(Error::MismatchedGraphs)
+        }
+    }
+
+    /// Returns a `FeatureSet` with features that are only reachable through dev-dependencies
+    /// removed.
+    ///
+    /// This models a regular (non-test) build of the features in this set. The root features of
+    /// this set (the ones with no dependents within it) are retained, along with every feature
+    /// reachable from them through some path that doesn't go through a dev-only dependency edge.
+    /// Features that are reachable through both dev-only and regular paths are retained.
+    pub fn without_dev(&self) -> Self {
+        let dep_graph = self.graph.dep_graph();
+        let roots = self
+            .core
+            .roots(dep_graph, self.graph.sccs(), DependencyDirection::Forward);
+        let included = &self.core.included;
+        let core = ResolveCore::with_edge_filter(
+            dep_graph,
+            QueryParams::Forward(roots.into_iter().collect()),
+            |_, target_ix, edge_ix| {
+                if !included.is_visited(&target_ix) {
+                    return false;
+                }
+                match &dep_graph[edge_ix] {
+                    FeatureEdge::FeatureToBase
+                    | FeatureEdge::FeatureDependency
+                    | FeatureEdge::WeakFeatureDependency => true,
+                    FeatureEdge::Dependency { normal, build, .. } => {
+                        !normal.is_never() || !build.is_never()
+                    }
+                }
+            },
+        );
+        Self {
+            graph: self.graph,
+            core,
+        }
+    }
+
+    /// Returns a `FeatureSet` with the default features of every package in this set removed.
+    ///
+    /// This computes the closure of the default feature of each package with at least one
+    /// feature in this set, and subtracts it from this set. The result shows what's enabled
+    /// beyond a standard build with default features.
+    ///
+    /// Note that this is a plain set difference: a feature that's part of the default closure is
+    /// removed even if it is also enabled through some non-default path (e.g. explicitly through
+    /// `--features`, or by another package depending on it with `default-features = false`).
+    pub fn minus_defaults(&self) -> Self {
+        let graph = *self.graph;
+        let defaults = self
+            .to_package_set()
+            .packages(DependencyDirection::Forward)
+            .filter_map(|package| graph.feature_ix(package.default_feature_id()))
+            .collect();
+        let default_closure = Self {
+            graph: self.graph,
+            core: Self::resolve_core(graph, QueryParams::Forward(defaults), |_, _, _| true),
+        };
+        self.difference(&default_closure)
     }
 
     // ---
@@ -320,6 +1007,18 @@ impl<'g> FeatureSet<'g> {
             })
     }
 
+    /// Constructs a representation of the selected features in `dot` format.
+    pub fn display_dot<'a, V: FeatureDotVisitor + 'g>(
+        &'a self,
+        visitor: V,
+    ) -> impl fmt::Display + 'a {
+        let included = &self.core.included;
+        let node_filtered = NodeFiltered::from_fn(self.graph.dep_graph(), move |feature_ix| {
+            included.is_visited(&feature_ix)
+        });
+        DotFmt::new(node_filtered, VisitorWrap::new(*self.graph, visitor))
+    }
+
     // ---
     // Helper methods
     // ---
@@ -368,3 +1067,44 @@ impl<'g> FeatureSet<'g> {
             })
     }
 }
+
+/// A visitor used for formatting `dot` graphs of features.
+pub trait FeatureDotVisitor {
+    /// Visits this feature. The implementation may output a label for this feature to the given
+    /// `DotWrite`.
+    fn visit_feature(&self, feature: FeatureMetadata<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result;
+
+    /// Visits this link between features. The implementation may output a label for this link to
+    /// the given `DotWrite`.
+    fn visit_link(&self, kind: FeatureEdgeKind, f: &mut DotWrite<'_, '_>) -> fmt::Result;
+}
+
+struct VisitorWrap<'g, V> {
+    graph: FeatureGraph<'g>,
+    inner: V,
+}
+
+impl<'g, V> VisitorWrap<'g, V> {
+    fn new(graph: FeatureGraph<'g>, inner: V) -> Self {
+        Self { graph, inner }
+    }
+}
+
+impl<'g, V, NR, ER> DotVisitor<NR, ER> for VisitorWrap<'g, V>
+where
+    V: FeatureDotVisitor,
+    NR: NodeRef<NodeId = NodeIndex<FeatureIx>, Weight = FeatureNode>,
+    ER: EdgeRef<NodeId = NodeIndex<FeatureIx>, Weight = FeatureEdge>,
+{
+    fn visit_node(&self, node: NR, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        let metadata = self
+            .graph
+            .metadata_for_node(node.weight())
+            .expect("visited node should have associated metadata");
+        self.inner.visit_feature(metadata, f)
+    }
+
+    fn visit_edge(&self, edge: ER, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        self.inner.visit_link(edge.weight().kind(), f)
+    }
+}
@@ -1,19 +1,20 @@
 // Copyright (c) The cargo-guppy Contributors
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::graph::feature::{FeatureGraphImpl, FeatureId, FeatureNode};
+use crate::graph::feature::{FeatureGraphBuildState, FeatureGraphImpl, FeatureId, FeatureNode};
 use crate::graph::{
-    cargo_version_matches, BuildTarget, BuildTargetId, BuildTargetImpl, BuildTargetKind, Cycles,
-    DependencyDirection, OwnedBuildTargetId, PackageIx,
+    cargo_version_matches, BuildTarget, BuildTargetId, BuildTargetImpl, BuildTargetKind,
+    Condensation, CondensationImpl, Cycles, DependencyDirection, OwnedBuildTargetId, PackageIx,
 };
 use crate::petgraph_support::scc::Sccs;
 use crate::{Error, JsonValue, Metadata, MetadataCommand, PackageId, Platform};
-use cargo_metadata::{DependencyKind, NodeDep};
+use cargo_metadata::DependencyKind;
 use fixedbitset::FixedBitSet;
 use indexmap::IndexMap;
 use once_cell::sync::OnceCell;
 use petgraph::algo::{has_path_connecting, DfsSpace};
 use petgraph::prelude::*;
+use petgraph::visit::{NodeFiltered, Reversed};
 use semver::{Version, VersionReq};
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::iter;
@@ -33,8 +34,13 @@ pub struct PackageGraph {
     pub(super) dep_graph: Graph<PackageId, PackageLinkImpl, Directed, PackageIx>,
     // The strongly connected components of the graph, computed on demand.
     pub(super) sccs: OnceCell<Sccs<PackageIx>>,
+    // The condensation of the graph, computed on demand.
+    pub(super) condensation: OnceCell<CondensationImpl>,
     // Feature graph, computed on demand.
     pub(super) feature_graph: OnceCell<FeatureGraphImpl>,
+    // For each edge, the indexes of its endpoints in `data.packages`, computed on demand. This
+    // avoids hashing package IDs every time a link is constructed.
+    pub(super) link_endpoints: OnceCell<Vec<(usize, usize)>>,
     // XXX Should this be in an Arc for quick cloning? Not clear how this would work with node
     // filters though.
     pub(super) data: PackageGraphData,
@@ -43,7 +49,7 @@ pub struct PackageGraph {
 /// Per-package data for a PackageGraph instance.
 #[derive(Clone, Debug)]
 pub(super) struct PackageGraphData {
-    pub(super) packages: HashMap<PackageId, PackageMetadataImpl>,
+    pub(super) packages: IndexMap<PackageId, PackageMetadataImpl>,
     pub(super) workspace: WorkspaceImpl,
 }
 
@@ -217,6 +223,16 @@ impl PackageGraph {
         }
     }
 
+    /// Returns an iterator over the packages in the workspace, sorted by name.
+    ///
+    /// This is a stable order that doesn't depend on the layout of the workspace or on dependency
+    /// relationships, which makes it useful for generating output that's diffed.
+    pub fn workspace_members(&self) -> impl ExactSizeIterator<Item = PackageMetadata<'_>> {
+        self.workspace()
+            .members_by_name()
+            .map(|(_, metadata)| metadata)
+    }
+
     /// Returns an iterator over all the package IDs in this graph.
     pub fn package_ids(&self) -> impl Iterator<Item = &PackageId> + ExactSizeIterator {
         self.data.package_ids()
@@ -237,6 +253,22 @@ impl PackageGraph {
             .map(move |inner| PackageMetadata::new(self, inner))
     }
 
+    /// Returns the metadata for each of the given package IDs, in the same order.
+    ///
+    /// Each element is an `UnknownPackageId` error if the corresponding package ID wasn't found.
+    pub fn metadata_many<'g, 'a>(
+        &'g self,
+        package_ids: impl IntoIterator<Item = &'a PackageId> + 'a,
+    ) -> impl Iterator<Item = Result<PackageMetadata<'g>, Error>> + 'a
+    where
+        'g: 'a,
+    {
+        package_ids.into_iter().map(move |package_id| {
+            self.metadata(package_id)
+                .ok_or_else(|| Error::UnknownPackageId(package_id.clone()))
+        })
+    }
+
     /// Returns the number of packages in this graph.
     pub fn package_count(&self) -> usize {
         // This can be obtained in two different ways: self.dep_graph.node_count() or
@@ -287,6 +319,474 @@ impl PackageGraph {
         Ok(self.dep_graph.contains_edge(a_ix, b_ix))
     }
 
+    /// Returns true if `from` depends (directly or indirectly) on `to` through `via`.
+    ///
+    /// In other words, this returns true if `from` depends on `via`, and `via` depends on `to`.
+    ///
+    /// Returns an error if any of the package IDs are unknown.
+    pub fn path_through(
+        &self,
+        from: &PackageId,
+        via: &PackageId,
+        to: &PackageId,
+    ) -> Result<bool, Error> {
+        // Look up all the package IDs first so that errors are returned consistently.
+        self.package_ix_err(to)?;
+        let mut depends_cache = self.new_depends_cache();
+        Ok(depends_cache.depends_on(from, via)? && depends_cache.depends_on(via, to)?)
+    }
+
+    /// Returns true if `from` depends (directly or indirectly) on `to` without going through
+    /// `via`.
+    ///
+    /// This returns false if `via` is the same as `from` or `to`.
+    ///
+    /// Returns an error if any of the package IDs are unknown.
+    pub fn path_avoiding(
+        &self,
+        from: &PackageId,
+        via: &PackageId,
+        to: &PackageId,
+    ) -> Result<bool, Error> {
+        let from_ix = self.package_ix_err(from)?;
+        let via_ix = self.package_ix_err(via)?;
+        let to_ix = self.package_ix_err(to)?;
+        if via_ix == from_ix || via_ix == to_ix {
+            return Ok(false);
+        }
+
+        let filtered = NodeFiltered::from_fn(&self.dep_graph, |ix| ix != via_ix);
+        Ok(has_path_connecting(&filtered, from_ix, to_ix, None))
+    }
+
+    /// Returns every simple path of dependencies from `from` to `to`.
+    ///
+    /// Each path starts with `from` and ends with `to`, and doesn't contain any package more than
+    /// once, so dependency cycles don't cause infinite paths. If `from` is the same as `to`, the
+    /// only path returned is `[from]`.
+    ///
+    /// The number of paths can grow exponentially with the size of the graph. If `max_paths` is
+    /// specified, at most that many paths are returned.
+    ///
+    /// Returns an error if any of the package IDs are unknown.
+    pub fn all_paths(
+        &self,
+        from: &PackageId,
+        to: &PackageId,
+        max_paths: Option<usize>,
+    ) -> Result<Vec<Vec<&PackageId>>, Error> {
+        let from_ix = self.package_ix_err(from)?;
+        let to_ix = self.package_ix_err(to)?;
+        let max_paths = max_paths.unwrap_or(usize::MAX);
+
+        // Only packages that end up at `to` need to be visited.
+        let mut reaches_to = FixedBitSet::with_capacity(self.dep_graph.node_count());
+        let mut dfs = Dfs::new(Reversed(&self.dep_graph), to_ix);
+        while let Some(ix) = dfs.next(Reversed(&self.dep_graph)) {
+            reaches_to.insert(ix.index());
+        }
+
+        let mut paths = vec![];
+        if max_paths == 0 || !reaches_to.contains(from_ix.index()) {
+            return Ok(paths);
+        }
+        if from_ix == to_ix {
+            paths.push(vec![&self.dep_graph[from_ix]]);
+            return Ok(paths);
+        }
+
+        // The current path, along with the dependencies left to visit for each package in it.
+        let mut on_path = FixedBitSet::with_capacity(self.dep_graph.node_count());
+        on_path.insert(from_ix.index());
+        let mut path = vec![from_ix];
+        let mut stack = vec![self.dep_graph.neighbors_directed(from_ix, Outgoing)];
+        while let Some(neighbors) = stack.last_mut() {
+            match neighbors.next() {
+                Some(ix) if ix == to_ix => {
+                    paths.push(
+                        path.iter()
+                            .chain(iter::once(&to_ix))
+                            .map(|ix| &self.dep_graph[*ix])
+                            .collect(),
+                    );
+                    if paths.len() >= max_paths {
+                        break;
+                    }
+                }
+                Some(ix) => {
+                    if reaches_to.contains(ix.index()) && !on_path.put(ix.index()) {
+                        path.push(ix);
+                        stack.push(self.dep_graph.neighbors_directed(ix, Outgoing));
+                    }
+                }
+                None => {
+                    stack.pop();
+                    let ix = path.pop().expect("path and stack have the same length");
+                    on_path.set(ix.index(), false);
+                }
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// Returns a map from each package ID to the IDs of its direct dependencies.
+    ///
+    /// This is a snapshot of the graph's structure that doesn't depend on internal node indexes,
+    /// and can be used to feed the graph into external algorithms.
+    pub fn adjacency_list(&self) -> HashMap<PackageId, Vec<PackageId>> {
+        self.adjacency_list_directed(DependencyDirection::Forward)
+    }
+
+    /// Returns a map from each package ID to the IDs of its direct dependencies in the specified
+    /// direction.
+    ///
+    /// * If direction is Forward, each package is mapped to its direct dependencies.
+    /// * If direction is Reverse, each package is mapped to the packages that directly depend on
+    ///   it.
+    pub fn adjacency_list_directed(
+        &self,
+        direction: DependencyDirection,
+    ) -> HashMap<PackageId, Vec<PackageId>> {
+        self.packages()
+            .map(|package| {
+                let neighbors = package
+                    .direct_links_directed(direction)
+                    .map(|link| match direction {
+                        DependencyDirection::Forward => link.to().id().clone(),
+                        DependencyDirection::Reverse => link.from().id().clone(),
+                    })
+                    .collect();
+                (package.id().clone(), neighbors)
+            })
+            .collect()
+    }
+
+    /// Returns every package in this graph, ranked by the number of other packages that depend on
+    /// it either directly or transitively.
+    ///
+    /// Packages are sorted in descending order of that count. Ties are broken by package ID.
+    ///
+    /// This computes the reverse transitive closure of every package, so it takes O(V·E) time
+    /// where V is the number of packages and E is the number of links. This may be slow for very
+    /// large graphs.
+    pub fn dependency_rank(&self) -> Vec<(PackageMetadata<'_>, usize)> {
+        let reversed = Reversed(&self.dep_graph);
+        let mut dfs = Dfs::empty(reversed);
+        let mut rank: Vec<_> = self
+            .packages()
+            .map(|package| {
+                dfs.reset(reversed);
+                dfs.move_to(package.package_ix());
+                let mut count = 0;
+                while dfs.next(reversed).is_some() {
+                    count += 1;
+                }
+                // The package itself is always visited, so don't count it.
+                (package, count - 1)
+            })
+            .collect();
+        rank.sort_by(|(a, a_count), (b, b_count)| {
+            b_count.cmp(a_count).then_with(|| a.id().cmp(b.id()))
+        });
+        rank
+    }
+
+    /// Returns groups of packages that share a name but have semver-incompatible versions.
+    ///
+    /// Versions are compatible according to Cargo's rules: `1.2.0` and `1.5.3` are compatible, as
+    /// are `0.3.1` and `0.3.7`, but `0.3.1` and `0.4.0` are not. Each group contains every package
+    /// with that name, sorted by version, and only groups with at least two incompatible versions
+    /// are returned. Groups are returned in order of package name.
+    ///
+    /// This is similar to `cargo tree --duplicates`.
+    pub fn duplicate_versions(&self) -> impl Iterator<Item = (&str, Vec<PackageMetadata<'_>>)> {
+        let mut by_name: BTreeMap<&str, Vec<PackageMetadata>> = BTreeMap::new();
+        for package in self.packages() {
+            by_name.entry(package.name()).or_default().push(package);
+        }
+
+        by_name.into_iter().filter_map(|(name, mut packages)| {
+            let compat_keys: HashSet<_> = packages
+                .iter()
+                .map(|package| semver_compat_key(package.version()))
+                .collect();
+            if compat_keys.len() < 2 {
+                return None;
+            }
+            packages.sort_by(|a, b| {
+                a.version()
+                    .cmp(b.version())
+                    .then_with(|| a.id().cmp(b.id()))
+            });
+            Some((name, packages))
+        })
+    }
+
+    /// Simulates minimal-version selection, similar to `cargo -Z minimal-versions`.
+    ///
+    /// Returns a new graph in which every dependency link points to the lowest version of that
+    /// package that satisfies the link's version requirement.
+    ///
+    /// This is a best-effort simulation: only versions already present in this graph are
+    /// considered, so a requirement may resolve to a version higher than the true minimum if that
+    /// minimum isn't in the lockfile. Packages are matched by name regardless of their source. A
+    /// link is left unchanged if retargeting it would create a self-loop or duplicate an existing
+    /// link from the same package.
+    ///
+    /// The new graph is checked for internal consistency, and an error is returned if that check
+    /// fails.
+    pub fn simulate_minimal_versions(&self) -> Result<PackageGraph, Error> {
+        let mut by_name: HashMap<&str, Vec<(&Version, NodeIndex<PackageIx>)>> = HashMap::new();
+        for package in self.packages() {
+            by_name
+                .entry(package.name())
+                .or_default()
+                .push((package.version(), package.package_ix()));
+        }
+        for candidates in by_name.values_mut() {
+            candidates.sort();
+        }
+
+        let mut dep_graph =
+            Graph::with_capacity(self.dep_graph.node_count(), self.dep_graph.edge_count());
+        for node in self.dep_graph.raw_nodes() {
+            dep_graph.add_node(node.weight.clone());
+        }
+        for edge in self.dep_graph.edge_references() {
+            let (from_ix, to_ix) = (edge.source(), edge.target());
+            let link = edge.weight();
+            let to_name = &self.data.packages[&self.dep_graph[to_ix]].name;
+            let lowest_ix = by_name[to_name.as_str()]
+                .iter()
+                .find(|(version, _)| cargo_version_matches(&link.version_req, version))
+                .map(|(_, package_ix)| *package_ix);
+
+            let target_ix = match lowest_ix {
+                Some(lowest_ix)
+                    if lowest_ix != from_ix
+                        && self.dep_graph.find_edge(from_ix, lowest_ix).is_none()
+                        && dep_graph.find_edge(from_ix, lowest_ix).is_none() =>
+                {
+                    lowest_ix
+                }
+                _ => to_ix,
+            };
+            dep_graph.add_edge(from_ix, target_ix, link.clone());
+        }
+
+        let graph = PackageGraph {
+            dep_graph,
+            sccs: OnceCell::new(),
+            condensation: OnceCell::new(),
+            feature_graph: OnceCell::new(),
+            link_endpoints: OnceCell::new(),
+            data: self.data.clone(),
+        };
+        graph.verify()?;
+        Ok(graph)
+    }
+
+    /// Removes all dependency links from this graph for which the predicate returns false.
+    ///
+    /// This can be used to simulate removing a dependency, e.g. to see which packages would no
+    /// longer be pulled in.
+    ///
+    /// ## Notes
+    ///
+    /// * Packages are never removed, even if they're no longer reachable from the workspace. Use
+    ///   `query_workspace` rather than `resolve_all` to only look at packages that are still
+    ///   reachable.
+    /// * The internal indexes of packages are preserved, but the indexes of links may change.
+    /// * Cached data, including the feature graph, is invalidated. The one exception is the set of
+    ///   dependency cycles: if it was already computed, only the cycles that removed links were a
+    ///   part of are recomputed. This makes repeatedly removing a few links from a large graph
+    ///   cheaper.
+    pub fn retain_edges(&mut self, mut f: impl FnMut(&PackageGraph, PackageLink<'_>) -> bool) {
+        let mut keep = FixedBitSet::with_capacity(self.dep_graph.edge_count());
+        let mut removed = vec![];
+        for edge in self.dep_graph.edge_references() {
+            let link =
+                self.edge_to_link(edge.source(), edge.target(), edge.id(), Some(edge.weight()));
+            if f(self, link) {
+                keep.insert(edge.id().index());
+            } else {
+                removed.push((edge.source(), edge.target()));
+            }
+        }
+        self.dep_graph = self.dep_graph.filter_map(
+            |_, package_id| Some(package_id.clone()),
+            |edge_ix, link| {
+                if keep.contains(edge_ix.index()) {
+                    Some(link.clone())
+                } else {
+                    None
+                }
+            },
+        );
+
+        let sccs = mem::replace(&mut self.sccs, OnceCell::new()).into_inner();
+        self.invalidate_caches();
+        if let Some(mut sccs) = sccs {
+            sccs.remove_edges(&self.dep_graph, removed);
+            self.sccs = OnceCell::from(sccs);
+        }
+    }
+
+    /// Compares the dependency links in this graph against the ones in `other`.
+    ///
+    /// `self` is treated as the old graph and `other` as the new one. Links are matched by their
+    /// `(from, to)` package IDs, so links whose kinds changed (e.g. a dependency that moved from
+    /// `[dependencies]` to `[dev-dependencies]`) are reported as kind changes rather than as
+    /// additions or removals.
+    pub fn edge_diff(&self, other: &PackageGraph) -> EdgeDiff {
+        fn link_kinds(
+            graph: &PackageGraph,
+        ) -> BTreeMap<(PackageId, PackageId), Vec<DependencyKind>> {
+            graph
+                .resolve_all()
+                .links(DependencyDirection::Forward)
+                .map(|link| {
+                    let key = (link.from().id().clone(), link.to().id().clone());
+                    (key, link.kinds())
+                })
+                .collect()
+        }
+
+        let old = link_kinds(self);
+        let mut new = link_kinds(other);
+
+        let mut removed = vec![];
+        let mut kind_changes = vec![];
+        for (key, old_kinds) in old {
+            match new.remove(&key) {
+                Some(new_kinds) => {
+                    if old_kinds != new_kinds {
+                        let (from, to) = key;
+                        kind_changes.push(EdgeKindChange {
+                            from,
+                            to,
+                            old_kinds,
+                            new_kinds,
+                        });
+                    }
+                }
+                None => removed.push(key),
+            }
+        }
+        // Whatever is left in new was added.
+        let added = new.into_keys().collect();
+
+        EdgeDiff {
+            added,
+            removed,
+            kind_changes,
+        }
+    }
+
+    /// Compares the packages in this graph against the ones in `other`.
+    ///
+    /// `self` is treated as the old graph and `other` as the new one. Packages are first matched
+    /// by their `PackageId`s, and are reported as changed if the names of their named features
+    /// differ.
+    ///
+    /// A `PackageId` includes the version, so a version bump would otherwise show up as one package
+    /// removed and another added. Of the packages that remain, if exactly one old package and
+    /// exactly one new package share a name and source, they're paired up and reported as changed.
+    /// If a name and source matches several packages on either side, for example because multiple
+    /// versions of a crate are present, those packages are reported as removed and added.
+    ///
+    /// To compare the dependency links between the two graphs, use `edge_diff`.
+    pub fn diff(&self, other: &PackageGraph) -> GraphDiff {
+        fn sorted_features(package: PackageMetadata<'_>) -> Vec<String> {
+            let mut features: Vec<_> = package.named_features().map(|f| f.to_string()).collect();
+            features.sort_unstable();
+            features
+        }
+
+        fn change(
+            old_package: PackageMetadata<'_>,
+            new_package: PackageMetadata<'_>,
+        ) -> Option<PackageChange> {
+            let old_features = sorted_features(old_package);
+            let new_features = sorted_features(new_package);
+            if old_package.id() != new_package.id() || old_features != new_features {
+                Some(PackageChange {
+                    old_id: old_package.id().clone(),
+                    new_id: new_package.id().clone(),
+                    old_version: old_package.version().clone(),
+                    new_version: new_package.version().clone(),
+                    old_features,
+                    new_features,
+                })
+            } else {
+                None
+            }
+        }
+
+        // Groups packages by (name, source).
+        fn by_name_source<'g>(
+            packages: impl IntoIterator<Item = PackageMetadata<'g>>,
+        ) -> BTreeMap<(&'g str, Option<&'g str>), Vec<PackageMetadata<'g>>> {
+            let mut map: BTreeMap<_, Vec<_>> = BTreeMap::new();
+            for package in packages {
+                map.entry((package.name(), package.source()))
+                    .or_default()
+                    .push(package);
+            }
+            map
+        }
+
+        let mut new: BTreeMap<_, _> = other
+            .packages()
+            .map(|package| (package.id(), package))
+            .collect();
+        let old: BTreeMap<_, _> = self
+            .packages()
+            .map(|package| (package.id(), package))
+            .collect();
+
+        let mut removed = vec![];
+        let mut changed = vec![];
+        for (package_id, old_package) in old {
+            match new.remove(package_id) {
+                Some(new_package) => changed.extend(change(old_package, new_package)),
+                None => removed.push(old_package),
+            }
+        }
+
+        // Pair up the remaining packages by name and source.
+        let mut added = by_name_source(new.into_values());
+        let mut removed_ids = vec![];
+        for (key, old_packages) in by_name_source(removed) {
+            match (
+                old_packages.as_slice(),
+                added.get(&key).map(|v| v.as_slice()),
+            ) {
+                ([old_package], Some([new_package])) => {
+                    changed.extend(change(*old_package, *new_package));
+                    added.remove(&key);
+                }
+                _ => removed_ids.extend(old_packages.iter().map(|package| package.id().clone())),
+            }
+        }
+        let mut added: Vec<_> = added
+            .into_values()
+            .flatten()
+            .map(|package| package.id().clone())
+            .collect();
+
+        added.sort_unstable();
+        removed_ids.sort_unstable();
+        changed.sort_unstable_by(|a, b| a.old_id.cmp(&b.old_id));
+
+        GraphDiff {
+            added,
+            removed: removed_ids,
+            changed,
+        }
+    }
+
     /// Returns information about dependency cycles in this graph.
     ///
     /// For more information, see the documentation for `Cycles`.
@@ -294,6 +794,23 @@ impl PackageGraph {
         Cycles::new(self)
     }
 
+    /// Returns the condensation of this graph, where every dependency cycle is collapsed into a
+    /// single component.
+    ///
+    /// The condensation is computed on first use and cached. For more information, see the
+    /// documentation for `Condensation`.
+    pub fn condensation(&self) -> Condensation<'_> {
+        Condensation::new(self)
+    }
+
+    /// Returns true if this graph has any dependency cycles, including self-loops.
+    ///
+    /// This uses the cached strongly connected components of this graph, so it is cheaper than
+    /// checking whether `cycles().all_cycles()` is empty.
+    pub fn is_cyclic(&self) -> bool {
+        self.sccs().is_cyclic()
+    }
+
     // For more traversals, see query.rs.
 
     // ---
@@ -317,11 +834,36 @@ impl PackageGraph {
         self.sccs.get_or_init(|| Sccs::new(&self.dep_graph))
     }
 
+    pub(super) fn condensation_impl(&self) -> &CondensationImpl {
+        self.condensation
+            .get_or_init(|| CondensationImpl::new(self))
+    }
+
+    /// Returns the indexes of the endpoints of each edge in `data.packages`, indexed by edge.
+    fn link_endpoints(&self) -> &[(usize, usize)] {
+        self.link_endpoints.get_or_init(|| {
+            let package_idx = |package_ix| {
+                let (idx, _, _) = self
+                    .data
+                    .packages
+                    .get_full(&self.dep_graph[package_ix])
+                    .expect("every package should have associated metadata");
+                idx
+            };
+            self.dep_graph
+                .edge_references()
+                .map(|edge| (package_idx(edge.source()), package_idx(edge.target())))
+                .collect()
+        })
+    }
+
     /// Invalidates internal caches. Primarily for testing.
     #[doc(hidden)]
     pub fn invalidate_caches(&mut self) {
         mem::replace(&mut self.sccs, OnceCell::new());
         mem::replace(&mut self.feature_graph, OnceCell::new());
+        self.condensation = OnceCell::new();
+        self.link_endpoints = OnceCell::new();
     }
 
     /// Returns the inner dependency graph.
@@ -344,14 +886,19 @@ impl PackageGraph {
         //
         // https://docs.rs/petgraph/0.4.13/petgraph/graph/struct.EdgeReference.html#method.weight
         // is defined separately for the same reason.
-        let from = self
+        let (from_idx, to_idx) = self.link_endpoints()[edge_ix.index()];
+        let (from_id, from) = self
             .data
-            .metadata_impl(&self.dep_graph[source])
+            .packages
+            .get_index(from_idx)
             .expect("'from' should have associated metadata");
-        let to = self
+        let (to_id, to) = self
             .data
-            .metadata_impl(&self.dep_graph[target])
+            .packages
+            .get_index(to_idx)
             .expect("'to' should have associated metadata");
+        debug_assert_eq!(from_id, &self.dep_graph[source], "'from' matches source");
+        debug_assert_eq!(to_id, &self.dep_graph[target], "'to' matches target");
         PackageLink {
             graph: self,
             from,
@@ -392,6 +939,16 @@ impl PackageGraph {
     }
 }
 
+/// Returns a key such that two versions are semver-compatible according to Cargo if and only if
+/// their keys are equal.
+fn semver_compat_key(version: &Version) -> (u64, u64, u64) {
+    match (version.major, version.minor) {
+        (0, 0) => (0, 0, version.patch),
+        (0, minor) => (0, minor, 0),
+        (major, _) => (major, 0, 0),
+    }
+}
+
 impl PackageGraphData {
     /// Returns an iterator over all the package IDs in this graph.
     pub fn package_ids(&self) -> impl Iterator<Item = &PackageId> + ExactSizeIterator {
@@ -682,6 +1239,14 @@ impl<'g> PackageMetadata<'g> {
         self.inner.publish.as_deref()
     }
 
+    /// Returns the source of this package, e.g. the registry or git repository it was fetched from.
+    ///
+    /// This is the same as the `source` field in `cargo metadata` output, and is `None` for
+    /// packages at local paths.
+    pub fn source(&self) -> Option<&'g str> {
+        self.inner.source.as_ref().map(|x| x.as_ref())
+    }
+
     /// Returns true if this package is in the workspace.
     pub fn in_workspace(&self) -> bool {
         self.inner.workspace_path.is_some()
@@ -712,6 +1277,10 @@ impl<'g> PackageMetadata<'g> {
 
     /// Returns true if this package is a procedural macro.
     ///
+    /// This is the case if the library target has the `proc-macro` crate type, i.e. if
+    /// `proc-macro = true` is set in the `[lib]` section of `Cargo.toml`. Proc macros are always
+    /// built for the host platform.
+    ///
     /// For more about procedural macros, see [Procedural
     /// Macros](https://doc.rust-lang.org/reference/procedural-macros.html) in the Rust reference.
     pub fn is_proc_macro(&self) -> bool {
@@ -763,6 +1332,31 @@ impl<'g> PackageMetadata<'g> {
             .map(|(_, named_feature, _)| named_feature)
     }
 
+    /// Returns the named features of this package that directly enable the given dependency.
+    ///
+    /// A feature enables a dependency if it lists `dep_name`, `dep:dep_name`, or
+    /// `dep_name/feature`. Weak dependency features like `dep_name?/feature` don't enable the
+    /// dependency, so they aren't included. Features that only enable the dependency through other
+    /// named features aren't included either.
+    ///
+    /// `dep_name` is the name the dependency is referred to by in `Cargo.toml`, which may be
+    /// different from the package name if the dependency is renamed.
+    pub fn features_enabling_dep(&self, dep_name: &str) -> Vec<&'g str> {
+        self.named_features_full()
+            .filter(|(_, _, feature_deps)| {
+                feature_deps.iter().any(|feature_dep| {
+                    match FeatureGraphBuildState::split_feature_dep(feature_dep) {
+                        (Some(name), _, weak) => !weak && name == dep_name,
+                        (None, name, _) => {
+                            name == dep_name || name.strip_prefix("dep:") == Some(dep_name)
+                        }
+                    }
+                })
+            })
+            .map(|(_, named_feature, _)| named_feature)
+            .collect()
+    }
+
     // ---
     // Helper methods
     // --
@@ -853,6 +1447,7 @@ pub(crate) struct PackageMetadataImpl {
     pub(super) metadata_table: JsonValue,
     pub(super) links: Option<Box<str>>,
     pub(super) publish: Option<Vec<String>>,
+    pub(super) source: Option<Box<str>>,
     // Some(...) means named feature with listed dependencies.
     // None means an optional dependency.
     pub(super) features: IndexMap<Box<str>, Option<Vec<String>>>,
@@ -862,7 +1457,6 @@ pub(crate) struct PackageMetadataImpl {
     pub(super) workspace_path: Option<Box<Path>>,
     pub(super) build_targets: BTreeMap<OwnedBuildTargetId, BuildTargetImpl>,
     pub(super) has_default_feature: bool,
-    pub(super) resolved_deps: Vec<NodeDep>,
     pub(super) resolved_features: Vec<String>,
 }
 
@@ -898,16 +1492,28 @@ impl<'g> PackageLink<'g> {
     }
 
     /// Returns the name for this dependency edge. This can be affected by a crate rename.
+    ///
+    /// For a dependency declared as `foo = { package = "bar" }`, this is `foo`. Feature
+    /// dependencies such as `"foo/some-feature"` refer to the dependency by this name.
     pub fn dep_name(&self) -> &'g str {
         &self.inner.dep_name
     }
 
     /// Returns the resolved name for this dependency edge. This may involve renaming the crate and
     /// replacing - with _.
+    ///
+    /// This is the name that the dependency is referred to by in Rust code. For the name of the
+    /// package that's depended on, use `self.to().name()`.
     pub fn resolved_name(&self) -> &'g str {
         &self.inner.resolved_name
     }
 
+    /// Returns true if this dependency was renamed in `Cargo.toml`, i.e. if `dep_name` is
+    /// different from the name of the package that's depended on.
+    pub fn is_renamed(&self) -> bool {
+        self.dep_name() != self.to().name()
+    }
+
     /// Returns the semver requirements specified for this dependency.
     ///
     /// To get the resolved version, see the `to` field of the `PackageLink` this was part of.
@@ -962,6 +1568,25 @@ impl<'g> PackageLink<'g> {
         !self.normal().is_present() && !self.build().is_present()
     }
 
+    /// Returns the kinds of dependency this edge is present as, in the order normal, build, dev.
+    pub(super) fn kinds(&self) -> Vec<DependencyKind> {
+        let kinds = [
+            DependencyKind::Normal,
+            DependencyKind::Build,
+            DependencyKind::Development,
+        ];
+        kinds
+            .iter()
+            .copied()
+            .filter(|kind| self.req_for_kind(*kind).is_present())
+            .collect()
+    }
+
+    /// Returns true if both the `from` and `to` packages are in the workspace.
+    pub fn is_workspace_internal(&self) -> bool {
+        self.from().in_workspace() && self.to().in_workspace()
+    }
+
     // ---
     // Helper methods
     // ---
@@ -995,6 +1620,157 @@ pub(crate) struct PackageLinkImpl {
     pub(super) dev: DependencyReqImpl,
 }
 
+/// The differences in dependency links between two package graphs.
+///
+/// Returned by `PackageGraph::edge_diff`. All lists are sorted by `(from, to)` package IDs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EdgeDiff {
+    added: Vec<(PackageId, PackageId)>,
+    removed: Vec<(PackageId, PackageId)>,
+    kind_changes: Vec<EdgeKindChange>,
+}
+
+impl EdgeDiff {
+    /// Returns the `(from, to)` package IDs for links that are only present in the new graph.
+    pub fn added(&self) -> &[(PackageId, PackageId)] {
+        &self.added
+    }
+
+    /// Returns the `(from, to)` package IDs for links that are only present in the old graph.
+    pub fn removed(&self) -> &[(PackageId, PackageId)] {
+        &self.removed
+    }
+
+    /// Returns the links that are present in both graphs, but with different dependency kinds.
+    pub fn kind_changes(&self) -> &[EdgeKindChange] {
+        &self.kind_changes
+    }
+
+    /// Returns true if there are no differences between the two graphs' links.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.kind_changes.is_empty()
+    }
+}
+
+/// The differences in packages between two package graphs.
+///
+/// Returned by `PackageGraph::diff`. All lists are sorted by package ID, with changed packages
+/// sorted by their IDs in the old graph.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphDiff {
+    added: Vec<PackageId>,
+    removed: Vec<PackageId>,
+    changed: Vec<PackageChange>,
+}
+
+impl GraphDiff {
+    /// Returns the IDs of packages that are only present in the new graph.
+    pub fn added(&self) -> &[PackageId] {
+        &self.added
+    }
+
+    /// Returns the IDs of packages that are only present in the old graph.
+    pub fn removed(&self) -> &[PackageId] {
+        &self.removed
+    }
+
+    /// Returns the packages that are present in both graphs, but with different versions or
+    /// named features.
+    ///
+    /// For more about how packages with different versions are matched up, see
+    /// `PackageGraph::diff`.
+    pub fn changed(&self) -> &[PackageChange] {
+        &self.changed
+    }
+
+    /// Returns true if there are no differences between the two graphs' packages.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A package present in two package graphs, but with a different version or named features.
+///
+/// Returned by `GraphDiff::changed`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageChange {
+    old_id: PackageId,
+    new_id: PackageId,
+    old_version: Version,
+    new_version: Version,
+    old_features: Vec<String>,
+    new_features: Vec<String>,
+}
+
+impl PackageChange {
+    /// Returns the ID of this package in the old graph.
+    pub fn old_id(&self) -> &PackageId {
+        &self.old_id
+    }
+
+    /// Returns the ID of this package in the new graph.
+    ///
+    /// This is the same as `old_id` unless the version changed.
+    pub fn new_id(&self) -> &PackageId {
+        &self.new_id
+    }
+
+    /// Returns the version of this package in the old graph.
+    pub fn old_version(&self) -> &Version {
+        &self.old_version
+    }
+
+    /// Returns the version of this package in the new graph.
+    pub fn new_version(&self) -> &Version {
+        &self.new_version
+    }
+
+    /// Returns the named features of this package in the old graph, sorted by name.
+    pub fn old_features(&self) -> &[String] {
+        &self.old_features
+    }
+
+    /// Returns the named features of this package in the new graph, sorted by name.
+    pub fn new_features(&self) -> &[String] {
+        &self.new_features
+    }
+}
+
+/// A dependency link present in two package graphs, but with different dependency kinds.
+///
+/// Returned by `EdgeDiff::kind_changes`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EdgeKindChange {
+    from: PackageId,
+    to: PackageId,
+    old_kinds: Vec<DependencyKind>,
+    new_kinds: Vec<DependencyKind>,
+}
+
+impl EdgeKindChange {
+    /// Returns the package ID on the `from` end of this link.
+    pub fn from(&self) -> &PackageId {
+        &self.from
+    }
+
+    /// Returns the package ID on the `to` end of this link.
+    pub fn to(&self) -> &PackageId {
+        &self.to
+    }
+
+    /// Returns the dependency kinds for this link in the old graph, in the order normal, build,
+    /// dev.
+    pub fn old_kinds(&self) -> &[DependencyKind] {
+        &self.old_kinds
+    }
+
+    /// Returns the dependency kinds for this link in the new graph, in the order normal, build,
+    /// dev.
+    pub fn new_kinds(&self) -> &[DependencyKind] {
+        &self.new_kinds
+    }
+}
+
 /// Information about a specific kind of dependency (normal, build or dev) from a package to another
 /// package.
 ///
@@ -1158,7 +1934,7 @@ pub enum PlatformStatus<'g> {
 }
 
 impl<'g> PlatformStatus<'g> {
-    fn new(specs: &'g PlatformStatusImpl) -> Self {
+    pub(in crate::graph) fn new(specs: &'g PlatformStatusImpl) -> Self {
         match specs {
             PlatformStatusImpl::Always => PlatformStatus::Always,
             PlatformStatusImpl::Specs(specs) => {
@@ -1190,7 +1966,7 @@ impl<'g> PlatformStatus<'g> {
     }
 
     /// Evaluates whether this dependency is enabled on the given platform.
-    pub fn enabled_on(&self, platform: &Platform<'g>) -> EnabledTernary {
+    pub fn enabled_on(&self, platform: &Platform<'_>) -> EnabledTernary {
         match self {
             PlatformStatus::Never => EnabledTernary::Disabled,
             PlatformStatus::Always => EnabledTernary::Enabled,
@@ -1237,7 +2013,7 @@ impl EnabledTernary {
     }
 
     /// OR operation in Kleene K3 logic.
-    fn or(self, other: Self) -> Self {
+    pub(super) fn or(self, other: Self) -> Self {
         use EnabledTernary::*;
 
         match (self, other) {
@@ -1348,6 +2124,14 @@ impl PlatformStatusImpl {
             PlatformStatusImpl::Specs(specs) => specs.is_empty(),
         }
     }
+
+    /// Evaluates whether this status is enabled on the given platform.
+    pub(super) fn enabled_on(&self, platform: &Platform<'_>) -> EnabledTernary {
+        match self {
+            PlatformStatusImpl::Always => EnabledTernary::Enabled,
+            PlatformStatusImpl::Specs(specs) => PlatformEval { specs }.eval(platform),
+        }
+    }
 }
 
 impl Default for PlatformStatusImpl {
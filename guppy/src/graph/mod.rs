@@ -14,6 +14,9 @@ use std::fmt;
 mod build;
 mod build_targets;
 mod cycles;
+mod explain;
+#[cfg(feature = "serde1")]
+mod export;
 #[doc(hidden)]
 pub mod feature;
 mod graph_impl;
@@ -23,10 +26,14 @@ mod query;
 mod query_core;
 mod resolve;
 mod resolve_core;
+mod summary;
 
 pub use crate::petgraph_support::dot::DotWrite;
 pub use build_targets::*;
 pub use cycles::*;
+pub use explain::*;
+#[cfg(feature = "serde1")]
+pub use export::*;
 pub use graph_impl::*;
 use once_cell::sync::Lazy;
 use petgraph::graph::IndexType;
@@ -35,6 +42,7 @@ pub use proptest09::*;
 pub use query::*;
 pub use resolve::*;
 use semver::{Version, VersionReq};
+pub use summary::*;
 
 /// The direction in which to follow dependencies.
 ///
@@ -3,9 +3,12 @@
 
 use crate::graph::query_core::QueryParams;
 use crate::graph::{
-    DependencyDirection, PackageGraph, PackageLink, PackageResolver, PackageSet, ResolverFn,
+    DependencyDirection, PackageGraph, PackageIx, PackageLink, PackageResolver, PackageSet,
+    ResolveState, ResolverFn,
 };
 use crate::{Error, PackageId};
+use fixedbitset::FixedBitSet;
+use petgraph::prelude::*;
 
 /// A query over a package graph.
 ///
@@ -48,6 +51,9 @@ impl PackageGraph {
 
     /// Creates a new query that returns transitive dependencies of the given packages.
     ///
+    /// If no package IDs are provided, the query resolves to an empty set. Use
+    /// `PackageGraph::resolve_all` to select every package in the graph.
+    ///
     /// Returns an error if any package IDs are unknown.
     pub fn query_forward<'g, 'a>(
         &'g self,
@@ -61,6 +67,8 @@ impl PackageGraph {
 
     /// Creates a new query that returns transitive reverse dependencies of the given packages.
     ///
+    /// If no package IDs are provided, the query resolves to an empty set.
+    ///
     /// Returns an error if any package IDs are unknown.
     pub fn query_reverse<'g, 'a>(
         &'g self,
@@ -99,6 +107,55 @@ impl<'g> PackageQuery<'g> {
         PackageSet::new(self)
     }
 
+    /// Resolves this query into a set of known packages, only including packages that are at
+    /// most `max_depth` links away from the initials.
+    ///
+    /// With a `max_depth` of 0 only the initials are returned, and with a `max_depth` of 1 the
+    /// initials and their direct dependencies (or dependents, for reverse queries) are returned.
+    /// A package reachable through several paths is included if any of them is short enough.
+    pub fn resolve_with_depth(self, max_depth: usize) -> PackageSet<'g> {
+        PackageSet::with_max_depth(self, max_depth)
+    }
+
+    /// Resolves this query into a set of known packages, never traversing into the excluded
+    /// packages.
+    ///
+    /// Unlike taking the `difference` of a resolved set with the excluded packages, this also
+    /// leaves out packages that are only reachable through excluded ones. Excluded packages are
+    /// left out even if the query starts from them.
+    ///
+    /// Returns an error if any excluded package IDs are unknown.
+    pub fn resolve_excluding<'a>(
+        self,
+        excluded: impl IntoIterator<Item = &'a PackageId>,
+    ) -> Result<PackageSet<'g>, Error> {
+        let graph = self.graph;
+        let mut excluded_ixs = FixedBitSet::with_capacity(graph.dep_graph().node_count());
+        for package_id in excluded {
+            excluded_ixs.insert(graph.package_ix_err(package_id)?.index());
+        }
+
+        let not_excluded =
+            |package_ix: &NodeIndex<PackageIx>| !excluded_ixs.contains(package_ix.index());
+        let direction = self.direction();
+        let params = match self.params {
+            QueryParams::Forward(initials) => {
+                QueryParams::Forward(initials.iter().copied().filter(not_excluded).collect())
+            }
+            QueryParams::Reverse(initials) => {
+                QueryParams::Reverse(initials.iter().copied().filter(not_excluded).collect())
+            }
+        };
+        let query = PackageQuery { graph, params };
+        Ok(query.resolve_with_fn(|_, link| {
+            let next = match direction {
+                DependencyDirection::Forward => link.to(),
+                DependencyDirection::Reverse => link.from(),
+            };
+            !excluded_ixs.contains(next.package_ix().index())
+        }))
+    }
+
     /// Resolves this query into a set of known packages, using the provided resolver to
     /// determine which links are followed.
     pub fn resolve_with(self, resolver: impl PackageResolver<'g>) -> PackageSet<'g> {
@@ -113,4 +170,24 @@ impl<'g> PackageQuery<'g> {
     ) -> PackageSet<'g> {
         self.resolve_with(ResolverFn(resolver_fn))
     }
+
+    /// Resolves this query into a set of known packages, using the provided resolver function
+    /// to determine which links are followed.
+    ///
+    /// The resolver function is also passed a `ResolveState`, which can be used to check whether
+    /// a package has already been reached. For example, returning false for links to packages
+    /// that have already been reached means that every package is reached through exactly one
+    /// link.
+    ///
+    /// ## Ordering
+    ///
+    /// Links are visited in depth-first order, so the first link to reach a package is not
+    /// necessarily part of a shortest path to it. The order in which links are visited is
+    /// unspecified and may change in the future.
+    pub fn resolve_with_state_fn(
+        self,
+        resolver_fn: impl FnMut(&PackageQuery<'g>, PackageLink<'g>, ResolveState<'_, 'g>) -> bool,
+    ) -> PackageSet<'g> {
+        PackageSet::with_state_resolver_fn(self, resolver_fn)
+    }
 }
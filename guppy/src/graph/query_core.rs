@@ -7,7 +7,8 @@ use crate::sorted_set::SortedSet;
 use fixedbitset::FixedBitSet;
 use petgraph::graph::IndexType;
 use petgraph::prelude::*;
-use petgraph::visit::{IntoEdges, IntoNeighbors, Visitable};
+use petgraph::visit::{IntoEdges, IntoNeighbors, VisitMap, Visitable};
+use std::collections::VecDeque;
 
 #[derive(Clone, Debug)]
 pub(super) enum QueryParams<G: GraphSpec> {
@@ -68,6 +69,68 @@ where
     (reachable, len)
 }
 
+/// Returns the nodes reachable from the roots in at most `max_depth` steps.
+pub(super) fn reachable_map_depth<G, Ix>(
+    graph: G,
+    roots: impl IntoIterator<Item = G::NodeId>,
+    max_depth: usize,
+) -> (FixedBitSet, usize)
+where
+    G: Visitable<NodeId = NodeIndex<Ix>, Map = FixedBitSet> + IntoNeighbors,
+    Ix: IndexType,
+{
+    // Use a BFS so that every node is first discovered at its minimum depth.
+    let mut discovered = graph.visit_map();
+    let mut queue: VecDeque<_> = roots
+        .into_iter()
+        .filter(|root| discovered.visit(*root))
+        .map(|root| (root, 0))
+        .collect();
+    while let Some((node, depth)) = queue.pop_front() {
+        if depth == max_depth {
+            continue;
+        }
+        for neighbor in graph.neighbors(node) {
+            if discovered.visit(neighbor) {
+                queue.push_back((neighbor, depth + 1));
+            }
+        }
+    }
+
+    let len = discovered.count_ones(..);
+    (discovered, len)
+}
+
+/// Returns the minimum number of steps from the roots to every node reachable from them, indexed
+/// by node index. Unreachable nodes have a depth of `None`.
+pub(super) fn bfs_depths<G, Ix>(
+    graph: G,
+    roots: impl IntoIterator<Item = G::NodeId>,
+    node_count: usize,
+) -> Vec<Option<usize>>
+where
+    G: IntoNeighbors<NodeId = NodeIndex<Ix>>,
+    Ix: IndexType,
+{
+    let mut depths = vec![None; node_count];
+    let mut queue = VecDeque::new();
+    for root in roots {
+        if depths[root.index()].is_none() {
+            depths[root.index()] = Some(0);
+            queue.push_back((root, 0));
+        }
+    }
+    while let Some((node, depth)) = queue.pop_front() {
+        for neighbor in graph.neighbors(node) {
+            if depths[neighbor.index()].is_none() {
+                depths[neighbor.index()] = Some(depth + 1);
+                queue.push_back((neighbor, depth + 1));
+            }
+        }
+    }
+    depths
+}
+
 pub(super) fn reachable_map_filtered<G, Ix>(
     graph: G,
     mut edge_filter: impl FnMut(G::EdgeRef) -> bool,
@@ -1,18 +1,28 @@
 // Copyright (c) The cargo-guppy Contributors
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use crate::graph::feature::{FeatureFilter, FeatureSet};
+use crate::graph::query_core::QueryParams;
 use crate::graph::resolve_core::{ResolveCore, Topo};
 use crate::graph::{
-    DependencyDirection, PackageGraph, PackageIx, PackageLink, PackageLinkImpl, PackageMetadata,
-    PackageQuery,
+    cargo_version_matches, BuildTargetId, DependencyDirection, PackageGraph, PackageIx,
+    PackageLink, PackageLinkImpl, PackageMetadata, PackageQuery,
 };
 use crate::petgraph_support::dot::{DotFmt, DotVisitor, DotWrite};
 use crate::petgraph_support::reversed::MaybeReversedEdge;
-use crate::PackageId;
+use crate::petgraph_support::scc::Sccs;
+use crate::{DependencyKind, Error, PackageId};
 use fixedbitset::FixedBitSet;
 use petgraph::prelude::*;
 use petgraph::visit::{NodeFiltered, NodeRef, VisitMap};
-use std::fmt;
+use semver::VersionReq;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::ops::ControlFlow;
+use std::path::Path;
 
 impl PackageGraph {
     /// Creates a new `PackageSet` consisting of all members of this package graph.
@@ -29,6 +39,73 @@ impl PackageGraph {
             core: ResolveCore::all_nodes(&self.dep_graph),
         }
     }
+
+    /// Creates a new `PackageSet` consisting of exactly the given package IDs.
+    ///
+    /// This is the inverse of `PackageSet::to_owned_ids`, and can be used to rehydrate a set whose
+    /// IDs were stored elsewhere. Dependencies of these packages are not included unless they're
+    /// also passed in.
+    ///
+    /// Returns an `UnknownPackageId` error if any of the package IDs aren't known to this graph.
+    pub fn resolve_ids(
+        &self,
+        package_ids: impl IntoIterator<Item = PackageId>,
+    ) -> Result<PackageSet<'_>, Error> {
+        let mut included = FixedBitSet::with_capacity(self.dep_graph().node_count());
+        for package_id in package_ids {
+            included.insert(self.package_ix_err(&package_id)?.index());
+        }
+        Ok(PackageSet::from_included(self, included))
+    }
+
+    /// Returns the packages in this graph that can't be reached from any workspace member.
+    ///
+    /// These are the packages in `resolve_all()` that aren't in `query_workspace().resolve()`. For
+    /// example, a package replaced through `[patch]` or `[replace]` might not be reachable from
+    /// the workspace.
+    ///
+    /// The packages are returned in topological order.
+    pub fn unreachable_from_workspace<'g>(
+        &'g self,
+    ) -> impl ExactSizeIterator<Item = PackageMetadata<'g>> + 'g {
+        let unreachable = self
+            .resolve_all()
+            .difference(&self.query_workspace().resolve());
+        let packages: Vec<_> = unreachable.packages(DependencyDirection::Forward).collect();
+        packages.into_iter()
+    }
+
+    /// Returns the workspace members that no other workspace member depends on.
+    ///
+    /// Dependencies of every kind, including dev-dependencies, are considered. Dependents outside
+    /// the workspace, and a package depending on itself, don't count. This can be used to find
+    /// first-party crates that are no longer used.
+    ///
+    /// If `exclude_entry_points` is true, packages that are meant to not have any dependents are
+    /// skipped: the package at the root of the workspace, and packages with binary targets.
+    ///
+    /// The packages are returned in the same order as `Workspace::members`.
+    pub fn unused_workspace_members(&self, exclude_entry_points: bool) -> Vec<PackageMetadata<'_>> {
+        self.workspace()
+            .members()
+            .map(|(_, package)| package)
+            .filter(|package| {
+                !package.reverse_direct_links().any(|link| {
+                    let from = link.from();
+                    from.in_workspace() && from.id() != package.id()
+                })
+            })
+            .filter(|package| {
+                let is_entry_point = || {
+                    package.workspace_path() == Some(Path::new(""))
+                        || package
+                            .build_targets()
+                            .any(|target| matches!(target.id(), BuildTargetId::Binary(_)))
+                };
+                !exclude_entry_points || !is_entry_point()
+            })
+            .collect()
+    }
 }
 
 /// A set of resolved packages in a package graph.
@@ -49,6 +126,14 @@ impl<'g> PackageSet<'g> {
         }
     }
 
+    pub(super) fn with_max_depth(query: PackageQuery<'g>, max_depth: usize) -> Self {
+        let graph = query.graph;
+        Self {
+            graph,
+            core: ResolveCore::with_max_depth(graph.dep_graph(), query.params, max_depth),
+        }
+    }
+
     pub(super) fn from_included(graph: &'g PackageGraph, included: FixedBitSet) -> Self {
         Self {
             graph,
@@ -75,6 +160,50 @@ impl<'g> PackageSet<'g> {
         }
     }
 
+    pub(super) fn with_state_resolver_fn(
+        query: PackageQuery<'g>,
+        mut resolver_fn: impl FnMut(&PackageQuery<'g>, PackageLink<'g>, ResolveState<'_, 'g>) -> bool,
+    ) -> Self {
+        let graph = query.graph;
+        let params = query.params.clone();
+        let direction = params.direction();
+
+        // Track every package reached so far: the initials, plus the packages at the far end of
+        // every link accepted so far.
+        let mut reached = FixedBitSet::with_capacity(graph.dep_graph().node_count());
+        match &params {
+            QueryParams::Forward(initials) | QueryParams::Reverse(initials) => {
+                for package_ix in initials.iter() {
+                    reached.insert(package_ix.index());
+                }
+            }
+        }
+
+        Self {
+            graph,
+            core: ResolveCore::with_edge_filter(
+                graph.dep_graph(),
+                params,
+                |source, target, edge_ix| {
+                    let link = graph.edge_to_link(source, target, edge_ix, None);
+                    let state = ResolveState {
+                        graph,
+                        reached: &reached,
+                    };
+                    let accepted = resolver_fn(&query, link, state);
+                    if accepted {
+                        let next_ix = match direction {
+                            DependencyDirection::Forward => target,
+                            DependencyDirection::Reverse => source,
+                        };
+                        reached.insert(next_ix.index());
+                    }
+                    accepted
+                },
+            ),
+        }
+    }
+
     /// Returns the number of packages in this set.
     pub fn len(&self) -> usize {
         self.core.len()
@@ -91,6 +220,38 @@ impl<'g> PackageSet<'g> {
         Some(self.core.contains(self.graph.package_ix(package_id)?))
     }
 
+    /// Returns whether each of the given package IDs is contained in this resolve set, in the
+    /// same order.
+    ///
+    /// Package IDs that weren't found in the graph are reported as not contained.
+    pub fn contains_all<'a>(
+        &self,
+        package_ids: impl IntoIterator<Item = &'a PackageId>,
+    ) -> Vec<bool> {
+        package_ids
+            .into_iter()
+            .map(|package_id| self.contains(package_id) == Some(true))
+            .collect()
+    }
+
+    /// Returns true if at least one of the given package IDs is contained in this resolve set.
+    ///
+    /// Stops at the first package ID that's contained. Package IDs that weren't found in the
+    /// graph are treated as not contained.
+    pub fn contains_any<'a>(&self, package_ids: impl IntoIterator<Item = &'a PackageId>) -> bool {
+        package_ids
+            .into_iter()
+            .any(|package_id| self.contains(package_id) == Some(true))
+    }
+
+    /// Returns true if none of the given package IDs are contained in this resolve set.
+    ///
+    /// Stops at the first package ID that's contained. Package IDs that weren't found in the
+    /// graph are treated as not contained.
+    pub fn contains_none<'a>(&self, package_ids: impl IntoIterator<Item = &'a PackageId>) -> bool {
+        !self.contains_any(package_ids)
+    }
+
     // ---
     // Set operations
     // ---
@@ -100,46 +261,68 @@ impl<'g> PackageSet<'g> {
     ///
     /// ## Panics
     ///
-    /// Panics if the package graphs associated with `self` and `other` don't match.
+    /// Panics if the package graphs associated with `self` and `other` don't match. See
+    /// `try_union` for a version that returns an error instead.
     pub fn union(&self, other: &Self) -> Self {
-        assert!(
-            ::std::ptr::eq(self.graph, other.graph),
-            "package graphs passed into union() match"
-        );
+        self.try_union(other)
+            .unwrap_or_else(|_| panic!("package graphs passed into union() match"))
+    }
+
+    /// Returns a `PackageSet` that contains all packages present in at least one of `self`
+    /// and `other`.
+    ///
+    /// Returns a `MismatchedGraphs` error if the package graphs associated with `self` and
+    /// `other` don't match.
+    pub fn try_union(&self, other: &Self) -> Result<Self, Error> {
+        self.check_same_graph(other)?;
         let mut res = self.clone();
         res.core.union_with(&other.core);
-        res
+        Ok(res)
     }
 
     /// Returns a `PackageSet` that contains all packages present in both `self` and `other`.
     ///
     /// ## Panics
     ///
-    /// Panics if the package graphs associated with `self` and `other` don't match.
+    /// Panics if the package graphs associated with `self` and `other` don't match. See
+    /// `try_intersection` for a version that returns an error instead.
     pub fn intersection(&self, other: &Self) -> Self {
-        assert!(
-            ::std::ptr::eq(self.graph, other.graph),
-            "package graphs passed into intersection() match"
-        );
+        self.try_intersection(other)
+            .unwrap_or_else(|_| panic!("package graphs passed into intersection() match"))
+    }
+
+    /// Returns a `PackageSet` that contains all packages present in both `self` and `other`.
+    ///
+    /// Returns a `MismatchedGraphs` error if the package graphs associated with `self` and
+    /// `other` don't match.
+    pub fn try_intersection(&self, other: &Self) -> Result<Self, Error> {
+        self.check_same_graph(other)?;
         let mut res = self.clone();
         res.core.intersect_with(&other.core);
-        res
+        Ok(res)
     }
 
     /// Returns a `PackageSet` that contains all packages present in `self` but not `other`.
     ///
     /// ## Panics
     ///
-    /// Panics if the package graphs associated with `self` and `other` don't match.
+    /// Panics if the package graphs associated with `self` and `other` don't match. See
+    /// `try_difference` for a version that returns an error instead.
     pub fn difference(&self, other: &Self) -> Self {
-        assert!(
-            ::std::ptr::eq(self.graph, other.graph),
-            "package graphs passed into difference() match"
-        );
-        Self {
+        self.try_difference(other)
+            .unwrap_or_else(|_| panic!("package graphs passed into difference() match"))
+    }
+
+    /// Returns a `PackageSet` that contains all packages present in `self` but not `other`.
+    ///
+    /// Returns a `MismatchedGraphs` error if the package graphs associated with `self` and
+    /// `other` don't match.
+    pub fn try_difference(&self, other: &Self) -> Result<Self, Error> {
+        self.check_same_graph(other)?;
+        Ok(Self {
             graph: self.graph,
             core: self.core.difference(&other.core),
-        }
+        })
     }
 
     /// Returns a `PackageSet` that contains all packages present in exactly one of `self` and
@@ -147,15 +330,98 @@ impl<'g> PackageSet<'g> {
     ///
     /// ## Panics
     ///
-    /// Panics if the package graphs associated with `self` and `other` don't match.
+    /// Panics if the package graphs associated with `self` and `other` don't match. See
+    /// `try_symmetric_difference` for a version that returns an error instead.
     pub fn symmetric_difference(&self, other: &Self) -> Self {
-        assert!(
-            ::std::ptr::eq(self.graph, other.graph),
-            "package graphs passed into symmetric_difference() match"
-        );
+        self.try_symmetric_difference(other)
+            .unwrap_or_else(|_| panic!("package graphs passed into symmetric_difference() match"))
+    }
+
+    /// Returns a `PackageSet` that contains all packages present in exactly one of `self` and
+    /// `other`.
+    ///
+    /// Returns a `MismatchedGraphs` error if the package graphs associated with `self` and
+    /// `other` don't match.
+    pub fn try_symmetric_difference(&self, other: &Self) -> Result<Self, Error> {
+        self.check_same_graph(other)?;
         let mut res = self.clone();
         res.core.symmetric_difference_with(&other.core);
-        res
+        Ok(res)
+    }
+
+    fn check_same_graph(&self, other: &Self) -> Result<(), Error> {
+        if ::std::ptr::eq(self.graph, other.graph) {
+            Ok(())
+        } else {
+            Err(Error::MismatchedGraphs)
+        }
+    }
+
+    /// Removes all packages from this set for which the predicate returns false.
+    ///
+    /// This is a pure set filter, and is cheaper than constructing a new query and intersecting it
+    /// with this set. Note that the remaining packages are not recomputed: a package that was only
+    /// included because it was reachable through a removed package will still be in the set.
+    pub fn retain(&mut self, mut f: impl FnMut(PackageMetadata<'g>) -> bool) {
+        let graph = self.graph;
+        let to_remove: Vec<_> = self
+            .core
+            .included
+            .ones()
+            .filter(|package_ix| {
+                let package_id = &graph.dep_graph[NodeIndex::new(*package_ix)];
+                !f(graph.metadata(package_id).expect("valid package ID"))
+            })
+            .collect();
+        for package_ix in to_remove {
+            self.core.included.set(package_ix, false);
+        }
+        self.core.invalidate_caches();
+    }
+
+    /// Returns a new set containing only the packages for which the predicate returns true.
+    ///
+    /// This is the non-mutating version of `retain`, and the same caveats apply: the remaining
+    /// packages are not recomputed. The predicate is called on packages in the order specified by
+    /// `direction`.
+    pub fn filter(
+        &self,
+        direction: DependencyDirection,
+        mut f: impl FnMut(PackageMetadata<'g>) -> bool,
+    ) -> PackageSet<'g> {
+        let mut included = self.core.included.clone();
+        for package in self.packages(direction) {
+            if !f(package) {
+                included.set(package.package_ix().index(), false);
+            }
+        }
+        Self::from_included(self.graph, included)
+    }
+
+    /// Splits this set into two: workspace members, and all other packages, in that order.
+    ///
+    /// Both sets are associated with the same package graph as this one, so they can be used in
+    /// set operations with each other and with this set.
+    pub fn partition_workspace(&self) -> (PackageSet<'g>, PackageSet<'g>) {
+        let graph = self.graph;
+        let mut workspace = FixedBitSet::with_capacity(self.core.included.len());
+        let mut non_workspace = FixedBitSet::with_capacity(self.core.included.len());
+        for package_ix in self.core.included.ones() {
+            let package_id = &graph.dep_graph[NodeIndex::new(package_ix)];
+            let in_workspace = graph
+                .metadata(package_id)
+                .expect("valid package ID")
+                .in_workspace();
+            if in_workspace {
+                workspace.insert(package_ix);
+            } else {
+                non_workspace.insert(package_ix);
+            }
+        }
+        (
+            Self::from_included(graph, workspace),
+            Self::from_included(graph, non_workspace),
+        )
     }
 
     // ---
@@ -182,6 +448,28 @@ impl<'g> PackageSet<'g> {
         self.core.topo(self.graph.sccs(), direction)
     }
 
+    /// Returns a SHA-256 fingerprint of the package IDs in this set.
+    ///
+    /// Package IDs are sorted lexicographically before being hashed, so the result is
+    /// deterministic and can be compared across runs and machines.
+    ///
+    /// Only package IDs are hashed: two sets with the same packages but different links between
+    /// them will have the same fingerprint.
+    pub fn stable_hash(&self) -> [u8; 32] {
+        let mut reprs: Vec<&str> = self
+            .package_ids(DependencyDirection::Forward)
+            .map(|package_id| package_id.repr())
+            .collect();
+        reprs.sort_unstable();
+
+        let mut hasher = Sha256::new();
+        for repr in reprs {
+            hasher.update(repr.as_bytes());
+            hasher.update(b"\n");
+        }
+        hasher.finalize().into()
+    }
+
     /// Iterates over package metadatas, in topological order in the direction specified.
     ///
     /// ## Cycles
@@ -203,6 +491,84 @@ impl<'g> PackageSet<'g> {
         })
     }
 
+    /// Calls `f` on each package, in topological order in the direction specified, stopping early
+    /// if it returns `ControlFlow::Break`.
+    ///
+    /// This is equivalent to calling `try_for_each` on the iterator returned by `packages`. Returns
+    /// `ControlFlow::Break` if `f` stopped the traversal, and `ControlFlow::Continue` if every
+    /// package was visited.
+    ///
+    /// ## Cycles
+    ///
+    /// The packages within a dependency cycle will be visited in arbitrary order, but overall
+    /// topological order will be maintained.
+    pub fn for_each_package(
+        &self,
+        direction: DependencyDirection,
+        f: impl FnMut(PackageMetadata<'g>) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        self.packages(direction).try_for_each(f)
+    }
+
+    /// Groups the packages in this set into waves, where every dependency of a package in a wave
+    /// is in an earlier wave.
+    ///
+    /// The first wave consists of the packages without any dependencies within this set, the
+    /// second wave of the packages that only depend on packages in the first wave, and so on. The
+    /// packages within a wave don't depend on each other, so a build scheduler can build them in
+    /// parallel. Within each wave, packages are sorted by package ID.
+    ///
+    /// Every link between packages in this set is considered, including dev-only links.
+    ///
+    /// ## Cycles
+    ///
+    /// The packages within a dependency cycle in this set are always in the same wave. That wave's
+    /// dependencies are all in earlier waves, other than the ones within the cycle.
+    pub fn build_waves(&self) -> Vec<Vec<PackageMetadata<'g>>> {
+        let dep_graph = self.graph.dep_graph();
+        let included = &self.core.included;
+        let filtered =
+            NodeFiltered::from_fn(dep_graph, |package_ix| included.is_visited(&package_ix));
+        // Compute SCCs over this set, since a cycle in the full graph might not be a cycle here.
+        let sccs = &Sccs::new(&filtered);
+
+        // SCCs are in reverse topological order, so dependencies are seen before dependents.
+        let mut scc_waves = vec![usize::MAX; dep_graph.node_count()];
+        let mut waves: Vec<Vec<PackageMetadata<'g>>> = vec![];
+        for scc in sccs.iter() {
+            let wave = scc
+                .iter()
+                .flat_map(|&package_ix| {
+                    dep_graph
+                        .neighbors_directed(package_ix, Outgoing)
+                        .filter(move |&dep_ix| {
+                            included.is_visited(&dep_ix) && !sccs.is_same_scc(package_ix, dep_ix)
+                        })
+                })
+                .map(|dep_ix| scc_waves[dep_ix.index()] + 1)
+                .max()
+                .unwrap_or(0);
+            if wave == waves.len() {
+                waves.push(vec![]);
+            }
+            for package_ix in scc {
+                scc_waves[package_ix.index()] = wave;
+                let package_id = &dep_graph[*package_ix];
+                waves[wave].push(self.graph.metadata(package_id).unwrap_or_else(|| {
+                    panic!(
+                        "known package ID '{}' not found in metadata map",
+                        package_id
+                    )
+                }));
+            }
+        }
+
+        for wave in &mut waves {
+            wave.sort_by_key(|package| package.id());
+        }
+        waves
+    }
+
     /// Returns the set of "root package" IDs in the specified direction.
     ///
     /// * If direction is Forward, return the set of packages that do not have any dependencies
@@ -225,6 +591,48 @@ impl<'g> PackageSet<'g> {
             .map(move |package_ix| &dep_graph[package_ix])
     }
 
+    /// Returns the set of "root package" IDs in both directions, as a pair of `(forward, reverse)`.
+    ///
+    /// This is equivalent to calling `root_ids` with `Forward` and `Reverse`, but computes both
+    /// sets in one pass.
+    ///
+    /// A package that is present in both sets is isolated: it has neither dependencies nor
+    /// dependents within the selected graph.
+    ///
+    /// ## Cycles
+    ///
+    /// If a root consists of a dependency cycle, all the packages in it will be returned in
+    /// arbitrary order.
+    pub fn boundary_ids(&self) -> (Vec<&'g PackageId>, Vec<&'g PackageId>) {
+        let dep_graph = &self.graph.dep_graph;
+        let (forward, reverse) = self
+            .core
+            .boundaries(self.graph.dep_graph(), self.graph.sccs());
+        let to_ids = |package_ixs: Vec<NodeIndex<PackageIx>>| {
+            package_ixs
+                .into_iter()
+                .map(|package_ix| &dep_graph[package_ix])
+                .collect()
+        };
+        (to_ids(forward), to_ids(reverse))
+    }
+
+    /// Returns the number of other packages in this set that transitively depend on this package.
+    ///
+    /// Only dependency paths that stay within this set are considered.
+    ///
+    /// Returns `None` if the package ID isn't known to the graph, or isn't in this set.
+    pub fn dependent_count(&self, package_id: &PackageId) -> Option<usize> {
+        let package_ix = self.graph.package_ix(package_id)?;
+        if !self.core.contains(package_ix) {
+            return None;
+        }
+        Some(
+            self.core
+                .dependent_count(self.graph.dep_graph(), package_ix),
+        )
+    }
+
     /// Returns the set of "root package" metadatas in the specified direction.
     ///
     /// * If direction is Forward, return the set of packages that do not have any dependencies
@@ -253,6 +661,8 @@ impl<'g> PackageSet<'g> {
 
     /// Creates an iterator over `PackageLink` instances.
     ///
+    /// The number of links is counted when the iterator is created, so its `len` is exact.
+    ///
     /// If the iteration is in forward order, for any given package, at least one link where the
     /// package is on the `to` end is returned before any links where the package is on the
     /// `from` end.
@@ -267,7 +677,7 @@ impl<'g> PackageSet<'g> {
     pub fn links<'a>(
         &'a self,
         direction: DependencyDirection,
-    ) -> impl Iterator<Item = PackageLink<'g>> + 'a {
+    ) -> impl ExactSizeIterator<Item = PackageLink<'g>> + 'a {
         let graph = self.graph;
         self.core
             .links(graph.dep_graph(), graph.sccs(), direction)
@@ -276,6 +686,173 @@ impl<'g> PackageSet<'g> {
             })
     }
 
+    /// Iterates over all links between packages in this set, in the order specified by `links`,
+    /// along with the depth of the package at the far end of each link.
+    ///
+    /// The far end is `to` for forward iteration and `from` for reverse iteration. Depths are
+    /// measured from the roots of this set in the given direction (see `root_ids`), which have a
+    /// depth of 0. A package reachable through several paths reports its minimum depth.
+    ///
+    /// This is useful for rendering a tree of dependencies indented by distance.
+    pub fn links_with_depth<'a>(
+        &'a self,
+        direction: DependencyDirection,
+    ) -> impl ExactSizeIterator<Item = (PackageLink<'g>, usize)> + 'a {
+        let graph = self.graph;
+        let depths = self.core.depths(graph.dep_graph(), graph.sccs(), direction);
+        self.core
+            .links(graph.dep_graph(), graph.sccs(), direction)
+            .map(move |(source_ix, target_ix, edge_ix)| {
+                let far_ix = match direction {
+                    DependencyDirection::Forward => target_ix,
+                    DependencyDirection::Reverse => source_ix,
+                };
+                let depth = depths[far_ix.index()].expect("every package in the set has a depth");
+                (
+                    graph.edge_to_link(source_ix, target_ix, edge_ix, None),
+                    depth,
+                )
+            })
+    }
+
+    /// Iterates over all links between packages in this set that are present for at least one of
+    /// the given dependency kinds, in the order specified by `links`.
+    ///
+    /// For example, passing in `DependencyKind::Development` returns only the dev-dependency links
+    /// in this set.
+    pub fn links_filtered<'a>(
+        &'a self,
+        direction: DependencyDirection,
+        kinds: impl IntoIterator<Item = DependencyKind>,
+    ) -> impl Iterator<Item = PackageLink<'g>> + 'a {
+        let kinds: Vec<_> = kinds
+            .into_iter()
+            .filter(|kind| {
+                matches!(
+                    kind,
+                    DependencyKind::Normal | DependencyKind::Build | DependencyKind::Development
+                )
+            })
+            .collect();
+        self.links(direction).filter(move |link| {
+            kinds
+                .iter()
+                .any(|kind| link.req_for_kind(*kind).is_present())
+        })
+    }
+
+    /// Returns the packages in this set that are at one end of a link matching the predicate.
+    ///
+    /// Only links between packages in this set are considered.
+    ///
+    /// * If direction is Forward, returns packages that are depended on through a matching link
+    ///   (the `to` end).
+    /// * If direction is Reverse, returns packages that depend on another package through a
+    ///   matching link (the `from` end).
+    ///
+    /// Packages are returned in topological order (in the direction specified), and each package
+    /// is returned at most once.
+    pub fn packages_with_link<'a>(
+        &'a self,
+        direction: DependencyDirection,
+        pred: impl Fn(PackageLink<'g>) -> bool,
+    ) -> impl Iterator<Item = PackageMetadata<'g>> + 'a {
+        let mut matching = FixedBitSet::with_capacity(self.graph.dep_graph().node_count());
+        for link in self.links(direction).filter(|link| pred(*link)) {
+            let package = match direction {
+                DependencyDirection::Forward => link.to(),
+                DependencyDirection::Reverse => link.from(),
+            };
+            matching.insert(package.package_ix().index());
+        }
+        self.packages(direction)
+            .filter(move |package| matching.contains(package.package_ix().index()))
+    }
+
+    /// Returns an `OwnedPackageSet` with the same packages as this set, that doesn't borrow from
+    /// the package graph.
+    ///
+    /// Use `OwnedPackageSet::bind` to convert it back into a `PackageSet`.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_owned(&self) -> OwnedPackageSet {
+        OwnedPackageSet {
+            package_ids: self
+                .package_ids(DependencyDirection::Forward)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Returns a `FeatureSet` consisting of the features of every package in this set, subject to
+    /// the provided filter.
+    ///
+    /// This can be used to find out whether a particular feature of a package in this set is
+    /// enabled. It is the same as `FeatureGraph::resolve_packages`.
+    pub fn to_feature_set(&self, filter: impl FeatureFilter<'g>) -> FeatureSet<'g> {
+        self.graph.feature_graph().resolve_packages(self, filter)
+    }
+
+    /// Returns the package IDs in this set, cloned out in topological order in the direction
+    /// specified.
+    ///
+    /// The result doesn't borrow from the package graph. Use `PackageGraph::resolve_ids` to convert
+    /// it back into a `PackageSet`.
+    pub fn to_owned_ids(&self, direction: DependencyDirection) -> Vec<PackageId> {
+        self.package_ids(direction).cloned().collect()
+    }
+
+    /// Returns a stable, lockfile-like summary of the packages in this set.
+    ///
+    /// The summary has one line per package, of the form `name version source` (the source is
+    /// omitted for packages at local paths). Lines are sorted, so the output is deterministic
+    /// and suitable for diffing resolutions across runs.
+    pub fn to_lock_summary(&self) -> String {
+        let mut lines: Vec<_> = self
+            .packages(DependencyDirection::Forward)
+            .map(|package| match package.source() {
+                Some(source) => format!("{} {} {}\n", package.name(), package.version(), source),
+                None => format!("{} {}\n", package.name(), package.version()),
+            })
+            .collect();
+        lines.sort();
+        lines.concat()
+    }
+
+    /// Estimates the amount of source code in this set by summing up the on-disk sizes of the
+    /// directories containing each package's `Cargo.toml`.
+    ///
+    /// Packages whose directories aren't present locally, such as git dependencies that haven't
+    /// been checked out, are skipped and returned through `SourceSize::missing`.
+    ///
+    /// Within each package directory, the `target` directory and any subdirectories containing
+    /// their own `Cargo.toml` (which are separate packages) aren't counted. Symbolic links aren't
+    /// followed.
+    ///
+    /// This is meant as a pragmatic measure of how heavy a set of dependencies is, e.g. to
+    /// prioritize what to audit. Returns an error if a directory couldn't be read.
+    pub fn total_source_size(&self) -> io::Result<SourceSize<'g>> {
+        let mut bytes = 0;
+        let mut missing = vec![];
+        for package in self.packages(DependencyDirection::Forward) {
+            let dir = match package.manifest_path().parent() {
+                Some(dir) => dir,
+                None => {
+                    missing.push(package);
+                    continue;
+                }
+            };
+            match fs::symlink_metadata(dir) {
+                Ok(metadata) if metadata.is_dir() => {
+                    bytes += dir_size(dir, true)?;
+                }
+                Ok(_) => missing.push(package),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => missing.push(package),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(SourceSize { bytes, missing })
+    }
+
     /// Constructs a representation of the selected packages in `dot` format.
     pub fn display_dot<'a, V: PackageDotVisitor + 'g>(
         &'a self,
@@ -287,6 +864,203 @@ impl<'g> PackageSet<'g> {
         });
         DotFmt::new(node_filtered, VisitorWrap::new(self.graph, visitor))
     }
+
+    /// Constructs a representation of the selected packages in GraphML format.
+    ///
+    /// Each node has `id`, `name` and `version` attributes, and each edge has boolean `normal`,
+    /// `build` and `dev` attributes indicating the kinds of the dependency. GraphML is understood
+    /// by many graph analysis tools, such as Gephi, yEd and networkx.
+    pub fn display_graphml<'a>(&'a self) -> impl fmt::Display + 'a {
+        GraphMlFmt { package_set: self }
+    }
+}
+
+struct GraphMlFmt<'a, 'g> {
+    package_set: &'a PackageSet<'g>,
+}
+
+impl<'a, 'g> fmt::Display for GraphMlFmt<'a, 'g> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            f,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        for (key, domain, name, ty) in &[
+            ("d0", "node", "id", "string"),
+            ("d1", "node", "name", "string"),
+            ("d2", "node", "version", "string"),
+            ("d3", "edge", "normal", "boolean"),
+            ("d4", "edge", "build", "boolean"),
+            ("d5", "edge", "dev", "boolean"),
+        ] {
+            writeln!(
+                f,
+                r#"  <key id="{}" for="{}" attr.name="{}" attr.type="{}"/>"#,
+                key, domain, name, ty
+            )?;
+        }
+        writeln!(f, r#"  <graph id="G" edgedefault="directed">"#)?;
+
+        for package in self.package_set.packages(DependencyDirection::Forward) {
+            writeln!(f, r#"    <node id="n{}">"#, package.package_ix().index())?;
+            writeln!(
+                f,
+                r#"      <data key="d0">{}</data>"#,
+                XmlEscape(package.id().repr())
+            )?;
+            writeln!(
+                f,
+                r#"      <data key="d1">{}</data>"#,
+                XmlEscape(package.name())
+            )?;
+            writeln!(
+                f,
+                r#"      <data key="d2">{}</data>"#,
+                XmlEscape(&package.version().to_string())
+            )?;
+            writeln!(f, "    </node>")?;
+        }
+
+        for link in self.package_set.links(DependencyDirection::Forward) {
+            writeln!(
+                f,
+                r#"    <edge id="e{}" source="n{}" target="n{}">"#,
+                link.edge_ix().index(),
+                link.from().package_ix().index(),
+                link.to().package_ix().index()
+            )?;
+            for (key, req) in &[
+                ("d3", link.normal()),
+                ("d4", link.build()),
+                ("d5", link.dev()),
+            ] {
+                writeln!(
+                    f,
+                    r#"      <data key="{}">{}</data>"#,
+                    key,
+                    req.is_present()
+                )?;
+            }
+            writeln!(f, "    </edge>")?;
+        }
+
+        writeln!(f, "  </graph>")?;
+        writeln!(f, "</graphml>")
+    }
+}
+
+/// Escapes a string for use in XML character data or attribute values.
+struct XmlEscape<'a>(&'a str);
+
+impl<'a> fmt::Display for XmlEscape<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '&' => f.write_str("&amp;")?,
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '"' => f.write_str("&quot;")?,
+                '\'' => f.write_str("&apos;")?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Iterates over package metadatas in forward topological order, consuming the set.
+///
+/// This is equivalent to `packages(DependencyDirection::Forward)`. The packages within a dependency
+/// cycle will be returned in arbitrary order, but overall topological order will be maintained.
+impl<'g> IntoIterator for PackageSet<'g> {
+    type Item = PackageMetadata<'g>;
+    type IntoIter = std::vec::IntoIter<PackageMetadata<'g>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        // The topo iterator borrows from the set, so collect the packages first.
+        let packages: Vec<_> = self.packages(DependencyDirection::Forward).collect();
+        packages.into_iter()
+    }
+}
+
+/// Returns the total size of the files in this directory, recursively.
+fn dir_size(dir: &Path, package_root: bool) -> io::Result<u64> {
+    let mut bytes = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            let path = entry.path();
+            if (package_root && entry.file_name() == "target") || path.join("Cargo.toml").exists() {
+                continue;
+            }
+            bytes += dir_size(&path, false)?;
+        } else if file_type.is_file() {
+            bytes += entry.metadata()?.len();
+        }
+    }
+    Ok(bytes)
+}
+
+/// An estimate of the amount of source code in a `PackageSet`.
+///
+/// Returned by `PackageSet::total_source_size`.
+#[derive(Clone, Debug)]
+pub struct SourceSize<'g> {
+    bytes: u64,
+    missing: Vec<PackageMetadata<'g>>,
+}
+
+impl<'g> SourceSize<'g> {
+    /// Returns the total size in bytes of the packages that are present locally.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Returns the packages that were skipped because their sources aren't present locally, in
+    /// forward topological order.
+    pub fn missing(&self) -> &[PackageMetadata<'g>] {
+        &self.missing
+    }
+}
+
+/// A set of package IDs that doesn't borrow from a package graph.
+///
+/// Created by `PackageSet::to_owned`. This is useful for storing a resolved set alongside the
+/// package graph it was created from.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OwnedPackageSet {
+    package_ids: BTreeSet<PackageId>,
+}
+
+impl OwnedPackageSet {
+    /// Returns the number of packages in this set.
+    pub fn len(&self) -> usize {
+        self.package_ids.len()
+    }
+
+    /// Returns true if there are no packages in this set.
+    pub fn is_empty(&self) -> bool {
+        self.package_ids.is_empty()
+    }
+
+    /// Returns true if this set contains the given package ID.
+    pub fn contains(&self, package_id: &PackageId) -> bool {
+        self.package_ids.contains(package_id)
+    }
+
+    /// Iterates over the package IDs in this set, in sorted order.
+    pub fn package_ids<'a>(&'a self) -> impl ExactSizeIterator<Item = &'a PackageId> + 'a {
+        self.package_ids.iter()
+    }
+
+    /// Converts this set back into a `PackageSet` over the given package graph.
+    ///
+    /// Returns an error if any of the package IDs in this set aren't known to the graph.
+    pub fn bind<'g>(&self, graph: &'g PackageGraph) -> Result<PackageSet<'g>, Error> {
+        graph.resolve_ids(self.package_ids.iter().cloned())
+    }
 }
 
 /// Represents whether a particular link within a package graph should be followed during a
@@ -331,6 +1105,183 @@ where
     }
 }
 
+/// A `PackageResolver` that records the decisions made by another resolver.
+///
+/// This is useful for debugging custom resolvers: pass in a mutable reference to a
+/// `RecordingResolver` to `PackageQuery::resolve_with`, then look at the decisions afterwards to
+/// see why a package did or didn't appear in the resulting set.
+#[derive(Clone, Debug)]
+pub struct RecordingResolver<'g, R> {
+    inner: R,
+    decisions: Vec<(&'g PackageId, &'g PackageId, bool)>,
+}
+
+impl<'g, R> RecordingResolver<'g, R>
+where
+    R: PackageResolver<'g>,
+{
+    /// Creates a new `RecordingResolver` that delegates to the given resolver.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            decisions: vec![],
+        }
+    }
+
+    /// Returns the decisions made so far as `(from, to, accepted)` triples, in the order they
+    /// were made.
+    pub fn decisions(&self) -> &[(&'g PackageId, &'g PackageId, bool)] {
+        &self.decisions
+    }
+
+    /// Returns the links rejected so far as `(from, to)` pairs, in the order they were rejected.
+    pub fn rejected<'a>(&'a self) -> impl Iterator<Item = (&'g PackageId, &'g PackageId)> + 'a {
+        self.decisions
+            .iter()
+            .filter(|(_, _, accepted)| !accepted)
+            .map(|(from, to, _)| (*from, *to))
+    }
+
+    /// Consumes this resolver, returning the inner resolver and the decisions it made.
+    pub fn into_inner(self) -> (R, Vec<(&'g PackageId, &'g PackageId, bool)>) {
+        (self.inner, self.decisions)
+    }
+}
+
+impl<'g, R> PackageResolver<'g> for RecordingResolver<'g, R>
+where
+    R: PackageResolver<'g>,
+{
+    fn accept(&mut self, query: &PackageQuery<'g>, link: PackageLink<'g>) -> bool {
+        let accepted = self.inner.accept(query, link);
+        self.decisions
+            .push((link.from().id(), link.to().id(), accepted));
+        accepted
+    }
+}
+
+/// A `PackageResolver` that follows a link only if all of its resolvers accept it.
+///
+/// Resolvers are consulted in order, stopping at the first one that rejects the link. An
+/// `AndResolver` with no resolvers accepts every link.
+pub struct AndResolver<'g, 'a> {
+    resolvers: Vec<Box<dyn PackageResolver<'g> + 'a>>,
+}
+
+impl<'g, 'a> AndResolver<'g, 'a> {
+    /// Creates a new `AndResolver` from the given resolvers.
+    pub fn new(resolvers: impl IntoIterator<Item = Box<dyn PackageResolver<'g> + 'a>>) -> Self {
+        Self {
+            resolvers: resolvers.into_iter().collect(),
+        }
+    }
+}
+
+impl<'g, 'a> PackageResolver<'g> for AndResolver<'g, 'a> {
+    fn accept(&mut self, query: &PackageQuery<'g>, link: PackageLink<'g>) -> bool {
+        self.resolvers
+            .iter_mut()
+            .all(|resolver| resolver.accept(query, link))
+    }
+}
+
+/// A `PackageResolver` that follows a link if any of its resolvers accept it.
+///
+/// Resolvers are consulted in order, stopping at the first one that accepts the link. An
+/// `OrResolver` with no resolvers rejects every link.
+pub struct OrResolver<'g, 'a> {
+    resolvers: Vec<Box<dyn PackageResolver<'g> + 'a>>,
+}
+
+impl<'g, 'a> OrResolver<'g, 'a> {
+    /// Creates a new `OrResolver` from the given resolvers.
+    pub fn new(resolvers: impl IntoIterator<Item = Box<dyn PackageResolver<'g> + 'a>>) -> Self {
+        Self {
+            resolvers: resolvers.into_iter().collect(),
+        }
+    }
+}
+
+impl<'g, 'a> PackageResolver<'g> for OrResolver<'g, 'a> {
+    fn accept(&mut self, query: &PackageQuery<'g>, link: PackageLink<'g>) -> bool {
+        self.resolvers
+            .iter_mut()
+            .any(|resolver| resolver.accept(query, link))
+    }
+}
+
+/// A `PackageResolver` that doesn't follow links into versions of a crate outside a semver range.
+///
+/// This is useful for estimating the impact of pinning a crate, e.g. to see what the dependency
+/// graph would look like if `foo` were restricted to `<2.0`.
+///
+/// ## Limitations
+///
+/// guppy operates on a graph that Cargo has already resolved, so this resolver doesn't pick
+/// other versions of the crate. Links into disallowed versions are simply not followed, which
+/// prunes away anything that's only reachable through them. Dependents of the crate are still
+/// included even though, with the pin in place, Cargo might fail to resolve them or pick a
+/// different version for them.
+///
+/// Only links are checked: if a disallowed version is one of the initial packages of a query, it
+/// is still included.
+#[derive(Clone, Debug)]
+pub struct VersionRangeResolver {
+    name: String,
+    req: VersionReq,
+}
+
+impl VersionRangeResolver {
+    /// Creates a new `VersionRangeResolver` that only follows links into the crate with this name
+    /// if its version matches `req`.
+    pub fn new(name: impl Into<String>, req: VersionReq) -> Self {
+        Self {
+            name: name.into(),
+            req,
+        }
+    }
+
+    /// Returns the name of the crate this resolver restricts.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the range of versions allowed for this crate.
+    pub fn req(&self) -> &VersionReq {
+        &self.req
+    }
+}
+
+impl<'g> PackageResolver<'g> for VersionRangeResolver {
+    fn accept(&mut self, _query: &PackageQuery<'g>, link: PackageLink<'g>) -> bool {
+        let to = link.to();
+        to.name() != self.name || cargo_version_matches(&self.req, to.version())
+    }
+}
+
+/// The state of a resolve operation that's in progress.
+///
+/// Passed into the resolver function in `PackageQuery::resolve_with_state_fn`.
+#[derive(Clone, Copy, Debug)]
+pub struct ResolveState<'a, 'g> {
+    graph: &'g PackageGraph,
+    reached: &'a FixedBitSet,
+}
+
+impl<'a, 'g> ResolveState<'a, 'g> {
+    /// Returns true if this package has already been reached by the resolve, false if it hasn't,
+    /// and None if the package ID wasn't found.
+    ///
+    /// A package has been reached if it is one of the initial packages in the query, or if it is
+    /// at the far end of a link that was accepted earlier.
+    pub fn contains(&self, package_id: &PackageId) -> Option<bool> {
+        Some(
+            self.reached
+                .contains(self.graph.package_ix(package_id)?.index()),
+        )
+    }
+}
+
 /// A visitor used for formatting `dot` graphs.
 pub trait PackageDotVisitor {
     /// Visits this package. The implementation may output a label for this package to the given
@@ -340,6 +1291,44 @@ pub trait PackageDotVisitor {
     /// Visits this dependency link. The implementation may output a label for this link to the
     /// given `DotWrite`.
     fn visit_link(&self, link: PackageLink<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result;
+
+    /// Returns the color this dependency link should be drawn in, if any.
+    ///
+    /// The color may be any color name or value that graphviz understands. Returns `None` by
+    /// default.
+    fn link_color(&self, _link: PackageLink<'_>) -> Option<&'static str> {
+        None
+    }
+}
+
+/// A `PackageDotVisitor` that labels packages with their names, and colors links by dependency
+/// kind.
+///
+/// Links are labeled with the name the dependency is imported as. They are colored:
+/// * green if they are normal dependencies
+/// * blue if they are build dependencies (and not normal dependencies)
+/// * red if they are dev-only dependencies
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ColoredDotVisitor;
+
+impl PackageDotVisitor for ColoredDotVisitor {
+    fn visit_package(&self, package: PackageMetadata<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        write!(f, "{}", package.name())
+    }
+
+    fn visit_link(&self, link: PackageLink<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        write!(f, "{}", link.dep_name())
+    }
+
+    fn link_color(&self, link: PackageLink<'_>) -> Option<&'static str> {
+        if link.normal().is_present() {
+            Some("green")
+        } else if link.build().is_present() {
+            Some("blue")
+        } else {
+            Some("red")
+        }
+    }
 }
 
 struct VisitorWrap<'g, V> {
@@ -378,4 +1367,12 @@ where
             .edge_to_link(source_ix, target_ix, edge.id(), Some(edge.weight()));
         self.inner.visit_link(link, f)
     }
+
+    fn edge_color(&self, edge: ER) -> Option<&'static str> {
+        let (source_ix, target_ix) = edge.original_endpoints();
+        let link = self
+            .graph
+            .edge_to_link(source_ix, target_ix, edge.id(), Some(edge.weight()));
+        self.inner.link_color(link)
+    }
 }
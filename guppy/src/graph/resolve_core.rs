@@ -2,7 +2,10 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use crate::debug_ignore::DebugIgnore;
-use crate::graph::query_core::{all_visit_map, reachable_map, reachable_map_filtered, QueryParams};
+use crate::graph::query_core::{
+    all_visit_map, bfs_depths, reachable_map, reachable_map_depth, reachable_map_filtered,
+    QueryParams,
+};
 use crate::graph::{DependencyDirection, GraphSpec};
 use crate::petgraph_support::scc::{NodeIter, Sccs};
 use crate::petgraph_support::walk::EdgeDfs;
@@ -11,6 +14,8 @@ use petgraph::prelude::*;
 use petgraph::visit::{NodeFiltered, Reversed, VisitMap};
 use serde::export::PhantomData;
 
+type RootIxs<Ix> = Vec<NodeIndex<Ix>>;
+
 /// Core logic for queries that have been resolved into a known set of packages.
 ///
 /// The `G` param ensures that package and feature resolutions aren't mixed up accidentally.
@@ -37,6 +42,27 @@ impl<G: GraphSpec> ResolveCore<G> {
         }
     }
 
+    /// Only includes nodes that are at most `max_depth` links away from the initials.
+    pub(super) fn with_max_depth(
+        graph: &Graph<G::Node, G::Edge, Directed, G::Ix>,
+        params: QueryParams<G>,
+        max_depth: usize,
+    ) -> Self {
+        let (included, len) = match params {
+            QueryParams::Forward(initials) => {
+                reachable_map_depth(graph, initials.into_inner(), max_depth)
+            }
+            QueryParams::Reverse(initials) => {
+                reachable_map_depth(Reversed(graph), initials.into_inner(), max_depth)
+            }
+        };
+        Self {
+            included,
+            len,
+            _phantom: PhantomData,
+        }
+    }
+
     pub(super) fn all_nodes(graph: &Graph<G::Node, G::Edge, Directed, G::Ix>) -> Self {
         let (included, len) = all_visit_map(graph);
         Self {
@@ -92,6 +118,25 @@ impl<G: GraphSpec> ResolveCore<G> {
         self.included.is_visited(&ix)
     }
 
+    /// Adds a node to this set, returning true if it wasn't already present.
+    pub(super) fn insert(&mut self, ix: NodeIndex<G::Ix>) -> bool {
+        let already_present = self.included.put(ix.index());
+        if !already_present {
+            self.len += 1;
+        }
+        !already_present
+    }
+
+    /// Removes a node from this set, returning true if it was present.
+    pub(super) fn remove(&mut self, ix: NodeIndex<G::Ix>) -> bool {
+        let was_present = self.included.contains(ix.index());
+        if was_present {
+            self.included.set(ix.index(), false);
+            self.len -= 1;
+        }
+        was_present
+    }
+
     // ---
     // Set operations
     // ---
@@ -147,6 +192,54 @@ impl<G: GraphSpec> ResolveCore<G> {
         }
     }
 
+    /// Returns the root metadatas in both the forward and reverse directions, in that order.
+    ///
+    /// This shares the filtered view of the graph across both directions.
+    pub(super) fn boundaries(
+        &self,
+        graph: &Graph<G::Node, G::Edge, Directed, G::Ix>,
+        sccs: &Sccs<G::Ix>,
+    ) -> (RootIxs<G::Ix>, RootIxs<G::Ix>) {
+        let filtered = NodeFiltered::from_fn(graph, |x| self.included.is_visited(&x));
+        let forward = sccs.externals(&filtered).collect();
+        let reverse = sccs.externals(Reversed(&filtered)).collect();
+        (forward, reverse)
+    }
+
+    /// Returns the minimum depth of every node in this set from the roots in the specified
+    /// direction, indexed by node index. Nodes that aren't in this set have a depth of `None`.
+    pub(super) fn depths(
+        &self,
+        graph: &Graph<G::Node, G::Edge, Directed, G::Ix>,
+        sccs: &Sccs<G::Ix>,
+        direction: DependencyDirection,
+    ) -> Vec<Option<usize>> {
+        let roots = self.roots(graph, sccs, direction);
+        let filtered = NodeFiltered::from_fn(graph, |x| self.included.is_visited(&x));
+        match direction {
+            DependencyDirection::Forward => bfs_depths(&filtered, roots, graph.node_count()),
+            DependencyDirection::Reverse => {
+                bfs_depths(Reversed(&filtered), roots, graph.node_count())
+            }
+        }
+    }
+
+    /// Returns the number of nodes in this set that transitively depend on `ix`, not counting
+    /// `ix` itself. Only paths that stay within this set are followed.
+    pub(super) fn dependent_count(
+        &self,
+        graph: &Graph<G::Node, G::Edge, Directed, G::Ix>,
+        ix: NodeIndex<G::Ix>,
+    ) -> usize {
+        // In the reversed graph, the target of an edge is the dependent.
+        let (_, len) = reachable_map_filtered(
+            Reversed(graph),
+            |edge_ref| self.included.is_visited(&edge_ref.target()),
+            vec![ix],
+        );
+        len - 1
+    }
+
     pub(super) fn topo<'g>(
         &'g self,
         sccs: &'g Sccs<G::Ix>,
@@ -178,12 +271,36 @@ impl<G: GraphSpec> ResolveCore<G> {
         }
     }
 
+    /// Returns true if this set contains some, but not all, of the nodes of an SCC.
+    fn splits_sccs(&self, sccs: &Sccs<G::Ix>) -> bool {
+        sccs.multi_sccs().any(|scc| {
+            let included = scc
+                .iter()
+                .filter(|ix| self.included.is_visited(*ix))
+                .count();
+            included != 0 && included != scc.len()
+        })
+    }
+
     pub(super) fn links<'g>(
         &'g self,
         graph: &'g Graph<G::Node, G::Edge, Directed, G::Ix>,
         sccs: &Sccs<G::Ix>,
         direction: DependencyDirection,
     ) -> Links<'g, G> {
+        // Start points are computed from the SCCs, so the SCCs of the full graph can only be used
+        // if this set doesn't contain just part of one of them. Otherwise, compute the SCCs of the
+        // subgraph induced by this set.
+        let induced_sccs;
+        let sccs = if self.splits_sccs(sccs) {
+            induced_sccs = Sccs::new(&NodeFiltered::from_fn(graph, |x| {
+                self.included.is_visited(&x)
+            }));
+            &induced_sccs
+        } else {
+            sccs
+        };
+
         let edge_dfs = match direction {
             DependencyDirection::Forward => {
                 let filtered_graph = NodeFiltered::from_fn(graph, |x| self.included.is_visited(&x));
@@ -199,11 +316,24 @@ impl<G: GraphSpec> ResolveCore<G> {
             }
         };
 
+        // Every link between included nodes is visited exactly once, so count them up front.
+        let remaining = self
+            .included
+            .ones()
+            .map(|ix| {
+                graph
+                    .edges(NodeIndex::new(ix))
+                    .filter(|edge| self.included.is_visited(&edge.target()))
+                    .count()
+            })
+            .sum();
+
         Links {
             graph: DebugIgnore(graph),
             included: &self.included,
             edge_dfs,
             direction,
+            remaining,
         }
     }
 }
@@ -249,6 +379,7 @@ pub(super) struct Links<'g, G: GraphSpec> {
     included: &'g FixedBitSet,
     edge_dfs: EdgeDfs<EdgeIndex<G::Ix>, NodeIndex<G::Ix>, FixedBitSet>,
     direction: DependencyDirection,
+    remaining: usize,
 }
 
 impl<'g, G: GraphSpec> Iterator for Links<'g, G> {
@@ -256,7 +387,7 @@ impl<'g, G: GraphSpec> Iterator for Links<'g, G> {
     type Item = (NodeIndex<G::Ix>, NodeIndex<G::Ix>, EdgeIndex<G::Ix>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.direction {
+        let next = match self.direction {
             DependencyDirection::Forward => {
                 let included = self.included;
                 let filtered =
@@ -277,6 +408,20 @@ impl<'g, G: GraphSpec> Iterator for Links<'g, G> {
                         (target_ix, source_ix, edge_ix)
                     })
             }
+        };
+        if next.is_some() {
+            self.remaining -= 1;
         }
+        next
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'g, G: GraphSpec> ExactSizeIterator for Links<'g, G> {
+    fn len(&self) -> usize {
+        self.remaining
     }
 }
@@ -0,0 +1,353 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::graph::{
+    kind_str, BuildTargetImpl, BuildTargetKindImpl, DepRequiredOrOptional, OwnedBuildTargetId,
+    PackageGraph, PackageIx, PackageLinkImpl, PackageMetadataImpl, PlatformStatusImpl,
+};
+use crate::{DependencyKind, Error, JsonValue, Metadata};
+use petgraph::graph::EdgeReference;
+use petgraph::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// The version of the summary format produced by `PackageGraph::to_summary`.
+///
+/// This is bumped every time the format changes in a way that `PackageGraph::from_summary` needs
+/// to be aware of.
+pub const GRAPH_SUMMARY_VERSION: u32 = 1;
+
+impl PackageGraph {
+    /// Serializes the structure of this graph into a compact, versioned JSON summary.
+    ///
+    /// The summary is an object with the following fields:
+    ///
+    /// * `summary_version`: currently `1`. See `GRAPH_SUMMARY_VERSION`.
+    /// * `metadata`: a trimmed version of the `cargo metadata` output this graph was built from.
+    ///   It contains every package with its ID, name, version, source, manifest path, edition,
+    ///   `links` key, named features, dependencies and build targets, along with the workspace
+    ///   and the resolved dependency graph.
+    ///
+    /// Other package metadata, such as authors, descriptions and licenses, isn't included. The
+    /// output is deterministic, so it's suitable for checking into a repository.
+    ///
+    /// Use `PackageGraph::from_summary` to rebuild the graph without invoking Cargo.
+    pub fn to_summary(&self) -> Result<String, Error> {
+        let mut packages = Vec::with_capacity(self.data.packages.len());
+        let mut nodes = Vec::with_capacity(self.data.packages.len());
+        for package in self.data.packages.values() {
+            // The resolved dependencies are written out from the graph rather than the original
+            // metadata, so that changes like retain_edges are preserved. Sort them by package ID
+            // so that the output doesn't depend on the order edges were added in.
+            let mut edges: Vec<_> = self
+                .dep_graph
+                .edges_directed(package.package_ix, Outgoing)
+                .collect();
+            edges.sort_by_key(|edge| &self.dep_graph[edge.target()]);
+
+            nodes.push(SummaryNode {
+                id: package.id.repr(),
+                deps: edges
+                    .iter()
+                    .map(|edge| SummaryNodeDep {
+                        name: &edge.weight().resolved_name,
+                        pkg: self.dep_graph[edge.target()].repr(),
+                    })
+                    .collect(),
+                dependencies: edges
+                    .iter()
+                    .map(|edge| self.dep_graph[edge.target()].repr())
+                    .collect(),
+                features: &package.resolved_features,
+            });
+            packages.push(self.summary_package(package, &edges));
+        }
+        let workspace_root = self.workspace().root();
+
+        serde_json::to_string(&GraphSummary {
+            summary_version: GRAPH_SUMMARY_VERSION,
+            metadata: SummaryMetadata {
+                packages,
+                workspace_members: self.workspace().member_ids().map(|id| id.repr()).collect(),
+                resolve: SummaryResolve { nodes },
+                workspace_root,
+                // The target directory isn't used by guppy, so it isn't tracked.
+                target_directory: workspace_root.join("target"),
+                version: 1,
+            },
+        })
+        .map_err(Error::JsonSerializeError)
+    }
+
+    /// Rebuilds a graph from a summary produced by `PackageGraph::to_summary`.
+    ///
+    /// Returns a `PackageGraphConstructError` if the summary was produced by an incompatible
+    /// version of `guppy`.
+    pub fn from_summary(summary: impl AsRef<str>) -> Result<Self, Error> {
+        let summary: GraphSummaryIn =
+            serde_json::from_str(summary.as_ref()).map_err(Error::MetadataParseError)?;
+        if summary.summary_version != GRAPH_SUMMARY_VERSION {
+            return Err(Error::PackageGraphConstructError(format!(
+                "unsupported summary version {} (expected {})",
+                summary.summary_version, GRAPH_SUMMARY_VERSION
+            )));
+        }
+        let metadata: Metadata =
+            serde_json::from_value(summary.metadata).map_err(Error::MetadataParseError)?;
+        Self::new(metadata)
+    }
+
+    fn summary_package<'g>(
+        &'g self,
+        package: &'g PackageMetadataImpl,
+        edges: &[EdgeReference<'g, PackageLinkImpl, PackageIx>],
+    ) -> SummaryPackage<'g> {
+        let mut features = BTreeMap::new();
+        let mut optional_deps = BTreeSet::new();
+        for (feature, deps) in &package.features {
+            match deps {
+                Some(deps) => {
+                    features.insert(feature.as_ref(), deps.as_slice());
+                }
+                None => {
+                    optional_deps.insert(feature.as_ref());
+                }
+            }
+        }
+
+        let mut dependencies = vec![];
+        for edge in edges {
+            let link = edge.weight();
+            let name = self.data.packages[&self.dep_graph[edge.target()]]
+                .name
+                .as_str();
+            let rename = if link.dep_name != name {
+                Some(link.dep_name.as_str())
+            } else {
+                None
+            };
+            let req = link.version_req.to_string();
+            for (kind, req_impl) in &[
+                (DependencyKind::Normal, &link.normal),
+                (DependencyKind::Build, &link.build),
+                (DependencyKind::Development, &link.dev),
+            ] {
+                for (optional, instances) in
+                    &[(false, &req_impl.required), (true, &req_impl.optional)]
+                {
+                    let dep = SummaryDependency {
+                        name,
+                        rename,
+                        req: req.clone(),
+                        kind: kind_str(*kind),
+                        optional: *optional,
+                        uses_default_features: false,
+                        features: vec![],
+                        target: None,
+                    };
+                    push_instances(&mut dependencies, dep, instances);
+                }
+            }
+            optional_deps.remove(link.dep_name.as_str());
+        }
+        // Optional dependencies that weren't resolved aren't in the graph, but they're still
+        // features of this package.
+        for name in optional_deps {
+            dependencies.push(SummaryDependency {
+                name,
+                rename: None,
+                req: "*".to_string(),
+                kind: kind_str(DependencyKind::Normal),
+                optional: true,
+                uses_default_features: true,
+                features: vec![],
+                target: None,
+            });
+        }
+
+        SummaryPackage {
+            id: package.id.repr(),
+            name: &package.name,
+            version: package.version.to_string(),
+            source: package.source.as_deref(),
+            manifest_path: &package.manifest_path,
+            edition: &package.edition,
+            links: package.links.as_deref(),
+            features,
+            dependencies,
+            targets: package
+                .build_targets
+                .iter()
+                .map(|(id, target)| summary_target(id, target))
+                .collect(),
+        }
+    }
+}
+
+/// Adds one dependency instance per target that `instances` is enabled for.
+///
+/// Instances in `Cargo.toml` are unioned together while the graph is built, so this reconstructs
+/// an equivalent set of instances rather than the original ones.
+fn push_instances<'g>(
+    dependencies: &mut Vec<SummaryDependency<'g>>,
+    dep: SummaryDependency<'g>,
+    instances: &'g DepRequiredOrOptional,
+) {
+    fn add_targets(targets: &mut BTreeSet<Option<String>>, status: &PlatformStatusImpl) {
+        match status {
+            PlatformStatusImpl::Always => {
+                targets.insert(None);
+            }
+            PlatformStatusImpl::Specs(specs) => {
+                targets.extend(specs.iter().map(|spec| Some(spec.to_string())));
+            }
+        }
+    }
+
+    fn contains(status: &PlatformStatusImpl, target: Option<&str>) -> bool {
+        match (status, target) {
+            (PlatformStatusImpl::Always, _) => true,
+            (PlatformStatusImpl::Specs(specs), Some(target)) => {
+                specs.iter().any(|spec| spec.to_string() == target)
+            }
+            (PlatformStatusImpl::Specs(_), None) => false,
+        }
+    }
+
+    let mut targets = BTreeSet::new();
+    add_targets(&mut targets, &instances.build_if);
+    add_targets(&mut targets, &instances.default_features_if);
+    for status in instances.feature_targets.values() {
+        add_targets(&mut targets, status);
+    }
+
+    for target in targets {
+        let target_ref = target.as_deref();
+        dependencies.push(SummaryDependency {
+            uses_default_features: contains(&instances.default_features_if, target_ref),
+            features: instances
+                .feature_targets
+                .iter()
+                .filter(|(_, status)| contains(status, target_ref))
+                .map(|(feature, _)| feature.as_str())
+                .collect(),
+            target,
+            ..dep.clone()
+        });
+    }
+}
+
+fn summary_target<'g>(
+    id: &'g OwnedBuildTargetId,
+    target: &'g BuildTargetImpl,
+) -> SummaryTarget<'g> {
+    let crate_types: Vec<&str> = match &target.kind {
+        BuildTargetKindImpl::LibraryOrExample(crate_types) => {
+            crate_types.iter().map(|s| s.as_str()).collect()
+        }
+        BuildTargetKindImpl::ProcMacro => vec!["proc-macro"],
+        BuildTargetKindImpl::Binary => vec!["bin"],
+    };
+    let lib_name = target.lib_name.as_deref().unwrap_or("");
+    let (name, kind) = match id {
+        // Library targets are identified by their crate types.
+        OwnedBuildTargetId::Library => (lib_name, crate_types.clone()),
+        OwnedBuildTargetId::BuildScript => (lib_name, vec!["custom-build"]),
+        OwnedBuildTargetId::Binary(name) => (name.as_ref(), vec!["bin"]),
+        OwnedBuildTargetId::Example(name) => (name.as_ref(), vec!["example"]),
+        OwnedBuildTargetId::Test(name) => (name.as_ref(), vec!["test"]),
+        OwnedBuildTargetId::Benchmark(name) => (name.as_ref(), vec!["bench"]),
+    };
+
+    SummaryTarget {
+        name,
+        kind,
+        crate_types,
+        required_features: &target.required_features,
+        src_path: &target.path,
+        edition: &target.edition,
+        doctest: target.doc_tests,
+    }
+}
+
+// The structs below mirror the parts of the `cargo metadata` format that guppy reads.
+
+#[derive(Serialize)]
+struct GraphSummary<'g> {
+    summary_version: u32,
+    metadata: SummaryMetadata<'g>,
+}
+
+#[derive(Deserialize)]
+struct GraphSummaryIn {
+    summary_version: u32,
+    metadata: JsonValue,
+}
+
+#[derive(Serialize)]
+struct SummaryMetadata<'g> {
+    packages: Vec<SummaryPackage<'g>>,
+    workspace_members: Vec<&'g str>,
+    resolve: SummaryResolve<'g>,
+    workspace_root: &'g Path,
+    target_directory: PathBuf,
+    version: u32,
+}
+
+#[derive(Serialize)]
+struct SummaryPackage<'g> {
+    id: &'g str,
+    name: &'g str,
+    version: String,
+    source: Option<&'g str>,
+    manifest_path: &'g Path,
+    edition: &'g str,
+    links: Option<&'g str>,
+    features: BTreeMap<&'g str, &'g [String]>,
+    dependencies: Vec<SummaryDependency<'g>>,
+    targets: Vec<SummaryTarget<'g>>,
+}
+
+#[derive(Clone, Serialize)]
+struct SummaryDependency<'g> {
+    name: &'g str,
+    rename: Option<&'g str>,
+    req: String,
+    kind: &'static str,
+    optional: bool,
+    uses_default_features: bool,
+    features: Vec<&'g str>,
+    target: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SummaryTarget<'g> {
+    name: &'g str,
+    kind: Vec<&'g str>,
+    crate_types: Vec<&'g str>,
+    #[serde(rename = "required-features")]
+    required_features: &'g [String],
+    src_path: &'g Path,
+    edition: &'g str,
+    doctest: bool,
+}
+
+#[derive(Serialize)]
+struct SummaryResolve<'g> {
+    nodes: Vec<SummaryNode<'g>>,
+}
+
+#[derive(Serialize)]
+struct SummaryNode<'g> {
+    id: &'g str,
+    deps: Vec<SummaryNodeDep<'g>>,
+    dependencies: Vec<&'g str>,
+    features: &'g [String],
+}
+
+#[derive(Serialize)]
+struct SummaryNodeDep<'g> {
+    name: &'g str,
+    pkg: &'g str,
+}
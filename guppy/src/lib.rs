@@ -64,7 +64,7 @@ pub use package_id::PackageId;
 // Public re-exports for upstream crates used in APIs. The no_inline ensures that they show up as
 // re-exports in documentation.
 #[doc(no_inline)]
-pub use cargo_metadata::{DependencyKind, Metadata, MetadataCommand};
+pub use cargo_metadata::{DependencyKind, Metadata, MetadataCommand, Node, Package};
 #[doc(no_inline)]
 pub use semver::Version;
 #[doc(no_inline)]
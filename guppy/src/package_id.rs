@@ -6,6 +6,8 @@ use std::fmt;
 /// An "opaque" identifier for a package.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[allow(clippy::derive_hash_xor_eq)] // safe because the same PartialEq impl is used everywhere
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde1", serde(transparent))]
 pub struct PackageId {
     /// The underlying string representation of an ID.
     repr: Box<str>,
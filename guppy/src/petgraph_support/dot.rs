@@ -17,7 +17,15 @@ pub trait DotVisitor<NR, ER> {
     /// `DotWrite`.
     fn visit_edge(&self, edge: ER, f: &mut DotWrite<'_, '_>) -> fmt::Result;
 
-    // TODO: allow more customizations? more labels, colors etc to be set?
+    /// Returns the color this edge should be drawn in, if any.
+    ///
+    /// The color may be any color name or value that graphviz understands. Returns `None` by
+    /// default.
+    fn edge_color(&self, _edge: ER) -> Option<&'static str> {
+        None
+    }
+
+    // TODO: allow more customizations? more labels, node colors etc to be set?
 }
 
 /// A visitor for formatting graph labels that outputs `fmt::Display` impls for node and edge
@@ -54,6 +62,10 @@ where
     fn visit_edge(&self, edge: ER, f: &mut DotWrite<'_, '_>) -> fmt::Result {
         (*self).visit_edge(edge, f)
     }
+
+    fn edge_color(&self, edge: ER) -> Option<&'static str> {
+        (*self).edge_color(edge)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -100,7 +112,11 @@ where
                 (&self.graph).to_index(edge.target())
             )?;
             self.visitor.visit_edge(edge, &mut DotWrite::new(f))?;
-            writeln!(f, "\"]")?;
+            write!(f, "\"")?;
+            if let Some(color) = self.visitor.edge_color(edge) {
+                write!(f, ", color=\"{}\"", color)?;
+            }
+            writeln!(f, "]")?;
         }
 
         writeln!(f, "}}")
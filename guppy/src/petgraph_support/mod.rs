@@ -13,6 +13,7 @@ use std::iter::FromIterator;
 
 pub mod dfs;
 pub mod dot;
+pub mod reachability;
 pub mod reversed;
 pub mod scc;
 pub mod walk;
@@ -0,0 +1,72 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::petgraph_support::scc::Sccs;
+use fixedbitset::FixedBitSet;
+use petgraph::graph::IndexType;
+use petgraph::prelude::*;
+use petgraph::visit::{IntoNeighbors, NodeCount};
+
+/// A precomputed transitive closure of a graph.
+///
+/// The closure is computed over the condensation of the graph (every SCC is collapsed into a single
+/// node), and is stored as one packed bitset per SCC. For a graph with `N` SCCs this uses roughly
+/// `N * N / 8` bytes.
+#[derive(Clone, Debug)]
+pub(crate) struct Reachability {
+    // The index of the SCC each node is in, indexed by node ix.
+    node_sccs: Vec<usize>,
+    // For each SCC, the set of SCCs reachable from it (including itself).
+    reachable: Vec<FixedBitSet>,
+}
+
+impl Reachability {
+    /// Computes the transitive closure of this graph, using the provided SCCs.
+    pub fn new<G, Ix>(graph: G, sccs: &Sccs<Ix>) -> Self
+    where
+        G: IntoNeighbors<NodeId = NodeIndex<Ix>> + NodeCount,
+        Ix: IndexType,
+    {
+        let scc_count = sccs.iter().len();
+        let mut node_sccs = vec![0; graph.node_count()];
+        for (scc_idx, scc) in sccs.iter().enumerate() {
+            for node_ix in scc {
+                node_sccs[node_ix.index()] = scc_idx;
+            }
+        }
+
+        // The SCCs are in reverse topological order, so every SCC reachable from a given SCC comes
+        // before it.
+        let mut reachable: Vec<FixedBitSet> = Vec::with_capacity(scc_count);
+        for (scc_idx, scc) in sccs.iter().enumerate() {
+            let mut scc_reachable = FixedBitSet::with_capacity(scc_count);
+            scc_reachable.insert(scc_idx);
+            for neighbor_ix in scc.iter().flat_map(|node_ix| graph.neighbors(*node_ix)) {
+                let neighbor_scc_idx = node_sccs[neighbor_ix.index()];
+                if neighbor_scc_idx != scc_idx && !scc_reachable.contains(neighbor_scc_idx) {
+                    scc_reachable.union_with(&reachable[neighbor_scc_idx]);
+                }
+            }
+            reachable.push(scc_reachable);
+        }
+
+        Self {
+            node_sccs,
+            reachable,
+        }
+    }
+
+    /// Returns true if `b` is reachable from `a`.
+    ///
+    /// Every node is reachable from itself.
+    pub fn is_reachable<Ix: IndexType>(&self, a: NodeIndex<Ix>, b: NodeIndex<Ix>) -> bool {
+        let a_scc = self.node_sccs[a.index()];
+        let b_scc = self.node_sccs[b.index()];
+        self.reachable[a_scc].contains(b_scc)
+    }
+
+    /// Returns the number of SCCs this closure was computed over.
+    pub fn scc_count(&self) -> usize {
+        self.reachable.len()
+    }
+}
@@ -6,14 +6,17 @@ use nested::Nested;
 use petgraph::algo::kosaraju_scc;
 use petgraph::graph::IndexType;
 use petgraph::prelude::*;
-use petgraph::visit::{IntoNeighborsDirected, IntoNodeIdentifiers, VisitMap, Visitable};
-use std::collections::HashMap;
+use petgraph::visit::{
+    IntoNeighborsDirected, IntoNodeIdentifiers, NodeFiltered, VisitMap, Visitable,
+};
+use std::collections::{HashMap, HashSet};
 use std::slice;
 
 #[derive(Clone, Debug)]
 pub(crate) struct Sccs<Ix: IndexType> {
     sccs: Nested<Vec<NodeIndex<Ix>>>,
     multi_map: HashMap<NodeIndex<Ix>, usize>,
+    has_self_loop: bool,
 }
 
 impl<Ix: IndexType> Sccs<Ix> {
@@ -33,7 +36,86 @@ impl<Ix: IndexType> Sccs<Ix> {
                 multi_map.extend(scc.iter().map(|ix| (*ix, idx)));
             }
         }
-        Self { sccs, multi_map }
+        Self {
+            sccs,
+            multi_map,
+            has_self_loop: Self::has_self_loop(graph),
+        }
+    }
+
+    // Self-loops form cycles, but show up as SCCs with a single element.
+    fn has_self_loop<G>(graph: G) -> bool
+    where
+        G: IntoNeighborsDirected<NodeId = NodeIndex<Ix>> + IntoNodeIdentifiers,
+    {
+        graph.node_identifiers().any(|ix| {
+            graph
+                .neighbors_directed(ix, Outgoing)
+                .any(|neighbor| neighbor == ix)
+        })
+    }
+
+    /// Updates this instance after the given edges were removed from the graph it was computed
+    /// from. `graph` is the graph with the edges removed, and must have the same nodes.
+    ///
+    /// Removing edges can only split SCCs, never merge them. So only the SCCs that a removed edge
+    /// was internal to are recomputed, and the pieces they split into take the place of the
+    /// original SCC. This preserves reverse topological order.
+    pub fn remove_edges<G>(
+        &mut self,
+        graph: G,
+        removed: impl IntoIterator<Item = (NodeIndex<Ix>, NodeIndex<Ix>)>,
+    ) where
+        G: IntoNeighborsDirected<NodeId = NodeIndex<Ix>> + Visitable + IntoNodeIdentifiers,
+        <G as Visitable>::Map: VisitMap<NodeIndex<Ix>>,
+    {
+        let mut affected = FixedBitSet::with_capacity(self.sccs.len());
+        let mut self_loop_removed = false;
+        for (source, target) in removed {
+            if source == target {
+                self_loop_removed = true;
+            } else if let (Some(&source_scc), Some(&target_scc)) =
+                (self.multi_map.get(&source), self.multi_map.get(&target))
+            {
+                if source_scc == target_scc {
+                    affected.insert(source_scc);
+                }
+            }
+        }
+
+        if affected.count_ones(..) > 0 {
+            let mut sccs: Nested<Vec<_>> =
+                Nested::with_capacity(self.sccs.len(), self.sccs.data_len());
+            for (idx, scc) in self.sccs.iter().enumerate() {
+                if !affected.contains(idx) {
+                    sccs.push(scc);
+                    continue;
+                }
+                let members: HashSet<_> = scc.iter().copied().collect();
+                let subgraph = NodeFiltered::from_fn(graph, |ix| members.contains(&ix));
+                for sub_scc in kosaraju_scc(&subgraph) {
+                    sccs.push(sub_scc);
+                }
+            }
+
+            let mut multi_map = HashMap::new();
+            for (idx, scc) in sccs.iter().enumerate() {
+                if scc.len() > 1 {
+                    multi_map.extend(scc.iter().map(|ix| (*ix, idx)));
+                }
+            }
+            self.sccs = sccs;
+            self.multi_map = multi_map;
+        }
+
+        if self_loop_removed {
+            self.has_self_loop = Self::has_self_loop(graph);
+        }
+    }
+
+    /// Returns true if the graph this was computed from has any cycles, including self-loops.
+    pub fn is_cyclic(&self) -> bool {
+        !self.multi_map.is_empty() || self.has_self_loop
     }
 
     /// Returns true if `a` and `b` are in the same scc.
@@ -47,6 +129,11 @@ impl<Ix: IndexType> Sccs<Ix> {
         }
     }
 
+    /// Returns all the SCCs, in reverse topological order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &[NodeIndex<Ix>]> {
+        self.sccs.iter()
+    }
+
     /// Returns all the SCCs with more than one element.
     pub fn multi_sccs(&self) -> impl Iterator<Item = &[NodeIndex<Ix>]> {
         self.sccs.iter().filter(|scc| scc.len() > 1)
@@ -168,6 +168,13 @@ pub(crate) fn assert_transitive_deps_internal(
     actual_dep_ids.sort();
 
     let actual_deps: Vec<_> = package_set.links(direction).collect();
+    assert_eq!(
+        package_set.links(direction).len(),
+        actual_deps.len(),
+        "{}: {} links len matches the number of links returned",
+        msg,
+        desc.direction_desc
+    );
     let actual_ptrs = dep_link_ptrs(actual_deps.iter().copied());
 
     // Use a BTreeSet for unique identifiers. This is also used later for set operations.
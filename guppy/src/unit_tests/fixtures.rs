@@ -94,6 +94,113 @@ pub(crate) static METADATA_PROC_MACRO1_BUILD_USER: &str =
 pub(crate) static METADATA_PROC_MACRO1_DEV_USER: &str =
     "dev-user 0.1.0 (path+file:///Users/fakeuser/local/testcrates/proc-macro/dev-user)";
 
+pub(crate) static METADATA_CUSTOM_CFG1: &str =
+    include_str!("../../fixtures/small/metadata_custom_cfg1.json");
+pub(crate) static METADATA_CUSTOM_CFG1_CUSTOM_CFG: &str =
+    "custom-cfg 0.1.0 (path+file:///Users/fakeuser/local/testcrates/custom-cfg/custom-cfg)";
+pub(crate) static METADATA_CUSTOM_CFG1_FUZZ_DEP: &str =
+    "fuzz-dep 0.1.0 (path+file:///Users/fakeuser/local/testcrates/custom-cfg/fuzz-dep)";
+pub(crate) static METADATA_CUSTOM_CFG1_NORMAL_DEP: &str =
+    "normal-dep 0.1.0 (path+file:///Users/fakeuser/local/testcrates/custom-cfg/normal-dep)";
+
+pub(crate) static METADATA_CYCLE_PLATFORM1: &str =
+    include_str!("../../fixtures/small/metadata_cycle_platform1.json");
+pub(crate) static METADATA_CYCLE_PLATFORM1_UPPER: &str =
+    "upper 0.1.0 (path+file:///Users/fakeuser/local/testcrates/cycle-platform/upper)";
+pub(crate) static METADATA_CYCLE_PLATFORM1_LOWER: &str =
+    "lower 0.1.0 (path+file:///Users/fakeuser/local/testcrates/cycle-platform/lower)";
+
+pub(crate) static METADATA_MINIMAL_VERSIONS1: &str =
+    include_str!("../../fixtures/small/metadata_minimal_versions1.json");
+pub(crate) static METADATA_MINIMAL_VERSIONS1_CONSUMER: &str =
+    "consumer 0.1.0 (path+file:///Users/fakeuser/local/testcrates/minimal-versions/consumer)";
+pub(crate) static METADATA_MINIMAL_VERSIONS1_LEGACY: &str =
+    "legacy 0.1.0 (path+file:///Users/fakeuser/local/testcrates/minimal-versions/legacy)";
+pub(crate) static METADATA_MINIMAL_VERSIONS1_LIB_OLD: &str =
+    "semver-lib 1.2.0 (path+file:///Users/fakeuser/local/testcrates/minimal-versions/deps/lib-old)";
+pub(crate) static METADATA_MINIMAL_VERSIONS1_LIB_NEW: &str =
+    "semver-lib 1.5.0 (path+file:///Users/fakeuser/local/testcrates/minimal-versions/deps/lib-new)";
+
+pub(crate) static METADATA_WEAK_FEATURES1: &str =
+    include_str!("../../fixtures/small/metadata_weak_features1.json");
+pub(crate) static METADATA_WEAK_FEATURES1_WEAK_USER: &str =
+    "weak-user 0.1.0 (path+file:///Users/fakeuser/local/testcrates/weak-features/weak-user)";
+pub(crate) static METADATA_WEAK_FEATURES1_WEAK_DEP: &str =
+    "weak-dep 0.1.0 (path+file:///Users/fakeuser/local/testcrates/weak-features/deps/weak-dep)";
+
+pub(crate) static METADATA_DEP_SYNTAX1: &str =
+    include_str!("../../fixtures/small/metadata_dep_syntax1.json");
+pub(crate) static METADATA_DEP_SYNTAX1_NS_USER: &str =
+    "ns-user 0.1.0 (path+file:///Users/fakeuser/local/testcrates/dep-syntax/ns-user)";
+pub(crate) static METADATA_DEP_SYNTAX1_NS_DEP: &str =
+    "ns-dep 0.1.0 (path+file:///Users/fakeuser/local/testcrates/dep-syntax/deps/ns-dep)";
+
+pub(crate) static METADATA_FEATURES1: &str =
+    include_str!("../../fixtures/small/metadata_features1.json");
+pub(crate) static METADATA_FEATURES1_MEMBER_A: &str =
+    "member-a 0.1.0 (path+file:///Users/fakeuser/local/testcrates/features1/member-a)";
+pub(crate) static METADATA_FEATURES1_MEMBER_B: &str =
+    "member-b 0.1.0 (path+file:///Users/fakeuser/local/testcrates/features1/member-b)";
+pub(crate) static METADATA_FEATURES1_DEP_C: &str =
+    "dep-c 0.1.0 (path+file:///Users/fakeuser/local/testcrates/features1/deps/dep-c)";
+pub(crate) static METADATA_FEATURES1_OPT_DEP: &str =
+    "opt-dep 0.1.0 (path+file:///Users/fakeuser/local/testcrates/features1/deps/opt-dep)";
+pub(crate) static METADATA_FEATURES1_DEV_ONLY: &str =
+    "dev-only 0.1.0 (path+file:///Users/fakeuser/local/testcrates/features1/deps/dev-only)";
+
+pub(crate) static METADATA_PLATFORM_FEATURES1: &str =
+    include_str!("../../fixtures/small/metadata_platform_features1.json");
+pub(crate) static METADATA_PLATFORM_FEATURES1_PF_ROOT: &str =
+    "pf-root 0.1.0 (path+file:///Users/fakeuser/local/testcrates/platform-features/pf-root)";
+pub(crate) static METADATA_PLATFORM_FEATURES1_WIN_DEP: &str =
+    "win-dep 0.1.0 (path+file:///Users/fakeuser/local/testcrates/platform-features/deps/win-dep)";
+pub(crate) static METADATA_PLATFORM_FEATURES1_NEVER_DEP: &str =
+    "never-dep 0.1.0 (path+file:///Users/fakeuser/local/testcrates/platform-features/deps/never-dep)";
+pub(crate) static METADATA_PLATFORM_FEATURES1_UNIX_BUILD_DEP: &str =
+    "unix-build-dep 0.1.0 (path+file:///Users/fakeuser/local/testcrates/platform-features/deps/unix-build-dep)";
+pub(crate) static METADATA_PLATFORM_FEATURES1_DEV_DEP: &str =
+    "dev-dep 0.1.0 (path+file:///Users/fakeuser/local/testcrates/platform-features/deps/dev-dep)";
+
+pub(crate) static METADATA_NO_DEFAULT1: &str =
+    include_str!("../../fixtures/small/metadata_no_default1.json");
+pub(crate) static METADATA_NO_DEFAULT1_APP: &str =
+    "app 0.1.0 (path+file:///Users/fakeuser/local/testcrates/no-default/app)";
+pub(crate) static METADATA_NO_DEFAULT1_OTHER: &str =
+    "other 0.1.0 (path+file:///Users/fakeuser/local/testcrates/no-default/other)";
+pub(crate) static METADATA_NO_DEFAULT1_LIB: &str =
+    "lib 0.1.0 (path+file:///Users/fakeuser/local/testcrates/no-default/deps/lib)";
+
+pub(crate) static METADATA_RENAME1: &str =
+    include_str!("../../fixtures/small/metadata_rename1.json");
+pub(crate) static METADATA_RENAME1_RENAMER: &str =
+    "renamer 0.1.0 (path+file:///Users/fakeuser/local/testcrates/rename/renamer)";
+pub(crate) static METADATA_RENAME1_REAL_DEP: &str =
+    "real-dep 0.1.0 (path+file:///Users/fakeuser/local/testcrates/rename/deps/real-dep)";
+pub(crate) static METADATA_RENAME1_PLAIN_DEP: &str =
+    "plain-dep 0.1.0 (path+file:///Users/fakeuser/local/testcrates/rename/deps/plain-dep)";
+
+pub(crate) static METADATA_FEATURE_CYCLE1: &str =
+    include_str!("../../fixtures/small/metadata_feature_cycle1.json");
+pub(crate) static METADATA_FEATURE_CYCLE1_CYC_A: &str =
+    "cyc-a 0.1.0 (path+file:///Users/fakeuser/local/testcrates/feature-cycle/cyc-a)";
+pub(crate) static METADATA_FEATURE_CYCLE1_CYC_B: &str =
+    "cyc-b 0.1.0 (path+file:///Users/fakeuser/local/testcrates/feature-cycle/cyc-b)";
+
+pub(crate) static METADATA_VERSION_CHANGE1_OLD: &str =
+    include_str!("../../fixtures/small/metadata_version_change1_old.json");
+pub(crate) static METADATA_VERSION_CHANGE1_NEW: &str =
+    include_str!("../../fixtures/small/metadata_version_change1_new.json");
+pub(crate) static METADATA_VERSION_CHANGE1_APP: &str =
+    "app 0.1.0 (path+file:///Users/fakeuser/local/testcrates/version-change/app)";
+pub(crate) static METADATA_VERSION_CHANGE1_HELPER_OLD: &str =
+    "helper 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)";
+pub(crate) static METADATA_VERSION_CHANGE1_HELPER_NEW: &str =
+    "helper 1.1.0 (registry+https://github.com/rust-lang/crates.io-index)";
+pub(crate) static METADATA_VERSION_CHANGE1_STALE: &str =
+    "stale 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)";
+pub(crate) static METADATA_VERSION_CHANGE1_FRESH: &str =
+    "fresh 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)";
+
 pub(crate) static METADATA_LIBRA: &str = include_str!("../../fixtures/large/metadata_libra.json");
 pub(crate) static METADATA_LIBRA_ADMISSION_CONTROL_SERVICE: &str =
     "admission-control-service 0.1.0 (path+file:///Users/fakeuser/local/libra/admission_control/admission-control-service)";
@@ -284,6 +391,18 @@ impl Fixture {
     define_fixture!(metadata_targets1, METADATA_TARGETS1);
     define_fixture!(metadata_build_targets1, METADATA_BUILD_TARGETS1);
     define_fixture!(metadata_proc_macro1, METADATA_PROC_MACRO1);
+    define_fixture!(metadata_custom_cfg1, METADATA_CUSTOM_CFG1);
+    define_fixture!(metadata_features1, METADATA_FEATURES1);
+    define_fixture!(metadata_cycle_platform1, METADATA_CYCLE_PLATFORM1);
+    define_fixture!(metadata_minimal_versions1, METADATA_MINIMAL_VERSIONS1);
+    define_fixture!(metadata_weak_features1, METADATA_WEAK_FEATURES1);
+    define_fixture!(metadata_dep_syntax1, METADATA_DEP_SYNTAX1);
+    define_fixture!(metadata_platform_features1, METADATA_PLATFORM_FEATURES1);
+    define_fixture!(metadata_no_default1, METADATA_NO_DEFAULT1);
+    define_fixture!(metadata_rename1, METADATA_RENAME1);
+    define_fixture!(metadata_feature_cycle1, METADATA_FEATURE_CYCLE1);
+    define_fixture!(metadata_version_change1_old, METADATA_VERSION_CHANGE1_OLD);
+    define_fixture!(metadata_version_change1_new, METADATA_VERSION_CHANGE1_NEW);
     define_fixture!(metadata_libra, METADATA_LIBRA);
     define_fixture!(metadata_libra_f0091a4, METADATA_LIBRA_F0091A4);
     define_fixture!(metadata_libra_9ffd93b, METADATA_LIBRA_9FFD93B);
@@ -570,6 +689,22 @@ impl FixtureDetails {
         let mut actual: Vec<_> = graph.feature_graph().build_warnings().to_vec();
         actual.sort();
         assert_eq!(&self.feature_graph_warnings, &actual, "{}", msg);
+
+        match graph.build_feature_graph_strict() {
+            Ok(_) => assert!(
+                actual.is_empty(),
+                "{}: strict build succeeded despite warnings",
+                msg
+            ),
+            Err(mut warnings) => {
+                warnings.sort();
+                assert_eq!(
+                    &actual, &warnings,
+                    "{}: strict build errors match warnings",
+                    msg
+                );
+            }
+        }
     }
 
     // ---
@@ -589,6 +724,12 @@ impl FixtureDetails {
         actual.sort();
 
         assert_eq!(&self.cycles, &actual, "{}", msg);
+        assert_eq!(
+            graph.is_cyclic(),
+            !actual.is_empty(),
+            "{}: is_cyclic matches cycles",
+            msg
+        );
     }
 
     // Specific fixtures follow.
@@ -1297,6 +1438,472 @@ impl FixtureDetails {
         Self::new(details)
     }
 
+    pub(crate) fn metadata_custom_cfg1() -> Self {
+        let mut details = HashMap::new();
+
+        PackageDetails::new(
+            METADATA_CUSTOM_CFG1_CUSTOM_CFG,
+            "custom-cfg",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![
+            ("fuzz-dep", METADATA_CUSTOM_CFG1_FUZZ_DEP),
+            ("normal-dep", METADATA_CUSTOM_CFG1_NORMAL_DEP),
+        ])
+        .with_named_features(vec!["fuzz"])
+        .insert_into(&mut details);
+
+        Self::new(details).with_workspace_members(vec![
+            ("custom-cfg", METADATA_CUSTOM_CFG1_CUSTOM_CFG),
+            ("fuzz-dep", METADATA_CUSTOM_CFG1_FUZZ_DEP),
+            ("normal-dep", METADATA_CUSTOM_CFG1_NORMAL_DEP),
+        ])
+    }
+
+    pub(crate) fn metadata_features1() -> Self {
+        let mut details = HashMap::new();
+
+        PackageDetails::new(
+            METADATA_FEATURES1_MEMBER_A,
+            "member-a",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![
+            ("dep-c", METADATA_FEATURES1_DEP_C),
+            ("dev-only", METADATA_FEATURES1_DEV_ONLY),
+            ("opt-dep", METADATA_FEATURES1_OPT_DEP),
+        ])
+        .with_reverse_deps(vec![("member-a", METADATA_FEATURES1_MEMBER_B)])
+        .with_named_features(vec!["a", "a-default", "b", "default", "shared"])
+        .insert_into(&mut details);
+
+        PackageDetails::new(
+            METADATA_FEATURES1_MEMBER_B,
+            "member-b",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![
+            ("dep-c", METADATA_FEATURES1_DEP_C),
+            ("member-a", METADATA_FEATURES1_MEMBER_A),
+        ])
+        .with_reverse_deps(vec![])
+        .with_named_features(vec!["shared"])
+        .insert_into(&mut details);
+
+        PackageDetails::new(
+            METADATA_FEATURES1_DEP_C,
+            "dep-c",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![])
+        .with_named_features(vec!["build-feat", "c-default", "default", "extra"])
+        .insert_into(&mut details);
+
+        Self::new(details).with_workspace_members(vec![
+            ("member-a", METADATA_FEATURES1_MEMBER_A),
+            ("member-b", METADATA_FEATURES1_MEMBER_B),
+        ])
+    }
+
+    pub(crate) fn metadata_cycle_platform1() -> Self {
+        // upper -> lower, and lower -> upper through a dev-dependency only on cfg(windows).
+        let mut details = HashMap::new();
+
+        PackageDetails::new(
+            METADATA_CYCLE_PLATFORM1_UPPER,
+            "upper",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![("lower", METADATA_CYCLE_PLATFORM1_LOWER)])
+        .with_reverse_deps(vec![("upper", METADATA_CYCLE_PLATFORM1_LOWER)])
+        .insert_into(&mut details);
+
+        PackageDetails::new(
+            METADATA_CYCLE_PLATFORM1_LOWER,
+            "lower",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![("upper", METADATA_CYCLE_PLATFORM1_UPPER)])
+        .with_reverse_deps(vec![("lower", METADATA_CYCLE_PLATFORM1_UPPER)])
+        .insert_into(&mut details);
+
+        Self::new(details)
+            .with_workspace_members(vec![
+                ("upper", METADATA_CYCLE_PLATFORM1_UPPER),
+                ("lower", METADATA_CYCLE_PLATFORM1_LOWER),
+            ])
+            .with_cycles(vec![vec![
+                METADATA_CYCLE_PLATFORM1_UPPER,
+                METADATA_CYCLE_PLATFORM1_LOWER,
+            ]])
+    }
+
+    pub(crate) fn metadata_minimal_versions1() -> Self {
+        // consumer depends on semver-lib with a "1.2" requirement, which resolves to 1.5.0.
+        // legacy pins semver-lib to "=1.2.0".
+        let mut details = HashMap::new();
+
+        PackageDetails::new(
+            METADATA_MINIMAL_VERSIONS1_CONSUMER,
+            "consumer",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![("semver-lib", METADATA_MINIMAL_VERSIONS1_LIB_NEW)])
+        .insert_into(&mut details);
+
+        PackageDetails::new(
+            METADATA_MINIMAL_VERSIONS1_LEGACY,
+            "legacy",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![("semver-lib", METADATA_MINIMAL_VERSIONS1_LIB_OLD)])
+        .insert_into(&mut details);
+
+        PackageDetails::new(
+            METADATA_MINIMAL_VERSIONS1_LIB_OLD,
+            "semver-lib",
+            "1.2.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_reverse_deps(vec![("semver-lib", METADATA_MINIMAL_VERSIONS1_LEGACY)])
+        .insert_into(&mut details);
+
+        PackageDetails::new(
+            METADATA_MINIMAL_VERSIONS1_LIB_NEW,
+            "semver-lib",
+            "1.5.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_reverse_deps(vec![("semver-lib", METADATA_MINIMAL_VERSIONS1_CONSUMER)])
+        .insert_into(&mut details);
+
+        Self::new(details).with_workspace_members(vec![
+            ("consumer", METADATA_MINIMAL_VERSIONS1_CONSUMER),
+            ("legacy", METADATA_MINIMAL_VERSIONS1_LEGACY),
+        ])
+    }
+
+    pub(crate) fn metadata_weak_features1() -> Self {
+        // weak-user has an optional dependency on weak-dep, and a feature "serde" that enables
+        // "weak-dep?/extra".
+        let mut details = HashMap::new();
+
+        PackageDetails::new(
+            METADATA_WEAK_FEATURES1_WEAK_USER,
+            "weak-user",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![("weak-dep", METADATA_WEAK_FEATURES1_WEAK_DEP)])
+        .insert_into(&mut details);
+
+        PackageDetails::new(
+            METADATA_WEAK_FEATURES1_WEAK_DEP,
+            "weak-dep",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_reverse_deps(vec![("weak-dep", METADATA_WEAK_FEATURES1_WEAK_USER)])
+        .insert_into(&mut details);
+
+        Self::new(details)
+            .with_workspace_members(vec![("weak-user", METADATA_WEAK_FEATURES1_WEAK_USER)])
+    }
+
+    pub(crate) fn metadata_dep_syntax1() -> Self {
+        // ns-user has an optional dependency on ns-dep, which is enabled by the feature
+        // "enable-dep" through "dep:ns-dep".
+        let mut details = HashMap::new();
+
+        PackageDetails::new(
+            METADATA_DEP_SYNTAX1_NS_USER,
+            "ns-user",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![("ns-dep", METADATA_DEP_SYNTAX1_NS_DEP)])
+        .insert_into(&mut details);
+
+        PackageDetails::new(
+            METADATA_DEP_SYNTAX1_NS_DEP,
+            "ns-dep",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_reverse_deps(vec![("ns-dep", METADATA_DEP_SYNTAX1_NS_USER)])
+        .insert_into(&mut details);
+
+        Self::new(details).with_workspace_members(vec![("ns-user", METADATA_DEP_SYNTAX1_NS_USER)])
+    }
+
+    pub(crate) fn metadata_platform_features1() -> Self {
+        // pf-root depends on win-dep on cfg(windows), never-dep on cfg(any()), unix-build-dep as
+        // a build dependency on cfg(unix), and dev-dep as a dev dependency. Every dependency is
+        // enabled with the feature "extra".
+        let mut details = HashMap::new();
+
+        PackageDetails::new(
+            METADATA_PLATFORM_FEATURES1_PF_ROOT,
+            "pf-root",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![
+            ("dev-dep", METADATA_PLATFORM_FEATURES1_DEV_DEP),
+            ("never-dep", METADATA_PLATFORM_FEATURES1_NEVER_DEP),
+            ("unix-build-dep", METADATA_PLATFORM_FEATURES1_UNIX_BUILD_DEP),
+            ("win-dep", METADATA_PLATFORM_FEATURES1_WIN_DEP),
+        ])
+        .with_reverse_deps(vec![])
+        .insert_into(&mut details);
+
+        Self::new(details)
+            .with_workspace_members(vec![("pf-root", METADATA_PLATFORM_FEATURES1_PF_ROOT)])
+    }
+
+    pub(crate) fn metadata_no_default1() -> Self {
+        // other -> app (default features) -> lib (no default features)
+        //
+        // app has default = ["fancy"], and lib has default = ["std"].
+        let mut details = HashMap::new();
+
+        PackageDetails::new(
+            METADATA_NO_DEFAULT1_APP,
+            "app",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![("lib", METADATA_NO_DEFAULT1_LIB)])
+        .with_reverse_deps(vec![("app", METADATA_NO_DEFAULT1_OTHER)])
+        .with_named_features(vec!["default", "fancy"])
+        .insert_into(&mut details);
+
+        PackageDetails::new(
+            METADATA_NO_DEFAULT1_OTHER,
+            "other",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![("app", METADATA_NO_DEFAULT1_APP)])
+        .with_reverse_deps(vec![])
+        .insert_into(&mut details);
+
+        PackageDetails::new(
+            METADATA_NO_DEFAULT1_LIB,
+            "lib",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![])
+        .with_reverse_deps(vec![("lib", METADATA_NO_DEFAULT1_APP)])
+        .with_named_features(vec!["default", "std"])
+        .insert_into(&mut details);
+
+        Self::new(details).with_workspace_members(vec![
+            ("app", METADATA_NO_DEFAULT1_APP),
+            ("other", METADATA_NO_DEFAULT1_OTHER),
+        ])
+    }
+
+    pub(crate) fn metadata_rename1() -> Self {
+        // renamer -> alias-dep (renamed from real-dep, optional)
+        //         -> plain-dep
+        //
+        // renamer has use-alias = ["alias-dep/extra"], which refers to real-dep through its alias.
+        let mut details = HashMap::new();
+
+        PackageDetails::new(
+            METADATA_RENAME1_RENAMER,
+            "renamer",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![
+            ("alias-dep", METADATA_RENAME1_REAL_DEP),
+            ("plain-dep", METADATA_RENAME1_PLAIN_DEP),
+        ])
+        .with_reverse_deps(vec![])
+        .with_named_features(vec!["default", "use-alias"])
+        .insert_into(&mut details);
+
+        PackageDetails::new(
+            METADATA_RENAME1_REAL_DEP,
+            "real-dep",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![])
+        .with_reverse_deps(vec![("alias-dep", METADATA_RENAME1_RENAMER)])
+        .with_named_features(vec!["extra"])
+        .insert_into(&mut details);
+
+        PackageDetails::new(
+            METADATA_RENAME1_PLAIN_DEP,
+            "plain-dep",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![])
+        .with_reverse_deps(vec![("plain-dep", METADATA_RENAME1_RENAMER)])
+        .insert_into(&mut details);
+
+        Self::new(details).with_workspace_members(vec![("renamer", METADATA_RENAME1_RENAMER)])
+    }
+
+    pub(crate) fn metadata_feature_cycle1() -> Self {
+        // cyc-a -> cyc-b (normal)
+        // cyc-b -> cyc-a (dev)
+        //
+        // cyc-a has x = ["y"] and y = ["x", "cyc-b/feat"], which form a cycle within cyc-a.
+        let mut details = HashMap::new();
+
+        PackageDetails::new(
+            METADATA_FEATURE_CYCLE1_CYC_A,
+            "cyc-a",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![("cyc-b", METADATA_FEATURE_CYCLE1_CYC_B)])
+        .with_reverse_deps(vec![("cyc-a", METADATA_FEATURE_CYCLE1_CYC_B)])
+        .with_named_features(vec!["x", "y"])
+        .insert_into(&mut details);
+
+        PackageDetails::new(
+            METADATA_FEATURE_CYCLE1_CYC_B,
+            "cyc-b",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![("cyc-a", METADATA_FEATURE_CYCLE1_CYC_A)])
+        .with_reverse_deps(vec![("cyc-b", METADATA_FEATURE_CYCLE1_CYC_A)])
+        .with_named_features(vec!["feat"])
+        .insert_into(&mut details);
+
+        Self::new(details)
+            .with_workspace_members(vec![
+                ("cyc-a", METADATA_FEATURE_CYCLE1_CYC_A),
+                ("cyc-b", METADATA_FEATURE_CYCLE1_CYC_B),
+            ])
+            .with_cycles(vec![vec![
+                METADATA_FEATURE_CYCLE1_CYC_A,
+                METADATA_FEATURE_CYCLE1_CYC_B,
+            ]])
+    }
+
+    pub(crate) fn metadata_version_change1_old() -> Self {
+        // app -> helper 1.0.0, stale 0.1.0
+        //
+        // metadata_version_change1_new bumps helper to 1.1.0 (adding the feature "extra") and
+        // replaces stale with fresh.
+        Self::version_change1(
+            METADATA_VERSION_CHANGE1_HELPER_OLD,
+            "1.0.0",
+            vec!["std"],
+            ("stale", METADATA_VERSION_CHANGE1_STALE),
+        )
+    }
+
+    pub(crate) fn metadata_version_change1_new() -> Self {
+        Self::version_change1(
+            METADATA_VERSION_CHANGE1_HELPER_NEW,
+            "1.1.0",
+            vec!["extra", "std"],
+            ("fresh", METADATA_VERSION_CHANGE1_FRESH),
+        )
+    }
+
+    fn version_change1(
+        helper_id: &'static str,
+        helper_version: &'static str,
+        helper_features: Vec<&'static str>,
+        (other_name, other_id): (&'static str, &'static str),
+    ) -> Self {
+        let mut details = HashMap::new();
+
+        PackageDetails::new(
+            METADATA_VERSION_CHANGE1_APP,
+            "app",
+            "0.1.0",
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_deps(vec![("helper", helper_id), (other_name, other_id)])
+        .insert_into(&mut details);
+
+        PackageDetails::new(
+            helper_id,
+            "helper",
+            helper_version,
+            vec![FAKE_AUTHOR],
+            None,
+            None,
+        )
+        .with_reverse_deps(vec![("helper", METADATA_VERSION_CHANGE1_APP)])
+        .with_named_features(helper_features)
+        .insert_into(&mut details);
+
+        PackageDetails::new(other_id, other_name, "0.1.0", vec![FAKE_AUTHOR], None, None)
+            .with_reverse_deps(vec![(other_name, METADATA_VERSION_CHANGE1_APP)])
+            .insert_into(&mut details);
+
+        Self::new(details).with_workspace_members(vec![("app", METADATA_VERSION_CHANGE1_APP)])
+    }
+
     pub(crate) fn metadata_libra() -> Self {
         let mut details = HashMap::new();
 
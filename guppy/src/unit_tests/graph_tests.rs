@@ -2,20 +2,38 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use super::fixtures::{self, Fixture};
-use crate::graph::feature::{all_filter, none_filter, FeatureId};
+use crate::graph::feature::{
+    all_filter, no_dev_resolver, none_filter, DefaultFeaturesOnly, FeatureDotVisitor,
+    FeatureEdgeKind, FeatureFilterFn, FeatureGraph, FeatureId, FeatureIdBuf, FeatureLinkType,
+    FeatureMetadata, FeatureType, NoDefaultFeatures, PlatformFeatureResolver,
+};
 use crate::graph::{
-    BuildTargetId, BuildTargetKind, DependencyDirection, DotWrite, PackageDotVisitor, PackageLink,
-    PackageMetadata,
+    AndResolver, BuildTargetId, BuildTargetKind, ColoredDotVisitor, DependencyDirection, DotWrite,
+    EnabledTernary, Explanation, OrResolver, PackageDotVisitor, PackageGraph, PackageLink,
+    PackageMetadata, PackageQuery, PackageResolver, PackageSet, PlatformStatus, RecordingResolver,
+    VersionRangeResolver, GRAPH_SUMMARY_VERSION,
 };
+use crate::PackageId;
+use assert_matches::assert_matches;
+use semver::{Version, VersionReq};
+use std::collections::HashMap;
 use std::fmt;
 use std::iter;
+use std::ops::ControlFlow;
 
 mod small {
     use super::*;
     use crate::graph::feature::{default_filter, feature_filter};
     use crate::unit_tests::feature_helpers::assert_features_for_package;
-    use crate::unit_tests::fixtures::{package_id, METADATA_PROC_MACRO1_MACRO};
+    use crate::unit_tests::fixtures::{
+        package_id, METADATA_CUSTOM_CFG1_CUSTOM_CFG, METADATA_CUSTOM_CFG1_FUZZ_DEP,
+        METADATA_CUSTOM_CFG1_NORMAL_DEP, METADATA_DEP_SYNTAX1_NS_USER, METADATA_FEATURES1_DEP_C,
+        METADATA_FEATURES1_DEV_ONLY, METADATA_FEATURES1_MEMBER_A, METADATA_FEATURES1_MEMBER_B,
+        METADATA_FEATURES1_OPT_DEP, METADATA_PROC_MACRO1_MACRO, METADATA_WEAK_FEATURES1_WEAK_USER,
+    };
+    use crate::{DependencyKind, Error, Metadata, Platform, TargetFeatures};
     use pretty_assertions::assert_eq;
+    use sha2::{Digest, Sha256};
 
     // Test specific details extracted from metadata1.json.
     #[test]
@@ -35,6 +53,10 @@ mod small {
         assert!(link.normal().is_present(), "normal dependency is defined");
         assert!(link.build().is_present(), "build dependency is defined");
         assert!(link.dev().is_present(), "dev dependency is defined");
+        assert!(
+            !link.is_workspace_internal(),
+            "link to crates.io is not workspace internal"
+        );
 
         // Print out dot graphs for small subgraphs.
         static EXPECTED_DOT: &str = r#"digraph {
@@ -132,180 +154,5237 @@ mod small {
     proptest_suite!(metadata1);
 
     #[test]
-    fn metadata2() {
-        let metadata2 = Fixture::metadata2();
-        metadata2.verify();
+    fn metadata_many() {
+        let graph = Fixture::metadata1().graph();
+        let testcrate_id = package_id(fixtures::METADATA1_TESTCRATE);
+        let unknown_id = package_id("unknown 0.1.0 (path+file:///fakepath/unknown)");
+        let dtoa_id = package_id(fixtures::METADATA1_DTOA);
 
-        let feature_graph = metadata2.graph().feature_graph();
-        assert_eq!(feature_graph.feature_count(), 472, "feature count");
-        assert_eq!(feature_graph.link_count(), 571, "link count");
-        let root_ids: Vec<_> = feature_graph
-            .query_workspace(none_filter())
-            .resolve()
-            .root_ids(DependencyDirection::Forward)
+        let results: Vec<_> = graph
+            .metadata_many(vec![&testcrate_id, &unknown_id, &dtoa_id])
             .collect();
-        let testcrate_id = fixtures::package_id(fixtures::METADATA2_TESTCRATE);
-        let expected = vec![FeatureId::base(&testcrate_id)];
-        assert_eq!(root_ids, expected, "feature graph root IDs match");
+        assert_eq!(results.len(), 3, "one result per package ID");
+        assert_eq!(
+            results[0].as_ref().expect("testcrate is known").id(),
+            &testcrate_id,
+            "testcrate metadata returned"
+        );
+        match &results[1] {
+            Err(Error::UnknownPackageId(id)) => {
+                assert_eq!(id, &unknown_id, "unknown package ID returned in error")
+            }
+            other => panic!("expected UnknownPackageId, found {:?}", other),
+        }
+        assert_eq!(
+            results[2].as_ref().expect("dtoa is known").id(),
+            &dtoa_id,
+            "dtoa metadata returned"
+        );
     }
 
-    proptest_suite!(metadata2);
-
     #[test]
-    fn metadata_dups() {
-        let metadata_dups = Fixture::metadata_dups();
-        metadata_dups.verify();
+    fn query_directed_unknown_ids() {
+        let graph = Fixture::metadata1().graph();
+        let testcrate_id = package_id(fixtures::METADATA1_TESTCRATE);
+        let unknown_id = package_id("unknown 0.1.0");
+        for direction in &[DependencyDirection::Forward, DependencyDirection::Reverse] {
+            assert_matches!(
+                graph.query_directed(vec![&testcrate_id, &unknown_id], *direction),
+                Err(Error::UnknownPackageId(id)) if id == unknown_id,
+                "{:?}: unknown IDs are rejected",
+                direction
+            );
+            let query = graph
+                .query_directed(iter::once(&testcrate_id), *direction)
+                .expect("known IDs are accepted");
+            assert_eq!(query.direction(), *direction, "query direction");
+        }
     }
 
-    proptest_suite!(metadata_dups);
-
     #[test]
-    fn metadata_cycle1() {
-        let metadata_cycle1 = Fixture::metadata_cycle1();
-        metadata_cycle1.verify();
-    }
+    fn empty_queries() {
+        let graph = Fixture::metadata1().graph();
+        for direction in &[DependencyDirection::Forward, DependencyDirection::Reverse] {
+            let empty = graph
+                .query_directed(iter::empty(), *direction)
+                .expect("empty queries are valid")
+                .resolve();
+            assert_eq!(
+                empty.len(),
+                0,
+                "{:?}: empty seed resolves to empty set",
+                direction
+            );
+            assert!(empty.is_empty(), "{:?}: empty set is empty", direction);
+            assert_eq!(
+                empty.package_ids(*direction).count(),
+                0,
+                "{:?}: no package IDs",
+                direction
+            );
+            assert_eq!(
+                empty.links(*direction).count(),
+                0,
+                "{:?}: no links",
+                direction
+            );
+        }
 
-    proptest_suite!(metadata_cycle1);
+        let empty = graph
+            .query_forward(iter::empty())
+            .expect("empty queries are valid")
+            .resolve();
+        let workspace = graph.query_workspace().resolve();
+        assert_eq!(
+            workspace.union(&empty).len(),
+            workspace.len(),
+            "empty set is the identity for union"
+        );
+        assert!(
+            workspace.intersection(&empty).is_empty(),
+            "empty set is absorbing for intersection"
+        );
+        assert_eq!(
+            workspace.difference(&empty).len(),
+            workspace.len(),
+            "subtracting the empty set is the identity"
+        );
+        assert!(
+            empty.difference(&workspace).is_empty(),
+            "subtracting from the empty set is empty"
+        );
+        assert_eq!(
+            workspace.symmetric_difference(&empty).len(),
+            workspace.len(),
+            "empty set is the identity for symmetric difference"
+        );
+        assert!(
+            workspace
+                .union(&empty)
+                .symmetric_difference(&workspace)
+                .is_empty(),
+            "union with the empty set has the same elements"
+        );
+
+        let feature_graph = graph.feature_graph();
+        let empty_features = feature_graph
+            .query_forward(iter::empty::<FeatureId>())
+            .expect("empty queries are valid")
+            .resolve();
+        assert!(
+            empty_features.is_empty(),
+            "empty feature seed resolves to empty set"
+        );
+        let all_features = feature_graph.resolve_all();
+        assert_eq!(
+            all_features.union(&empty_features).len(),
+            all_features.len(),
+            "empty feature set is the identity for union"
+        );
+        assert!(
+            all_features.intersection(&empty_features).is_empty(),
+            "empty feature set is absorbing for intersection"
+        );
+    }
 
     #[test]
-    fn metadata_cycle2() {
-        let metadata_cycle2 = Fixture::metadata_cycle2();
-        metadata_cycle2.verify();
+    fn resolve_with_state_fn() {
+        let graph = Fixture::metadata1().graph();
+        let testcrate_id = package_id(fixtures::METADATA1_TESTCRATE);
+
+        // Every package should be reached through exactly one link if links to already-reached
+        // packages are rejected.
+        let mut first_links = HashMap::new();
+        let package_set = graph
+            .query_forward(iter::once(&testcrate_id))
+            .expect("valid package ID")
+            .resolve_with_state_fn(|_, link, state| {
+                let to_id = link.to().id();
+                if state.contains(to_id).expect("valid package ID") {
+                    return false;
+                }
+                let prev = first_links.insert(to_id.clone(), link.from().id().clone());
+                assert!(prev.is_none(), "{} reached through a single link", to_id);
+                true
+            });
+
+        let full_set = graph
+            .query_forward(iter::once(&testcrate_id))
+            .expect("valid package ID")
+            .resolve();
+        assert_eq!(
+            package_set.len(),
+            full_set.len(),
+            "first-path-wins resolve reaches the same packages"
+        );
+        assert_eq!(
+            first_links.len(),
+            package_set.len() - 1,
+            "every package other than the initial is reached through one link"
+        );
+        assert!(
+            !first_links.contains_key(&testcrate_id),
+            "initial package is never reached through a link"
+        );
     }
 
-    proptest_suite!(metadata_cycle2);
+    #[test]
+    fn package_feature_diff() {
+        let old_graph =
+            PackageGraph::from_json(fixtures::METADATA_FEATURES1).expect("valid fixture");
+        // Bump dep-c and add a new feature to it.
+        let new_json = fixtures::METADATA_FEATURES1
+            .replace("dep-c 0.1.0", "dep-c 0.2.0")
+            .replace(
+                r#""name":"dep-c","version":"0.1.0""#,
+                r#""name":"dep-c","version":"0.2.0""#,
+            )
+            .replace(
+                r#""features":{"build-feat":[],"c-default":[],"default":["c-default"],"extra":[]}"#,
+                r#""features":{"build-feat":[],"c-default":[],"default":["c-default"],"extra":[],"new-feat":[]}"#,
+            );
+        let new_graph = PackageGraph::from_json(new_json).expect("valid modified fixture");
+        let old = old_graph.feature_graph();
+        let new = new_graph.feature_graph();
+
+        let diff = FeatureGraph::package_feature_diff(&old, &new, "dep-c");
+        assert_eq!(diff.added(), &["new-feat".to_string()], "new-feat added");
+        assert!(diff.removed().is_empty(), "no features removed");
+
+        let diff = FeatureGraph::package_feature_diff(&new, &old, "dep-c");
+        assert!(diff.added().is_empty(), "no features added");
+        assert_eq!(
+            diff.removed(),
+            &["new-feat".to_string()],
+            "new-feat removed"
+        );
+
+        assert!(
+            FeatureGraph::package_feature_diff(&old, &new, "member-a").is_empty(),
+            "member-a is unchanged"
+        );
+    }
 
     #[test]
-    fn metadata_targets1() {
-        let metadata_targets1 = Fixture::metadata_targets1();
-        metadata_targets1.verify();
+    fn edge_diff() {
+        let old_graph =
+            PackageGraph::from_json(fixtures::METADATA_FEATURES1).expect("valid fixture");
+        assert!(
+            old_graph.edge_diff(&old_graph).is_empty(),
+            "graph has no differences with itself"
+        );
 
-        let package_graph = metadata_targets1.graph();
-        let package_set = package_graph.resolve_all();
-        let feature_graph = metadata_targets1.graph().feature_graph();
-        assert_eq!(feature_graph.feature_count(), 31, "feature count");
+        // Move member-a's dev-dependency on dev-only to a build-dependency.
+        let new_json = fixtures::METADATA_FEATURES1.replace(
+            r#"{"name":"dev-only","source":null,"req":"*","kind":"dev""#,
+            r#"{"name":"dev-only","source":null,"req":"*","kind":"build""#,
+        );
+        let new_graph = PackageGraph::from_json(new_json).expect("valid modified fixture");
+        let diff = old_graph.edge_diff(&new_graph);
+        assert!(diff.added().is_empty(), "no links added");
+        assert!(diff.removed().is_empty(), "no links removed");
+        assert_eq!(diff.kind_changes().len(), 1, "one link changed kinds");
+        let change = &diff.kind_changes()[0];
+        assert_eq!(
+            change.from(),
+            &package_id(METADATA_FEATURES1_MEMBER_A),
+            "from member-a"
+        );
+        assert_eq!(
+            change.to(),
+            &package_id(METADATA_FEATURES1_DEV_ONLY),
+            "to dev-only"
+        );
+        assert_eq!(
+            change.old_kinds(),
+            &[DependencyKind::Development],
+            "was a dev-dependency"
+        );
+        assert_eq!(
+            change.new_kinds(),
+            &[DependencyKind::Build],
+            "is now a build-dependency"
+        );
 
-        // Some code that might be useful for debugging.
-        if false {
-            for (source, target, edge) in feature_graph
+        // Comparing against an unrelated graph shows every link as added or removed.
+        let other_graph = Fixture::metadata_custom_cfg1().graph();
+        let diff = old_graph.edge_diff(other_graph);
+        assert_eq!(
+            diff.removed().len(),
+            old_graph
                 .resolve_all()
                 .links(DependencyDirection::Forward)
-            {
-                let source_metadata = package_graph.metadata(source.package_id()).unwrap();
-                let target_metadata = package_graph.metadata(target.package_id()).unwrap();
+                .count(),
+            "all old links removed"
+        );
+        assert_eq!(
+            diff.added().len(),
+            other_graph
+                .resolve_all()
+                .links(DependencyDirection::Forward)
+                .count(),
+            "all new links added"
+        );
+        assert!(diff.kind_changes().is_empty(), "no links in common");
+    }
 
-                println!(
-                    "feature link: {}:{} {} -> {}:{} {} {:?}",
-                    source_metadata.name(),
-                    source_metadata.version(),
-                    source.feature().unwrap_or("[base]"),
-                    target_metadata.name(),
-                    target_metadata.version(),
-                    target.feature().unwrap_or("[base]"),
-                    edge
-                );
-            }
-        }
+    #[test]
+    fn package_graph_diff() {
+        let old_graph =
+            PackageGraph::from_json(fixtures::METADATA_FEATURES1).expect("valid fixture");
+        assert!(
+            old_graph.diff(&old_graph).is_empty(),
+            "graph has no differences with itself"
+        );
 
-        assert_eq!(feature_graph.link_count(), 48, "feature link count");
+        // Add a named feature to member-b.
+        let new_json = fixtures::METADATA_FEATURES1.replace(
+            r#""features":{"shared":[]}"#,
+            r#""features":{"new-feat":[],"shared":[]}"#,
+        );
+        let new_graph = PackageGraph::from_json(new_json).expect("valid modified fixture");
+        let diff = old_graph.diff(&new_graph);
+        assert!(diff.added().is_empty(), "no packages added");
+        assert!(diff.removed().is_empty(), "no packages removed");
+        assert_eq!(diff.changed().len(), 1, "one package changed");
+        let change = &diff.changed()[0];
+        assert_eq!(
+            change.old_id(),
+            &package_id(METADATA_FEATURES1_MEMBER_B),
+            "member-b changed"
+        );
+        assert_eq!(change.old_id(), change.new_id(), "ID is unchanged");
+        assert_eq!(
+            change.old_version(),
+            change.new_version(),
+            "version is unchanged"
+        );
+        assert_eq!(change.old_features(), &["shared"], "old features");
+        assert_eq!(
+            change.new_features(),
+            &["new-feat", "shared"],
+            "new features"
+        );
 
-        // Check that resolve_packages + a feature filter works.
-        let feature_set = feature_graph.resolve_packages(
-            &package_set,
-            feature_filter(default_filter(), ["foo", "bar"].iter().copied()),
+        // Comparing against an unrelated graph shows every package as added or removed.
+        let other_graph = Fixture::metadata_custom_cfg1().graph();
+        let diff = old_graph.diff(other_graph);
+        assert_eq!(
+            diff.removed().len(),
+            old_graph.package_count(),
+            "all old packages removed"
         );
-        let dep_a_id = fixtures::package_id(fixtures::METADATA_TARGETS1_DEP_A);
-        assert!(feature_set
-            .contains((&dep_a_id, "foo"))
-            .expect("valid feature ID"));
-        assert!(feature_set
-            .contains((&dep_a_id, "bar"))
-            .expect("valid feature ID"));
-        assert!(!feature_set
-            .contains((&dep_a_id, "baz"))
-            .expect("valid feature ID"));
-        assert!(!feature_set
-            .contains((&dep_a_id, "quux"))
-            .expect("valid feature ID"));
+        assert_eq!(
+            diff.added().len(),
+            other_graph.package_count(),
+            "all new packages added"
+        );
+        assert!(diff.changed().is_empty(), "no packages in common");
+        let mut sorted = diff.added().to_vec();
+        sorted.sort();
+        assert_eq!(diff.added(), sorted.as_slice(), "added IDs are sorted");
+    }
 
-        assert_features_for_package(
-            &feature_set,
-            &fixtures::package_id(fixtures::METADATA_TARGETS1_TESTCRATE),
-            &[None],
-            "testcrate",
+    #[test]
+    fn metadata_version_change1() {
+        let old = Fixture::metadata_version_change1_old();
+        old.verify();
+        let new = Fixture::metadata_version_change1_new();
+        new.verify();
+
+        let diff = old.graph().diff(new.graph());
+        assert_eq!(
+            diff.removed(),
+            &[package_id(fixtures::METADATA_VERSION_CHANGE1_STALE)],
+            "stale removed"
         );
-        assert_features_for_package(
-            &feature_set,
-            &dep_a_id,
-            &[None, Some("foo"), Some("bar")],
-            "dep a",
+        assert_eq!(
+            diff.added(),
+            &[package_id(fixtures::METADATA_VERSION_CHANGE1_FRESH)],
+            "fresh added"
         );
-        assert_features_for_package(
-            &feature_set,
-            &fixtures::package_id(fixtures::METADATA_TARGETS1_LAZY_STATIC_1),
-            &[None],
-            "lazy_static",
+        assert_eq!(diff.changed().len(), 1, "one package changed");
+        let change = &diff.changed()[0];
+        assert_eq!(
+            change.old_id(),
+            &package_id(fixtures::METADATA_VERSION_CHANGE1_HELPER_OLD),
+            "old helper ID"
         );
-    }
+        assert_eq!(
+            change.new_id(),
+            &package_id(fixtures::METADATA_VERSION_CHANGE1_HELPER_NEW),
+            "new helper ID"
+        );
+        assert_eq!(
+            change.old_version(),
+            &Version::parse("1.0.0").unwrap(),
+            "old version"
+        );
+        assert_eq!(
+            change.new_version(),
+            &Version::parse("1.1.0").unwrap(),
+            "new version"
+        );
+        assert_eq!(change.old_features(), &["std"], "old features");
+        assert_eq!(change.new_features(), &["extra", "std"], "new features");
 
-    proptest_suite!(metadata_targets1);
+        // Multiple versions of a package with the same name and source aren't paired up.
+        let other = Fixture::metadata_minimal_versions1().graph();
+        let mut bumped_json = fixtures::METADATA_MINIMAL_VERSIONS1.to_string();
+        for (from, to) in [("1.2.0", "1.3.0"), ("1.5.0", "1.6.0")] {
+            bumped_json = bumped_json
+                .replace(
+                    &format!("semver-lib {}", from),
+                    &format!("semver-lib {}", to),
+                )
+                .replace(
+                    &format!(r#""name":"semver-lib","version":"{}""#, from),
+                    &format!(r#""name":"semver-lib","version":"{}""#, to),
+                );
+        }
+        // legacy pins the old version exactly.
+        let bumped_json = bumped_json.replace(r#""req":"=1.2.0""#, r#""req":"=1.3.0""#);
+        let bumped = PackageGraph::from_json(bumped_json).expect("valid modified fixture");
+        let diff = other.diff(&bumped);
+        assert_eq!(diff.removed().len(), 2, "both old versions removed");
+        assert_eq!(diff.added().len(), 2, "both new versions added");
+        assert!(
+            diff.changed().is_empty(),
+            "ambiguous versions aren't paired"
+        );
+    }
 
     #[test]
-    fn metadata_build_targets1() {
-        let metadata_build_targets1 = Fixture::metadata_build_targets1();
-        metadata_build_targets1.verify();
-    }
+    fn adjacency_list() {
+        let graph = Fixture::metadata1().graph();
+        let forward = graph.adjacency_list();
+        let reverse = graph.adjacency_list_directed(DependencyDirection::Reverse);
+        assert_eq!(
+            forward.len(),
+            graph.package_count(),
+            "one entry per package"
+        );
+        assert_eq!(
+            reverse.len(),
+            graph.package_count(),
+            "one entry per package"
+        );
 
-    // No need for proptests because there are no dependencies involved.
+        for package in graph.packages() {
+            let deps: Vec<_> = package.direct_links().map(|link| link.to().id()).collect();
+            let forward_deps: Vec<_> = forward[package.id()].iter().collect();
+            assert_eq!(
+                forward_deps,
+                deps,
+                "forward adjacency list matches direct_links for {}",
+                package.id()
+            );
 
-    #[test]
-    fn metadata_proc_macro1() {
-        let metadata = Fixture::metadata_proc_macro1();
-        metadata.verify();
-        let graph = metadata.graph();
+            let dependents: Vec<_> = package
+                .reverse_direct_links()
+                .map(|link| link.from().id())
+                .collect();
+            let reverse_deps: Vec<_> = reverse[package.id()].iter().collect();
+            assert_eq!(
+                reverse_deps,
+                dependents,
+                "reverse adjacency list matches reverse_direct_links for {}",
+                package.id()
+            );
+        }
 
-        let package = graph
-            .metadata(&package_id(METADATA_PROC_MACRO1_MACRO))
-            .expect("valid package ID");
-        assert!(package.is_proc_macro(), "is proc macro");
-        assert!(matches!(
-            package
-                .build_target(&BuildTargetId::Library)
-                .expect("library package is present")
-                .kind(),
-            BuildTargetKind::ProcMacro
-        ));
+        let testcrate_id = package_id(fixtures::METADATA1_TESTCRATE);
+        let datatest_id = package_id(fixtures::METADATA1_DATATEST);
+        assert!(
+            forward[&testcrate_id].contains(&datatest_id),
+            "testcrate depends on datatest"
+        );
+        assert!(
+            reverse[&datatest_id].contains(&testcrate_id),
+            "datatest is depended on by testcrate"
+        );
     }
 
-    // No need for proptests because this is a really simple test.
-}
+    #[test]
+    fn dependency_rank() {
+        let graph = Fixture::metadata1().graph();
+        let rank = graph.dependency_rank();
+        assert_eq!(rank.len(), graph.package_count(), "every package is ranked");
 
-mod large {
-    use super::*;
-    use crate::unit_tests::dep_helpers::GraphAssert;
-    use crate::unit_tests::fixtures::{
-        package_id, METADATA_LIBRA_ADMISSION_CONTROL_SERVICE, METADATA_LIBRA_EXECUTOR_UTILS,
-    };
+        // unicode-xid is a leaf used by the proc-macro stack. It's tied with the winapi target
+        // crates, and the tie is broken by package ID.
+        let (top, top_count) = &rank[0];
+        assert_eq!(
+            top.name(),
+            "unicode-xid",
+            "unicode-xid is the most depended upon"
+        );
+        assert_eq!(*top_count, 7, "seven packages depend on unicode-xid");
+        assert_eq!(
+            top.direct_links().count(),
+            0,
+            "unicode-xid has no dependencies"
+        );
 
-    #[test]
-    fn metadata_libra() {
-        let metadata_libra = Fixture::metadata_libra();
-        metadata_libra.verify();
-    }
+        for window in rank.windows(2) {
+            assert!(window[0].1 >= window[1].1, "sorted in descending order");
+        }
+        for (package, count) in &rank {
+            let dependents = graph
+                .query_reverse(iter::once(package.id()))
+                .expect("valid package ID")
+                .resolve()
+                .len();
+            assert_eq!(
+                *count,
+                dependents - 1,
+                "count for {} matches the reverse closure",
+                package.id()
+            );
+            assert!(count <= top_count, "no package ranks above unicode-xid");
+        }
 
-    proptest_suite!(metadata_libra);
+        let testcrate = rank
+            .iter()
+            .find(|(package, _)| package.id().repr() == fixtures::METADATA1_TESTCRATE)
+            .expect("testcrate is ranked");
+        assert_eq!(testcrate.1, 0, "nothing depends on testcrate");
+    }
 
     #[test]
-    fn metadata_libra_f0091a4() {
-        let metadata = Fixture::metadata_libra_f0091a4();
-        metadata.verify();
+    fn duplicate_versions() {
+        let graph = Fixture::metadata_dups().graph();
+        let duplicates: Vec<_> = graph
+            .duplicate_versions()
+            .map(|(name, packages)| {
+                let versions: Vec<_> = packages
+                    .iter()
+                    .map(|package| package.version().to_string())
+                    .collect();
+                (name, versions)
+            })
+            .collect();
+        assert_eq!(
+            duplicates,
+            vec![
+                ("bytes", vec!["0.3.0".to_string(), "0.5.4".to_string()]),
+                (
+                    "lazy_static",
+                    vec!["0.2.11".to_string(), "1.4.0".to_string()]
+                ),
+            ],
+            "incompatible versions are grouped by name"
+        );
+
+        // metadata1 has packages with the same name and version from different sources, which
+        // are compatible.
+        let graph = Fixture::metadata1().graph();
+        assert_eq!(
+            graph.duplicate_versions().count(),
+            0,
+            "compatible versions aren't duplicates"
+        );
     }
 
-    proptest_suite!(metadata_libra_f0091a4);
+    #[test]
+    fn unreachable_from_workspace() {
+        let graph = Fixture::metadata1().graph();
+        let mut unreachable: Vec<_> = graph
+            .unreachable_from_workspace()
+            .map(|package| package.id().repr())
+            .collect();
+        unreachable.sort();
+        let feature_graph = graph.feature_graph();
+        for repr in &unreachable {
+            let package_id = package_id(*repr);
+            assert!(
+                !feature_graph
+                    .reachable_from_workspace(FeatureId::base(&package_id))
+                    .expect("valid feature ID"),
+                "features of {} aren't reachable from the workspace",
+                package_id
+            );
+        }
+        // quote was replaced with a local path through [patch], and walkdir was replaced with a git
+        // repository through [replace]. The original packages remain in the graph but can't be
+        // reached from the workspace.
+        assert_eq!(
+            unreachable,
+            vec![
+                "quote 1.0.2 (registry+https://github.com/rust-lang/crates.io-index)",
+                "walkdir 2.2.9 (registry+https://github.com/rust-lang/crates.io-index)",
+            ],
+            "replaced packages are unreachable from the workspace"
+        );
+        assert_eq!(
+            Fixture::metadata_features1()
+                .graph()
+                .unreachable_from_workspace()
+                .len(),
+            0,
+            "all packages are reachable from the workspace"
+        );
+    }
 
     #[test]
-    fn metadata_libra_9ffd93b() {
-        let metadata = Fixture::metadata_libra_9ffd93b();
+    fn unused_workspace_members() {
+        let names = |graph: &PackageGraph, exclude_entry_points| {
+            graph
+                .unused_workspace_members(exclude_entry_points)
+                .into_iter()
+                .map(|package| package.name().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        // member-b depends on member-a.
+        let graph = Fixture::metadata_features1().graph();
+        assert_eq!(names(graph, false), vec!["member-b"], "features1");
+
+        // Build and dev dependencies count as well.
+        let graph = Fixture::metadata_proc_macro1().graph();
+        assert_eq!(
+            names(graph, false),
+            vec!["build-user", "dev-user", "normal-user"],
+            "proc_macro1"
+        );
+
+        // Every member of a cycle has a dependent.
+        let graph = Fixture::metadata_cycle2().graph();
+        assert!(names(graph, false).is_empty(), "cycle2");
+
+        // testcycles-helper depends on testcycles-base, but isn't in the workspace.
+        let graph = Fixture::metadata_cycle1().graph();
+        assert_eq!(names(graph, false), vec!["testcycles-base"], "cycle1");
+
+        // testcrate is a binary at the root of the workspace.
+        let graph = Fixture::metadata1().graph();
+        assert_eq!(names(graph, false), vec!["testcrate"], "metadata1");
+        assert!(
+            names(graph, true).is_empty(),
+            "metadata1 with entry points excluded"
+        );
+
+        // cycle1's root package is excluded as an entry point, even though it's a library.
+        let graph = Fixture::metadata_cycle1().graph();
+        assert!(
+            names(graph, true).is_empty(),
+            "cycle1 with entry points excluded"
+        );
+        let graph = Fixture::metadata_features1().graph();
+        assert_eq!(
+            names(graph, true),
+            vec!["member-b"],
+            "features1 with entry points excluded"
+        );
+    }
+
+    #[test]
+    fn lock_summary() {
+        let graph = Fixture::metadata1().graph();
+        let summary = graph.resolve_all().to_lock_summary();
+        assert_eq!(
+            summary,
+            graph.resolve_all().to_lock_summary(),
+            "equal resolutions produce identical summaries"
+        );
+        assert_eq!(
+            summary.lines().count(),
+            graph.package_count(),
+            "one line per package"
+        );
+        assert!(
+            summary
+                .lines()
+                .any(|line| line
+                    == "dtoa 0.4.4 registry+https://github.com/rust-lang/crates.io-index"),
+            "registry packages have their source included"
+        );
+        assert!(
+            summary.lines().any(|line| line == "testcrate 0.1.0"),
+            "path packages have no source"
+        );
+
+        // Bump the version of opt-dep and check that exactly one line changes.
+        let original = PackageGraph::from_json(fixtures::METADATA_FEATURES1)
+            .expect("fixture is valid")
+            .resolve_all()
+            .to_lock_summary();
+        let bumped_json = fixtures::METADATA_FEATURES1
+            .replace("opt-dep 0.1.0", "opt-dep 0.2.0")
+            .replace(
+                r#""name":"opt-dep","version":"0.1.0""#,
+                r#""name":"opt-dep","version":"0.2.0""#,
+            );
+        let bumped = PackageGraph::from_json(bumped_json)
+            .expect("bumped fixture is valid")
+            .resolve_all()
+            .to_lock_summary();
+        let changed: Vec<_> = original
+            .lines()
+            .zip(bumped.lines())
+            .filter(|(a, b)| a != b)
+            .collect();
+        assert_eq!(
+            changed,
+            vec![("opt-dep 0.1.0", "opt-dep 0.2.0")],
+            "only opt-dep's line changed"
+        );
+    }
+
+    #[test]
+    fn total_source_size() {
+        use std::fs;
+
+        // Point the no-default1 fixture at a temporary directory.
+        let root = std::env::temp_dir().join(format!("guppy-source-size-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (path, len) in [
+            ("app/Cargo.toml", 10),
+            ("app/src/lib.rs", 20),
+            // The target directory and nested packages are skipped.
+            ("app/target/debug/output", 1000),
+            ("app/nested/Cargo.toml", 100),
+            ("app/nested/src/lib.rs", 100),
+            ("deps/lib/Cargo.toml", 3),
+            ("deps/lib/src/lib.rs", 4),
+        ] {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().expect("path has a parent")).expect("created dir");
+            fs::write(&path, vec![b'x'; len]).expect("wrote file");
+        }
+        let escaped_root = serde_json::to_string(&root).expect("path serialized");
+        let json = fixtures::METADATA_NO_DEFAULT1.replace(
+            "/Users/fakeuser/local/testcrates/no-default",
+            &escaped_root[1..escaped_root.len() - 1],
+        );
+        let graph = PackageGraph::from_json(&json).expect("valid graph");
+
+        // other isn't present on disk.
+        let size = graph
+            .resolve_all()
+            .total_source_size()
+            .expect("sizes computed");
+        assert_eq!(size.bytes(), 37, "total size");
+        let missing: Vec<_> = size
+            .missing()
+            .iter()
+            .map(|package| package.name())
+            .collect();
+        assert_eq!(missing, vec!["other"], "missing packages");
+
+        // Package IDs contain paths, so they've been rewritten as well.
+        let lib = graph
+            .packages()
+            .find(|package| package.name() == "lib")
+            .expect("lib exists");
+        let lib_size = graph
+            .query_forward(iter::once(lib.id()))
+            .expect("valid package ID")
+            .resolve()
+            .total_source_size()
+            .expect("sizes computed");
+        assert_eq!(lib_size.bytes(), 7, "lib size");
+        assert!(lib_size.missing().is_empty(), "lib is present");
+
+        fs::remove_dir_all(&root).expect("removed temporary directory");
+    }
+
+    #[test]
+    fn feature_set_ops() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let resolve = |feature: &str| {
+            feature_graph
+                .query_forward(iter::once((&member_a_id, feature)))
+                .expect("valid feature ID")
+                .resolve()
+        };
+        // "a" enables "b", and both enable the base feature.
+        let a = resolve("a");
+        let b = resolve("b");
+
+        let difference = a.difference(&b);
+        assert_eq!(difference.len(), 1, "only a is in a - b");
+        assert_eq!(difference.contains((&member_a_id, "a")), Some(true));
+        assert!(
+            a.union(&b).symmetric_difference(&a).is_empty(),
+            "a includes b"
+        );
+        assert!(
+            a.intersection(&b).symmetric_difference(&b).is_empty(),
+            "a & b == b"
+        );
+        assert!(
+            a.symmetric_difference(&b)
+                .symmetric_difference(&difference)
+                .is_empty(),
+            "a ^ b == a - b"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "package graphs passed into union() match")]
+    fn feature_set_ops_mismatched_graphs() {
+        let set1 = Fixture::metadata1().graph().feature_graph().resolve_all();
+        let set2 = Fixture::metadata2().graph().feature_graph().resolve_all();
+        set1.union(&set2);
+    }
+
+    #[test]
+    fn try_set_ops_mismatched_graphs() {
+        let graph1 = Fixture::metadata1().graph();
+        let graph2 = Fixture::metadata2().graph();
+
+        let set1 = graph1.resolve_all();
+        let set2 = graph2.resolve_all();
+        assert_matches!(set1.try_union(&set2), Err(Error::MismatchedGraphs));
+        assert_matches!(set1.try_intersection(&set2), Err(Error::MismatchedGraphs));
+        assert_matches!(set1.try_difference(&set2), Err(Error::MismatchedGraphs));
+        assert_matches!(
+            set1.try_symmetric_difference(&set2),
+            Err(Error::MismatchedGraphs)
+        );
+        let union = set1.try_union(&set1).expect("same graph");
+        assert_eq!(union.len(), set1.len(), "union with self");
+
+        let feature_set1 = graph1.feature_graph().resolve_all();
+        let feature_set2 = graph2.feature_graph().resolve_all();
+        assert_matches!(
+            feature_set1.try_union(&feature_set2),
+            Err(Error::MismatchedGraphs)
+        );
+        assert_matches!(
+            feature_set1.try_intersection(&feature_set2),
+            Err(Error::MismatchedGraphs)
+        );
+        assert_matches!(
+            feature_set1.try_difference(&feature_set2),
+            Err(Error::MismatchedGraphs)
+        );
+        assert_matches!(
+            feature_set1.try_symmetric_difference(&feature_set2),
+            Err(Error::MismatchedGraphs)
+        );
+        let difference = feature_set1
+            .try_difference(&feature_set1)
+            .expect("same graph");
+        assert!(difference.is_empty(), "difference with self");
+    }
+
+    #[test]
+    fn feature_reachability() {
+        // Use a fresh graph so that depends_on doesn't use a cached reachability matrix.
+        let package_graph =
+            PackageGraph::from_json(fixtures::METADATA_CYCLE2).expect("valid metadata");
+        let feature_graph = package_graph.feature_graph();
+        let feature_ids: Vec<_> = feature_graph
+            .resolve_all()
+            .feature_ids(DependencyDirection::Forward)
+            .collect();
+        let pairs: Vec<_> = feature_ids
+            .iter()
+            .flat_map(|a| feature_ids.iter().map(move |b| (*a, *b)))
+            .collect();
+        let expected: Vec<_> = pairs
+            .iter()
+            .map(|(a, b)| feature_graph.depends_on(*a, *b).expect("valid feature IDs"))
+            .collect();
+        assert!(
+            expected.iter().any(|x| *x),
+            "some features depend on others"
+        );
+        assert!(
+            !expected.iter().all(|x| *x),
+            "not every feature depends on others"
+        );
+
+        let reachability = feature_graph.reachability();
+        assert!(
+            reachability.scc_count() < feature_ids.len(),
+            "cycle2 has feature cycles"
+        );
+        for ((a, b), expected) in pairs.iter().zip(&expected) {
+            assert_eq!(
+                reachability.depends_on(*a, *b).expect("valid feature IDs"),
+                *expected,
+                "reachability matches for {:?} -> {:?}",
+                a,
+                b
+            );
+            assert_eq!(
+                feature_graph.depends_on(*a, *b).expect("valid feature IDs"),
+                *expected,
+                "depends_on with reachability matches for {:?} -> {:?}",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn owned_package_set() {
+        let graph = Fixture::metadata1().graph();
+        let package_set = graph
+            .query_workspace()
+            .resolve_with_fn(|_, link| !link.dev_only());
+        let owned = package_set.to_owned();
+        assert_eq!(owned.len(), package_set.len(), "lengths match");
+
+        let bound = owned.bind(graph).expect("all package IDs are known");
+        assert!(
+            bound.symmetric_difference(&package_set).is_empty(),
+            "binding produces an equal set"
+        );
+        assert_eq!(bound.to_owned(), owned, "round trip is stable");
+
+        // Binding to a graph without these packages fails.
+        let other_graph = Fixture::metadata2().graph();
+        match owned.bind(other_graph) {
+            Err(Error::UnknownPackageId(package_id)) => assert!(
+                owned.contains(&package_id),
+                "unknown package ID comes from the owned set"
+            ),
+            other => panic!("expected UnknownPackageId, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn owned_package_ids() {
+        let graph = Fixture::metadata1().graph();
+        let package_set = graph
+            .query_workspace()
+            .resolve_with_fn(|_, link| !link.dev_only());
+        let ids = package_set.to_owned_ids(DependencyDirection::Forward);
+        let expected: Vec<_> = package_set
+            .package_ids(DependencyDirection::Forward)
+            .cloned()
+            .collect();
+        assert_eq!(ids, expected, "IDs are returned in topological order");
+
+        let resolved = graph
+            .resolve_ids(ids.iter().cloned())
+            .expect("all package IDs are known");
+        assert!(
+            resolved.symmetric_difference(&package_set).is_empty(),
+            "resolving IDs produces an equal set"
+        );
+
+        let other_graph = Fixture::metadata2().graph();
+        match other_graph.resolve_ids(ids) {
+            Err(Error::UnknownPackageId(package_id)) => assert!(
+                package_set.contains(&package_id) == Some(true),
+                "unknown package ID comes from the original set"
+            ),
+            other => panic!("expected UnknownPackageId, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn retain_edges() {
+        let mut graph = Fixture::metadata_features1().graph().clone();
+        let dev_only_id = package_id(METADATA_FEATURES1_DEV_ONLY);
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        // Build the feature graph to ensure it gets invalidated.
+        assert_eq!(
+            graph
+                .feature_graph()
+                .depends_on(FeatureId::base(&member_a_id), FeatureId::base(&dev_only_id))
+                .expect("valid feature IDs"),
+            true,
+            "member-a depends on dev-only before links are removed"
+        );
+
+        let link_count = graph.link_count();
+        graph.retain_edges(|_, link| *link.to().id() != dev_only_id);
+        graph.verify().expect("graph is still valid");
+
+        assert_eq!(graph.link_count(), link_count - 1, "one link was removed");
+        assert_eq!(
+            graph.metadata(&dev_only_id).map(|package| package.id()),
+            Some(&dev_only_id),
+            "packages aren't removed"
+        );
+        assert_eq!(
+            graph.query_workspace().resolve().contains(&dev_only_id),
+            Some(false),
+            "dev-only is no longer reachable from the workspace"
+        );
+        assert_eq!(
+            graph
+                .feature_graph()
+                .depends_on(FeatureId::base(&member_a_id), FeatureId::base(&dev_only_id))
+                .expect("valid feature IDs"),
+            false,
+            "feature graph is rebuilt"
+        );
+    }
+
+    #[test]
+    fn retain_edges_cycles() {
+        let upper_a = package_id(fixtures::METADATA_CYCLE2_UPPER_A);
+        let upper_b = package_id(fixtures::METADATA_CYCLE2_UPPER_B);
+        let lower_a = package_id(fixtures::METADATA_CYCLE2_LOWER_A);
+        let lower_b = package_id(fixtures::METADATA_CYCLE2_LOWER_B);
+
+        fn sorted_cycles(graph: &PackageGraph) -> Vec<Vec<&PackageId>> {
+            let mut cycles: Vec<_> = graph
+                .cycles()
+                .all_cycles()
+                .map(|mut cycle| {
+                    cycle.sort();
+                    cycle
+                })
+                .collect();
+            cycles.sort();
+            cycles
+        }
+
+        fn assert_topo_order(graph: &PackageGraph) {
+            let condensation = graph.condensation();
+            let order: HashMap<_, _> = graph
+                .resolve_all()
+                .package_ids(DependencyDirection::Forward)
+                .enumerate()
+                .map(|(idx, package_id)| (package_id, idx))
+                .collect();
+            for link in graph.resolve_all().links(DependencyDirection::Forward) {
+                let (from, to) = (link.from().id(), link.to().id());
+                if condensation.component_for(from) != condensation.component_for(to) {
+                    assert!(
+                        order[from] < order[to],
+                        "{} is before {} in forward topo order",
+                        from,
+                        to
+                    );
+                }
+            }
+        }
+
+        // Cycles are computed before links are removed here, so they're updated incrementally.
+        let mut incremental = Fixture::metadata_cycle2().graph().clone();
+        assert!(incremental.is_cyclic(), "graph starts out cyclic");
+        // Cycles are computed from scratch here.
+        let mut fresh = Fixture::metadata_cycle2().graph().clone();
+        fresh.invalidate_caches();
+
+        let remove_upper = |_: &PackageGraph, link: PackageLink<'_>| {
+            !(*link.from().id() == upper_b && *link.to().id() == upper_a)
+        };
+        incremental.retain_edges(remove_upper);
+        fresh.retain_edges(remove_upper);
+        assert_eq!(
+            sorted_cycles(&incremental),
+            vec![vec![&lower_a, &lower_b]],
+            "upper cycle is broken"
+        );
+        assert_eq!(
+            sorted_cycles(&incremental),
+            sorted_cycles(&fresh),
+            "incremental update matches recomputation"
+        );
+        assert_eq!(
+            incremental.condensation().component_count(),
+            3,
+            "upper-a, upper-b and the lower cycle"
+        );
+        assert_topo_order(&incremental);
+
+        incremental
+            .retain_edges(|_, link| !(*link.from().id() == lower_b && *link.to().id() == lower_a));
+        assert!(!incremental.is_cyclic(), "both cycles are broken");
+        assert_eq!(
+            incremental.condensation().component_count(),
+            4,
+            "every package is its own component"
+        );
+        assert_topo_order(&incremental);
+    }
+
+    #[test]
+    fn links_len() {
+        let graph = Fixture::metadata_cycle2().graph();
+        let upper_a = package_id(fixtures::METADATA_CYCLE2_UPPER_A);
+        let upper_b = package_id(fixtures::METADATA_CYCLE2_UPPER_B);
+
+        // Rejecting the link back into upper-a leaves only part of the upper cycle in the set.
+        let package_set = graph
+            .query_forward(iter::once(&upper_b))
+            .expect("valid package ID")
+            .resolve_with_fn(|_, link| {
+                !(*link.from().id() == upper_b && *link.to().id() == upper_a)
+            });
+        for direction in &[DependencyDirection::Forward, DependencyDirection::Reverse] {
+            let mut links = package_set.links(*direction);
+            assert_eq!(links.len(), 3, "{:?}: links len is exact", direction);
+            links.next();
+            assert_eq!(
+                links.size_hint(),
+                (2, Some(2)),
+                "{:?}: size hint goes down after next",
+                direction
+            );
+            assert_eq!(links.count(), 2, "{:?}: remaining links", direction);
+        }
+
+        let package_set = graph.resolve_all();
+        assert_eq!(
+            package_set.links(DependencyDirection::Forward).len(),
+            5,
+            "all links in the graph"
+        );
+    }
+
+    #[test]
+    fn explain() {
+        let graph = Fixture::metadata_features1().graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+        let opt_dep_id = package_id(METADATA_FEATURES1_OPT_DEP);
+        let dev_only_id = package_id(METADATA_FEATURES1_DEV_ONLY);
+
+        // (from name, to name, kinds, optional) for each link in each chain.
+        type ChainSummary<'g> = Vec<(&'g str, &'g str, Vec<DependencyKind>, bool)>;
+
+        fn summarize<'g>(explanation: &Explanation<'g>) -> Vec<ChainSummary<'g>> {
+            let mut chains: Vec<_> = explanation
+                .chains()
+                .iter()
+                .map(|chain| {
+                    chain
+                        .iter()
+                        .map(|explanation_link| {
+                            let link = explanation_link.link();
+                            (
+                                link.from().name(),
+                                link.to().name(),
+                                explanation_link.kinds().to_vec(),
+                                explanation_link.is_optional(),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            chains.sort_by_key(|chain| {
+                chain
+                    .iter()
+                    .map(|(from, to, _, _)| (*from, *to))
+                    .collect::<Vec<_>>()
+            });
+            chains
+        }
+
+        let explanation = graph.explain(&dep_c_id).expect("valid package ID");
+        assert_eq!(explanation.package().id(), &dep_c_id, "package is recorded");
+        assert_eq!(
+            summarize(&explanation),
+            vec![
+                vec![("member-a", "dep-c", vec![DependencyKind::Normal], false)],
+                vec![(
+                    "member-b",
+                    "dep-c",
+                    vec![DependencyKind::Normal, DependencyKind::Build],
+                    false
+                )],
+            ],
+            "chains stop at the first workspace member"
+        );
+
+        let explanation = graph.explain(&opt_dep_id).expect("valid package ID");
+        assert_eq!(
+            summarize(&explanation),
+            vec![vec![(
+                "member-a",
+                "opt-dep",
+                vec![DependencyKind::Normal],
+                true
+            )]],
+            "optional dependency"
+        );
+
+        let explanation = graph.explain(&dev_only_id).expect("valid package ID");
+        assert_eq!(
+            summarize(&explanation),
+            vec![vec![(
+                "member-a",
+                "dev-only",
+                vec![DependencyKind::Development],
+                false
+            )]],
+            "dev-only dependency"
+        );
+
+        let explanation = graph.explain(&member_a_id).expect("valid package ID");
+        assert_eq!(
+            summarize(&explanation),
+            vec![vec![(
+                "member-b",
+                "member-a",
+                vec![DependencyKind::Normal],
+                false
+            )]],
+            "workspace members are explained through other members"
+        );
+        assert!(
+            graph
+                .explain(&member_b_id)
+                .expect("valid package ID")
+                .chains()
+                .is_empty(),
+            "nothing depends on member-b"
+        );
+
+        // testcycles-helper has a dev-dependency back on testcycles-base.
+        let graph = Fixture::metadata_cycle1().graph();
+        let base_id = package_id(fixtures::METADATA_CYCLE1_BASE);
+        let helper_id = package_id(fixtures::METADATA_CYCLE1_HELPER);
+        assert_eq!(
+            graph
+                .explain(&helper_id)
+                .expect("valid package ID")
+                .chains()
+                .len(),
+            1,
+            "testcycles-base depends on testcycles-helper"
+        );
+        assert!(
+            graph
+                .explain(&base_id)
+                .expect("valid package ID")
+                .chains()
+                .is_empty(),
+            "cycles back into the package aren't followed"
+        );
+
+        assert_matches!(
+            graph.explain(&package_id("unknown 0.1.0")),
+            Err(Error::UnknownPackageId(_)),
+            "unknown package"
+        );
+    }
+
+    #[test]
+    fn resolve_excluding() {
+        let graph = Fixture::metadata_features1().graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        fn sorted_names<'g>(package_set: &PackageSet<'g>) -> Vec<&'g str> {
+            let mut names: Vec<_> = package_set
+                .packages(DependencyDirection::Forward)
+                .map(|package| package.name())
+                .collect();
+            names.sort_unstable();
+            names
+        }
+
+        // dev-only and opt-dep are only reachable through member-a, so they're pruned. dep-c is
+        // still reachable directly from member-b.
+        let excluding = graph
+            .query_workspace()
+            .resolve_excluding(iter::once(&member_a_id))
+            .expect("valid package ID");
+        assert_eq!(
+            sorted_names(&excluding),
+            vec!["dep-c", "member-b"],
+            "member-a and its exclusive dependencies are pruned"
+        );
+        let difference = graph.query_workspace().resolve().difference(
+            &graph
+                .resolve_ids(iter::once(member_a_id.clone()))
+                .expect("valid package ID"),
+        );
+        assert_eq!(
+            sorted_names(&difference),
+            vec!["dep-c", "dev-only", "member-b", "opt-dep"],
+            "difference doesn't prune dependencies"
+        );
+
+        // Excluded initials are left out.
+        let excluding = graph
+            .query_forward(iter::once(&member_a_id))
+            .expect("valid package ID")
+            .resolve_excluding(iter::once(&member_a_id))
+            .expect("valid package ID");
+        assert!(excluding.is_empty(), "excluded initial is left out");
+
+        let excluding = graph
+            .query_reverse(iter::once(&package_id(METADATA_FEATURES1_DEP_C)))
+            .expect("valid package ID")
+            .resolve_excluding(iter::once(&member_a_id))
+            .expect("valid package ID");
+        assert_eq!(
+            sorted_names(&excluding),
+            vec!["dep-c", "member-b"],
+            "reverse query"
+        );
+
+        assert_matches!(
+            graph
+                .query_workspace()
+                .resolve_excluding(iter::once(&package_id("unknown 0.1.0"))),
+            Err(Error::UnknownPackageId(_)),
+            "unknown excluded package"
+        );
+    }
+
+    #[test]
+    fn resolve_with_depth() {
+        let graph = Fixture::metadata_features1().graph();
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+        fn sorted_names(package_set: PackageSet<'_>) -> Vec<&str> {
+            let mut names: Vec<_> = package_set
+                .into_iter()
+                .map(|package| package.name())
+                .collect();
+            names.sort_unstable();
+            names
+        }
+        let query = || {
+            graph
+                .query_forward(iter::once(&member_b_id))
+                .expect("valid package ID")
+        };
+
+        assert_eq!(
+            sorted_names(query().resolve_with_depth(0)),
+            vec!["member-b"],
+            "depth 0 only has the initials"
+        );
+        // dep-c is a direct dependency of member-b, and also reachable through member-a.
+        assert_eq!(
+            sorted_names(query().resolve_with_depth(1)),
+            vec!["dep-c", "member-a", "member-b"],
+            "depth 1 has direct dependencies"
+        );
+        assert_eq!(
+            sorted_names(query().resolve_with_depth(2)),
+            sorted_names(query().resolve()),
+            "depth 2 has everything"
+        );
+
+        let reverse = graph
+            .query_reverse(iter::once(&package_id(METADATA_FEATURES1_DEP_C)))
+            .expect("valid package ID")
+            .resolve_with_depth(1);
+        assert_eq!(
+            sorted_names(reverse),
+            vec!["dep-c", "member-a", "member-b"],
+            "reverse depth 1 has direct dependents"
+        );
+    }
+
+    #[test]
+    fn links_with_depth() {
+        let graph = Fixture::metadata_features1().graph();
+        let package_set = graph.query_workspace().resolve();
+        let depths = |direction| {
+            let mut depths: Vec<_> = package_set
+                .links_with_depth(direction)
+                .map(|(link, depth)| (link.from().name(), link.to().name(), depth))
+                .collect();
+            depths.sort_unstable();
+            depths
+        };
+
+        // member-b is the only forward root. dep-c is reachable at depth 1 directly from
+        // member-b, and at depth 2 through member-a.
+        assert_eq!(
+            depths(DependencyDirection::Forward),
+            vec![
+                ("member-a", "dep-c", 1),
+                ("member-a", "dev-only", 2),
+                ("member-a", "opt-dep", 2),
+                ("member-b", "dep-c", 1),
+                ("member-b", "member-a", 1),
+            ],
+            "forward depths"
+        );
+        // dep-c, dev-only and opt-dep are the reverse roots.
+        assert_eq!(
+            depths(DependencyDirection::Reverse),
+            vec![
+                ("member-a", "dep-c", 1),
+                ("member-a", "dev-only", 1),
+                ("member-a", "opt-dep", 1),
+                ("member-b", "dep-c", 1),
+                ("member-b", "member-a", 1),
+            ],
+            "reverse depths"
+        );
+    }
+
+    #[test]
+    fn package_set_into_iter() {
+        let graph = Fixture::metadata1().graph();
+        let package_set = graph.query_workspace().resolve();
+        let expected: Vec<_> = package_set
+            .package_ids(DependencyDirection::Forward)
+            .collect();
+
+        fn consume(package_set: PackageSet<'_>) -> Vec<&PackageId> {
+            let mut ids = vec![];
+            for package in package_set {
+                ids.push(package.id());
+            }
+            ids
+        }
+        assert_eq!(
+            consume(package_set),
+            expected,
+            "into_iter is in forward topo order"
+        );
+    }
+
+    #[test]
+    fn for_each_package() {
+        let graph = Fixture::metadata1().graph();
+        let package_set = graph.resolve_all();
+
+        for direction in [DependencyDirection::Forward, DependencyDirection::Reverse] {
+            let expected: Vec<_> = package_set.package_ids(direction).collect();
+            let mut visited = vec![];
+            let result = package_set.for_each_package(direction, |package| {
+                visited.push(package.id());
+                ControlFlow::Continue(())
+            });
+            assert_eq!(
+                result,
+                ControlFlow::Continue(()),
+                "{:?}: traversal completed",
+                direction
+            );
+            assert_eq!(visited, expected, "{:?}: topological order", direction);
+
+            // Stop once the package in the middle is found.
+            let target = expected[expected.len() / 2];
+            let mut visited = vec![];
+            let result = package_set.for_each_package(direction, |package| {
+                visited.push(package.id());
+                if package.id() == target {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            });
+            assert_eq!(
+                result,
+                ControlFlow::Break(()),
+                "{:?}: traversal stopped",
+                direction
+            );
+            assert_eq!(
+                visited,
+                &expected[..=expected.len() / 2],
+                "{:?}: packages after the target aren't visited",
+                direction
+            );
+        }
+    }
+
+    #[test]
+    fn package_set_contains_many() {
+        let graph = Fixture::metadata_features1().graph();
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+        let opt_dep_id = package_id(METADATA_FEATURES1_OPT_DEP);
+        let unknown_id = package_id("unknown 0.1.0");
+
+        // dep-c has no dependencies of its own.
+        let package_set = graph
+            .query_forward(iter::once(&dep_c_id))
+            .expect("valid package ID")
+            .resolve();
+
+        assert_eq!(
+            package_set.contains_all(vec![&dep_c_id, &member_b_id, &unknown_id]),
+            vec![true, false, false],
+            "contains_all reports each ID in order"
+        );
+        assert!(
+            package_set.contains_any(vec![&unknown_id, &member_b_id, &dep_c_id]),
+            "contains_any finds dep-c"
+        );
+        assert!(
+            !package_set.contains_any(vec![&member_b_id, &opt_dep_id]),
+            "contains_any with no matches"
+        );
+        assert!(
+            package_set.contains_none(vec![&member_b_id, &unknown_id]),
+            "contains_none with no matches"
+        );
+        assert!(
+            !package_set.contains_none(vec![&opt_dep_id, &dep_c_id]),
+            "contains_none finds dep-c"
+        );
+        assert!(
+            package_set.contains_all(vec![]).is_empty(),
+            "contains_all with no IDs"
+        );
+    }
+
+    #[test]
+    fn package_set_filter() {
+        let graph = Fixture::metadata_features1().graph();
+        let all = graph.resolve_all();
+
+        let mut visited = vec![];
+        let members = all.filter(DependencyDirection::Reverse, |package| {
+            visited.push(package.id());
+            package.name().starts_with("member-")
+        });
+        let expected: Vec<_> = all.package_ids(DependencyDirection::Reverse).collect();
+        assert_eq!(
+            visited, expected,
+            "predicate is called in reverse topo order"
+        );
+
+        let mut retained = all.clone();
+        retained.retain(|package| package.name().starts_with("member-"));
+        assert_eq!(
+            members
+                .package_ids(DependencyDirection::Forward)
+                .collect::<Vec<_>>(),
+            retained
+                .package_ids(DependencyDirection::Forward)
+                .collect::<Vec<_>>(),
+            "filter matches retain"
+        );
+        assert_eq!(members.len(), 2, "two workspace members");
+        assert_eq!(
+            all.len(),
+            graph.package_count(),
+            "original set is unchanged"
+        );
+    }
+
+    #[test]
+    fn display_graphml() {
+        let graph = Fixture::metadata_features1().graph();
+        let package_set = graph
+            .query_forward(iter::once(&package_id(METADATA_FEATURES1_MEMBER_A)))
+            .expect("valid package ID")
+            .resolve();
+        let graphml = format!("{}", package_set.display_graphml());
+
+        assert!(
+            graphml.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#),
+            "XML declaration is present"
+        );
+        assert!(
+            graphml.trim_end().ends_with("</graphml>"),
+            "document is closed"
+        );
+        assert_eq!(
+            graphml.matches("<node ").count(),
+            package_set.len(),
+            "one node per package"
+        );
+        assert_eq!(
+            graphml.matches("<edge ").count(),
+            package_set.links(DependencyDirection::Forward).count(),
+            "one edge per link"
+        );
+        for name in ["member-a", "dep-c", "opt-dep", "dev-only"] {
+            assert!(
+                graphml.contains(&format!(r#"<data key="d1">{}</data>"#, name)),
+                "node for {} is present",
+                name
+            );
+        }
+        assert!(
+            !graphml.contains(">member-b<"),
+            "member-b is in the graph but not the set"
+        );
+        assert!(
+            graphml.contains(r#"<data key="d5">true</data>"#),
+            "dev-only is a dev dependency"
+        );
+    }
+
+    #[test]
+    fn partition_workspace() {
+        let graph = Fixture::metadata_features1().graph();
+        let package_set = graph.query_workspace().resolve();
+        let (workspace, third_party) = package_set.partition_workspace();
+
+        let mut workspace_names: Vec<_> = workspace
+            .packages(DependencyDirection::Forward)
+            .map(|package| package.name())
+            .collect();
+        workspace_names.sort_unstable();
+        assert_eq!(
+            workspace_names,
+            vec!["member-a", "member-b"],
+            "workspace members"
+        );
+        assert!(
+            third_party
+                .packages(DependencyDirection::Forward)
+                .all(|package| !package.in_workspace()),
+            "no workspace members in the other set"
+        );
+        assert!(
+            workspace.intersection(&third_party).is_empty(),
+            "sets are disjoint"
+        );
+        assert!(
+            workspace
+                .union(&third_party)
+                .symmetric_difference(&package_set)
+                .is_empty(),
+            "sets add up to the original"
+        );
+    }
+
+    #[test]
+    fn workspace_members() {
+        let graph = Fixture::metadata_cycle2().graph();
+        let names: Vec<_> = graph
+            .workspace_members()
+            .map(|package| {
+                assert!(
+                    package.in_workspace(),
+                    "{} is in the workspace",
+                    package.name()
+                );
+                package.name()
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec!["lower-a", "lower-b", "upper-a", "upper-b"],
+            "members are sorted by name"
+        );
+
+        let graph = Fixture::metadata_features1().graph();
+        assert_eq!(
+            graph.workspace_members().len(),
+            graph.workspace().member_ids().len(),
+            "third-party packages aren't included"
+        );
+    }
+
+    #[test]
+    fn recording_resolver() {
+        struct NoDevOnly;
+
+        impl<'g> PackageResolver<'g> for NoDevOnly {
+            fn accept(&mut self, _query: &PackageQuery<'g>, link: PackageLink<'g>) -> bool {
+                !link.dev_only()
+            }
+        }
+
+        let graph = Fixture::metadata_features1().graph();
+        let mut recorder = RecordingResolver::new(NoDevOnly);
+        let package_set = graph.query_workspace().resolve_with(&mut recorder);
+        let expected = graph
+            .query_workspace()
+            .resolve_with_fn(|_, link| !link.dev_only());
+        assert!(
+            package_set.symmetric_difference(&expected).is_empty(),
+            "recording doesn't change the result"
+        );
+
+        let dev_only_id = package_id(METADATA_FEATURES1_DEV_ONLY);
+        assert_eq!(
+            recorder.rejected().map(|(_, to)| to).collect::<Vec<_>>(),
+            vec![&dev_only_id],
+            "the dev-only link is rejected"
+        );
+        assert_eq!(
+            package_set.contains(&dev_only_id),
+            Some(false),
+            "dev-only isn't included"
+        );
+        for &(from, to, accepted) in recorder.decisions() {
+            let link = graph
+                .metadata(from)
+                .expect("valid package ID")
+                .direct_links()
+                .find(|link| link.to().id() == to)
+                .expect("recorded link exists");
+            assert_eq!(accepted, !link.dev_only(), "{} -> {}", from, to);
+            if accepted {
+                assert_eq!(package_set.contains(to), Some(true), "{} is included", to);
+            }
+        }
+    }
+
+    #[test]
+    fn and_or_resolvers() {
+        struct NoDevOnly;
+
+        impl<'g> PackageResolver<'g> for NoDevOnly {
+            fn accept(&mut self, _query: &PackageQuery<'g>, link: PackageLink<'g>) -> bool {
+                !link.dev_only()
+            }
+        }
+
+        // Rejects links that don't apply to the given platform.
+        struct OnPlatform<'p>(Platform<'p>);
+
+        impl<'g, 'p> PackageResolver<'g> for OnPlatform<'p> {
+            fn accept(&mut self, _query: &PackageQuery<'g>, link: PackageLink<'g>) -> bool {
+                [link.normal(), link.build(), link.dev()]
+                    .iter()
+                    .any(|req| req.status().enabled_on(&self.0) != EnabledTernary::Disabled)
+            }
+        }
+
+        fn on_windows() -> OnPlatform<'static> {
+            OnPlatform(
+                Platform::new("x86_64-pc-windows-msvc", TargetFeatures::Unknown)
+                    .expect("known triple"),
+            )
+        }
+
+        let graph = Fixture::metadata_targets1().graph();
+        let mut windows = on_windows();
+        let expected_and = graph
+            .query_workspace()
+            .resolve_with_fn(|query, link| !link.dev_only() && windows.accept(query, link));
+        let mut windows = on_windows();
+        let expected_or = graph
+            .query_workspace()
+            .resolve_with_fn(|query, link| !link.dev_only() || windows.accept(query, link));
+
+        let and_set = graph.query_workspace().resolve_with(AndResolver::new(vec![
+            Box::new(NoDevOnly) as Box<dyn PackageResolver<'_>>,
+            Box::new(on_windows()),
+        ]));
+        assert!(
+            and_set.symmetric_difference(&expected_and).is_empty(),
+            "AndResolver requires both resolvers to accept"
+        );
+        let or_set = graph.query_workspace().resolve_with(OrResolver::new(vec![
+            Box::new(NoDevOnly) as Box<dyn PackageResolver<'_>>,
+            Box::new(on_windows()),
+        ]));
+        assert!(
+            or_set.symmetric_difference(&expected_or).is_empty(),
+            "OrResolver requires either resolver to accept"
+        );
+        assert!(
+            and_set.len() < or_set.len(),
+            "resolvers differ on this graph"
+        );
+
+        // AND stops at the first rejection, and OR stops at the first acceptance.
+        let mut recorder = RecordingResolver::new(on_windows());
+        graph.query_workspace().resolve_with(AndResolver::new(vec![
+            Box::new(NoDevOnly) as Box<dyn PackageResolver<'_>>,
+            Box::new(&mut recorder),
+        ]));
+        assert!(
+            recorder
+                .decisions()
+                .iter()
+                .all(|(from, to, _)| !is_dev_only(graph, from, to)),
+            "dev-only links aren't passed on by AndResolver"
+        );
+        let mut recorder = RecordingResolver::new(on_windows());
+        graph.query_workspace().resolve_with(OrResolver::new(vec![
+            Box::new(NoDevOnly) as Box<dyn PackageResolver<'_>>,
+            Box::new(&mut recorder),
+        ]));
+        assert!(
+            recorder
+                .decisions()
+                .iter()
+                .all(|(from, to, _)| is_dev_only(graph, from, to)),
+            "only dev-only links are passed on by OrResolver"
+        );
+
+        fn is_dev_only(graph: &PackageGraph, from: &PackageId, to: &PackageId) -> bool {
+            graph
+                .metadata(from)
+                .expect("valid package ID")
+                .direct_links()
+                .find(|link| link.to().id() == to)
+                .expect("link exists")
+                .dev_only()
+        }
+
+        assert_eq!(
+            graph
+                .query_workspace()
+                .resolve_with(OrResolver::new(vec![]))
+                .len(),
+            graph.workspace().member_ids().len(),
+            "an empty OrResolver rejects every link"
+        );
+    }
+
+    #[test]
+    fn boundary_ids() {
+        let graph = Fixture::metadata1().graph();
+        let package_set = graph.query_workspace().resolve();
+        let (forward, reverse) = package_set.boundary_ids();
+        for (ids, direction) in [
+            (forward, DependencyDirection::Forward),
+            (reverse, DependencyDirection::Reverse),
+        ] {
+            let mut ids = ids;
+            ids.sort();
+            let mut expected: Vec<_> = package_set.root_ids(direction).collect();
+            expected.sort();
+            assert_eq!(
+                ids, expected,
+                "boundary IDs match root IDs ({:?})",
+                direction
+            );
+        }
+
+        // A package by itself is both a forward and a reverse root.
+        let testcrate_id = package_id(fixtures::METADATA1_TESTCRATE);
+        let isolated = graph
+            .resolve_ids(iter::once(testcrate_id.clone()))
+            .expect("valid package ID");
+        assert_eq!(
+            isolated.boundary_ids(),
+            (vec![&testcrate_id], vec![&testcrate_id]),
+            "isolated package is in both sets"
+        );
+    }
+
+    #[test]
+    fn dependent_count() {
+        let graph = Fixture::metadata_features1().graph();
+        let package_set = graph.query_workspace().resolve();
+        for (id, count) in [
+            (METADATA_FEATURES1_DEP_C, 2),
+            (METADATA_FEATURES1_DEV_ONLY, 2),
+            (METADATA_FEATURES1_MEMBER_A, 1),
+            (METADATA_FEATURES1_MEMBER_B, 0),
+        ] {
+            assert_eq!(
+                package_set.dependent_count(&package_id(id)),
+                Some(count),
+                "dependent count for {}",
+                id
+            );
+        }
+
+        // Dependents outside the set aren't counted.
+        let member_a = graph
+            .query_forward(iter::once(&package_id(METADATA_FEATURES1_MEMBER_A)))
+            .expect("valid package ID")
+            .resolve();
+        assert_eq!(
+            member_a.dependent_count(&package_id(METADATA_FEATURES1_DEP_C)),
+            Some(1),
+            "member-b isn't in the set"
+        );
+        assert_eq!(
+            member_a.dependent_count(&package_id(METADATA_FEATURES1_MEMBER_B)),
+            None,
+            "package not in the set"
+        );
+        assert_eq!(
+            member_a.dependent_count(&package_id("unknown 0.1.0")),
+            None,
+            "unknown package"
+        );
+    }
+
+    #[test]
+    fn metadata2() {
+        let metadata2 = Fixture::metadata2();
+        metadata2.verify();
+
+        let feature_graph = metadata2.graph().feature_graph();
+        assert_eq!(feature_graph.feature_count(), 472, "feature count");
+        assert_eq!(feature_graph.link_count(), 571, "link count");
+        let root_ids: Vec<_> = feature_graph
+            .query_workspace(none_filter())
+            .resolve()
+            .root_ids(DependencyDirection::Forward)
+            .collect();
+        let testcrate_id = fixtures::package_id(fixtures::METADATA2_TESTCRATE);
+        let expected = vec![FeatureId::base(&testcrate_id)];
+        assert_eq!(root_ids, expected, "feature graph root IDs match");
+    }
+
+    proptest_suite!(metadata2);
+
+    #[test]
+    fn metadata_dups() {
+        let metadata_dups = Fixture::metadata_dups();
+        metadata_dups.verify();
+    }
+
+    proptest_suite!(metadata_dups);
+
+    #[test]
+    fn metadata_cycle1() {
+        let metadata_cycle1 = Fixture::metadata_cycle1();
+        metadata_cycle1.verify();
+
+        // The only cycle is through a dev-dependency.
+        let graph = metadata_cycle1.graph();
+        let base_id = fixtures::package_id(fixtures::METADATA_CYCLE1_BASE);
+        let helper_id = fixtures::package_id(fixtures::METADATA_CYCLE1_HELPER);
+        for dev_only in &[false, true] {
+            let cycles: Vec<_> = graph
+                .cycles()
+                .all_cycles_filtered(*dev_only)
+                .map(|mut cycle| {
+                    cycle.sort();
+                    cycle
+                })
+                .collect();
+            assert_eq!(
+                cycles,
+                vec![vec![&base_id, &helper_id]],
+                "cycles with dev_only = {}",
+                dev_only
+            );
+        }
+
+        let feature_cycles = graph.feature_graph().cycles();
+        let all_feature_cycles: Vec<_> = feature_cycles.all_cycles().collect();
+        assert!(!all_feature_cycles.is_empty(), "feature cycles exist");
+        assert!(graph.feature_graph().is_cyclic(), "feature graph is cyclic");
+        assert_eq!(
+            feature_cycles.all_cycles_filtered(true).count(),
+            all_feature_cycles.len(),
+            "all feature cycles are through dev-dependencies"
+        );
+        assert_eq!(
+            feature_cycles.feature_only_cycles().count(),
+            0,
+            "all feature cycles have matching package cycles"
+        );
+    }
+
+    proptest_suite!(metadata_cycle1);
+
+    #[test]
+    fn metadata_cycle2() {
+        let metadata_cycle2 = Fixture::metadata_cycle2();
+        metadata_cycle2.verify();
+    }
+
+    proptest_suite!(metadata_cycle2);
+
+    #[test]
+    fn metadata_feature_cycle1() {
+        let metadata = Fixture::metadata_feature_cycle1();
+        metadata.verify();
+
+        let graph = metadata.graph();
+        let cyc_a_id = package_id(fixtures::METADATA_FEATURE_CYCLE1_CYC_A);
+        let cyc_b_id = package_id(fixtures::METADATA_FEATURE_CYCLE1_CYC_B);
+        let sorted = |cycles: &mut dyn Iterator<Item = Vec<FeatureId<'_>>>| {
+            let mut cycles: Vec<_> = cycles
+                .map(|cycle| {
+                    let mut cycle: Vec<(PackageId, Option<String>)> = cycle
+                        .into_iter()
+                        .map(|feature_id| feature_id.into())
+                        .collect();
+                    cycle.sort();
+                    cycle
+                })
+                .collect();
+            cycles.sort();
+            cycles
+        };
+
+        let feature_cycles = graph.feature_graph().cycles();
+        // The base features form a cycle through the dev-dependency, matching the package cycle.
+        assert_eq!(
+            sorted(&mut feature_cycles.all_cycles()),
+            vec![
+                vec![(cyc_a_id.clone(), None), (cyc_b_id.clone(), None)],
+                vec![
+                    (cyc_a_id.clone(), Some("x".to_string())),
+                    (cyc_a_id.clone(), Some("y".to_string())),
+                ],
+            ],
+            "all feature cycles"
+        );
+        // x and y enable each other, which is a cycle that only exists at the feature level.
+        assert_eq!(
+            sorted(&mut feature_cycles.feature_only_cycles()),
+            vec![vec![
+                (cyc_a_id.clone(), Some("x".to_string())),
+                (cyc_a_id, Some("y".to_string())),
+            ]],
+            "feature-only cycles"
+        );
+    }
+
+    #[test]
+    fn condensation() {
+        let graph = Fixture::metadata_cycle2().graph();
+        let condensation = graph.condensation();
+        assert_eq!(
+            condensation.component_count(),
+            2,
+            "two cycles of two packages each"
+        );
+
+        let upper_a = package_id(fixtures::METADATA_CYCLE2_UPPER_A);
+        let upper_b = package_id(fixtures::METADATA_CYCLE2_UPPER_B);
+        let lower_a = package_id(fixtures::METADATA_CYCLE2_LOWER_A);
+        let lower_b = package_id(fixtures::METADATA_CYCLE2_LOWER_B);
+        let upper = condensation
+            .component_for(&upper_a)
+            .expect("valid package ID");
+        let lower = condensation
+            .component_for(&lower_a)
+            .expect("valid package ID");
+        assert_eq!(
+            condensation.component_for(&upper_b),
+            Some(upper),
+            "upper-a and upper-b are in the same component"
+        );
+        assert_eq!(
+            condensation.component_for(&lower_b),
+            Some(lower),
+            "lower-a and lower-b are in the same component"
+        );
+        assert_eq!(
+            condensation.component_for(&package_id("unknown 0.1.0")),
+            None,
+            "unknown package"
+        );
+
+        // Dependencies come first.
+        assert_eq!((lower, upper), (0, 1), "reverse topological order");
+        let mut lower_ids = condensation.component(lower).expect("valid index");
+        lower_ids.sort();
+        let mut expected = vec![&lower_a, &lower_b];
+        expected.sort();
+        assert_eq!(lower_ids, expected, "lower component members");
+        assert_eq!(condensation.component(2), None, "index out of range");
+        assert_eq!(
+            condensation.components().len(),
+            condensation.component_count(),
+            "components matches component_count"
+        );
+
+        // upper-b -> lower-a is the only link between the cycles.
+        assert_eq!(
+            condensation.edges().collect::<Vec<_>>(),
+            vec![(upper, lower)],
+            "edges between components"
+        );
+
+        // An acyclic graph has one component per package.
+        let graph = Fixture::metadata1().graph();
+        assert_eq!(
+            graph.condensation().component_count(),
+            graph.package_count(),
+            "one component per package"
+        );
+        assert_eq!(
+            graph.condensation().edges().len(),
+            graph.link_count(),
+            "one edge per link"
+        );
+    }
+
+    #[test]
+    fn build_waves() {
+        let graph = Fixture::metadata_cycle2().graph();
+        let waves: Vec<Vec<_>> = graph
+            .resolve_all()
+            .build_waves()
+            .into_iter()
+            .map(|wave| wave.into_iter().map(|package| package.id()).collect())
+            .collect();
+        let mut lower = [
+            package_id(fixtures::METADATA_CYCLE2_LOWER_A),
+            package_id(fixtures::METADATA_CYCLE2_LOWER_B),
+        ];
+        lower.sort();
+        let mut upper = [
+            package_id(fixtures::METADATA_CYCLE2_UPPER_A),
+            package_id(fixtures::METADATA_CYCLE2_UPPER_B),
+        ];
+        upper.sort();
+        assert_eq!(
+            waves,
+            vec![
+                lower.iter().collect::<Vec<_>>(),
+                upper.iter().collect::<Vec<_>>()
+            ],
+            "each cycle is in a single wave"
+        );
+
+        for fixture in &[Fixture::metadata1(), Fixture::metadata_cycle2()] {
+            let graph = fixture.graph();
+            let package_set = graph.resolve_all();
+            let waves = package_set.build_waves();
+            assert!(
+                waves.iter().all(|wave| !wave.is_empty()),
+                "waves aren't empty"
+            );
+            let package_waves: HashMap<_, _> = waves
+                .iter()
+                .enumerate()
+                .flat_map(|(idx, wave)| wave.iter().map(move |package| (package.id(), idx)))
+                .collect();
+            assert_eq!(
+                package_waves.len(),
+                package_set.len(),
+                "every package is in exactly one wave"
+            );
+            let cycles = graph.cycles();
+            for link in package_set.links(DependencyDirection::Forward) {
+                let (from, to) = (link.from().id(), link.to().id());
+                let cyclic = cycles.is_cyclic(from, to).expect("valid package IDs");
+                if cyclic {
+                    assert_eq!(
+                        package_waves[from], package_waves[to],
+                        "{} -> {}: cycles are in the same wave",
+                        from, to
+                    );
+                } else {
+                    assert!(
+                        package_waves[from] > package_waves[to],
+                        "{} -> {}: dependencies are in earlier waves",
+                        from,
+                        to
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn all_paths() {
+        let graph = Fixture::metadata_features1().graph();
+        let member_a = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b = package_id(METADATA_FEATURES1_MEMBER_B);
+        let dep_c = package_id(METADATA_FEATURES1_DEP_C);
+
+        let mut paths = graph
+            .all_paths(&member_b, &dep_c, None)
+            .expect("valid package IDs");
+        paths.sort();
+        let mut expected = vec![vec![&member_b, &dep_c], vec![&member_b, &member_a, &dep_c]];
+        expected.sort();
+        assert_eq!(paths, expected, "direct and transitive paths to dep-c");
+        assert_eq!(
+            graph
+                .all_paths(&member_b, &dep_c, Some(1))
+                .expect("valid package IDs")
+                .len(),
+            1,
+            "max_paths limits the number of paths"
+        );
+        assert_eq!(
+            graph
+                .all_paths(&member_b, &member_b, None)
+                .expect("valid package IDs"),
+            vec![vec![&member_b]],
+            "a package has a trivial path to itself"
+        );
+        assert!(
+            graph
+                .all_paths(&dep_c, &member_b, None)
+                .expect("valid package IDs")
+                .is_empty(),
+            "no paths against the direction of dependencies"
+        );
+        assert_matches!(
+            graph.all_paths(&member_b, &package_id("unknown 0.1.0"), None),
+            Err(Error::UnknownPackageId(_)),
+            "unknown package ID"
+        );
+
+        let graph = Fixture::metadata_cycle2().graph();
+        let upper_a = package_id(fixtures::METADATA_CYCLE2_UPPER_A);
+        let upper_b = package_id(fixtures::METADATA_CYCLE2_UPPER_B);
+        let lower_a = package_id(fixtures::METADATA_CYCLE2_LOWER_A);
+        let lower_b = package_id(fixtures::METADATA_CYCLE2_LOWER_B);
+        assert_eq!(
+            graph
+                .all_paths(&upper_a, &lower_b, None)
+                .expect("valid package IDs"),
+            vec![vec![&upper_a, &upper_b, &lower_a, &lower_b]],
+            "cycles are only traversed once"
+        );
+        assert_eq!(
+            graph
+                .all_paths(&upper_a, &upper_b, None)
+                .expect("valid package IDs"),
+            vec![vec![&upper_a, &upper_b]],
+            "paths within a cycle don't loop back"
+        );
+    }
+
+    #[test]
+    fn metadata_cycle_platform1() {
+        let metadata = Fixture::metadata_cycle_platform1();
+        metadata.verify();
+
+        let cycles = metadata.graph().cycles();
+        let cycle_names = |platform: &Platform<'_>| -> Vec<Vec<&str>> {
+            cycles
+                .cycles_on_platform(platform)
+                .map(|cycle| {
+                    let mut names: Vec<_> = cycle.iter().map(|package| package.name()).collect();
+                    names.sort();
+                    names
+                })
+                .collect()
+        };
+
+        let windows = Platform::new("x86_64-pc-windows-msvc", TargetFeatures::Unknown)
+            .expect("platform should be known");
+        assert_eq!(
+            cycle_names(&windows),
+            vec![vec!["lower", "upper"]],
+            "cycle exists on Windows"
+        );
+        let linux = Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown)
+            .expect("platform should be known");
+        assert!(
+            cycle_names(&linux).is_empty(),
+            "cfg(windows) link doesn't form a cycle on Linux"
+        );
+
+        // Searching within a cycle terminates.
+        let upper_id = package_id(fixtures::METADATA_CYCLE_PLATFORM1_UPPER);
+        let lower_id = package_id(fixtures::METADATA_CYCLE_PLATFORM1_LOWER);
+        let feature_graph = metadata.graph().feature_graph();
+        assert_eq!(
+            feature_graph
+                .shortest_path(FeatureId::base(&lower_id), FeatureId::base(&upper_id))
+                .expect("valid feature IDs"),
+            Some(vec![FeatureId::base(&lower_id), FeatureId::base(&upper_id)]),
+            "lower -> upper through the dev-dependency"
+        );
+    }
+
+    proptest_suite!(metadata_cycle_platform1);
+
+    #[test]
+    fn metadata_weak_features1() {
+        let metadata = Fixture::metadata_weak_features1();
+        // This also checks that "weak-dep?/extra" doesn't produce any warnings.
+        metadata.verify();
+
+        let feature_graph = metadata.graph().feature_graph();
+        let user_id = package_id(fixtures::METADATA_WEAK_FEATURES1_WEAK_USER);
+        let dep_id = package_id(fixtures::METADATA_WEAK_FEATURES1_WEAK_DEP);
+        let serde = FeatureId::new(&user_id, "serde");
+        let extra = FeatureId::new(&dep_id, "extra");
+
+        assert!(
+            feature_graph
+                .all_links(DependencyDirection::Forward)
+                .any(|link| link == (serde, extra, FeatureEdgeKind::WeakFeatureDependency)),
+            "serde -> weak-dep/extra is a weak link"
+        );
+
+        let resolve = |features: &[&str]| {
+            feature_graph
+                .query_forward(features.iter().map(|feature| (&user_id, *feature)))
+                .expect("valid feature IDs")
+                .resolve()
+        };
+
+        // serde alone doesn't enable weak-dep.
+        let serde_only = resolve(&["serde"]);
+        assert_eq!(
+            serde_only.contains(FeatureId::base(&dep_id)),
+            Some(false),
+            "weak-dep isn't enabled"
+        );
+        assert_eq!(
+            serde_only.contains(extra),
+            Some(false),
+            "weak-dep/extra isn't enabled"
+        );
+
+        // weak-dep enabled through another feature, without serde.
+        let with_dep = resolve(&["with-dep"]);
+        assert_eq!(
+            with_dep.contains(FeatureId::base(&dep_id)),
+            Some(true),
+            "weak-dep is enabled"
+        );
+        assert_eq!(
+            with_dep.contains(extra),
+            Some(false),
+            "weak-dep/extra isn't enabled without serde"
+        );
+
+        // Both together enable weak-dep/extra.
+        let both = resolve(&["serde", "with-dep"]);
+        assert_eq!(
+            both.contains(extra),
+            Some(true),
+            "weak-dep/extra is enabled with serde and with-dep"
+        );
+
+        let report = feature_graph
+            .feature_report(&user_id)
+            .expect("weak-user is known");
+        let serde_report = report
+            .features()
+            .iter()
+            .find(|feature| feature.name() == "serde")
+            .expect("serde is a named feature");
+        assert_eq!(
+            serde_report.enables_in_dependencies(),
+            &["weak-dep?/extra".to_string()],
+            "weak features are reported with ?/"
+        );
+
+        // Weak links don't pull in weak-dep by themselves.
+        let mut adding: Vec<_> = feature_graph
+            .package_adding_features(&user_id)
+            .expect("weak-user is known")
+            .collect();
+        adding.sort();
+        let mut expected = vec![
+            FeatureId::new(&user_id, "weak-dep"),
+            FeatureId::new(&user_id, "with-dep"),
+        ];
+        expected.sort();
+        assert_eq!(adding, expected, "serde doesn't add packages");
+        let impact = feature_graph
+            .feature_impact(serde)
+            .expect("valid feature ID");
+        assert_eq!(
+            impact.reachable_packages(),
+            1,
+            "serde only covers weak-user"
+        );
+        assert_eq!(
+            impact.reachable_features(),
+            serde_only.len(),
+            "impact matches a resolve of serde"
+        );
+        assert_eq!(impact.added_edges(), 1, "serde -> base");
+    }
+
+    #[test]
+    fn weak_features_resolver_calls() {
+        let feature_graph = Fixture::metadata_weak_features1().graph().feature_graph();
+        let user_id = package_id(fixtures::METADATA_WEAK_FEATURES1_WEAK_USER);
+        let dep_id = package_id(fixtures::METADATA_WEAK_FEATURES1_WEAK_DEP);
+        let serde = FeatureId::new(&user_id, "serde");
+        let extra = FeatureId::new(&dep_id, "extra");
+
+        let mut calls: HashMap<(FeatureId, FeatureId), usize> = HashMap::new();
+        let feature_set = feature_graph
+            .query_forward([(&user_id, "serde"), (&user_id, "with-dep")])
+            .expect("valid feature IDs")
+            .resolve_with_fn(|_, link| {
+                *calls.entry((link.from(), link.to())).or_default() += 1;
+                true
+            });
+        assert_eq!(
+            feature_set.contains(extra),
+            Some(true),
+            "weak-dep/extra is enabled"
+        );
+
+        // Following the weak link continues from weak-dep/extra, so links that were already
+        // followed aren't passed to the resolver again.
+        assert_eq!(
+            calls.get(&(serde, extra)),
+            Some(&1),
+            "weak link checked once"
+        );
+        for ((from, to), count) in &calls {
+            assert_eq!(*count, 1, "link {:?} -> {:?} checked once", from, to);
+        }
+    }
+
+    #[test]
+    fn metadata_dep_syntax1() {
+        let metadata = Fixture::metadata_dep_syntax1();
+        // This also checks that "dep:ns-dep" doesn't produce any warnings.
+        metadata.verify();
+
+        let feature_graph = metadata.graph().feature_graph();
+        assert!(
+            feature_graph.build_warnings().is_empty(),
+            "no feature graph warnings"
+        );
+        assert!(!feature_graph.is_cyclic(), "feature graph is acyclic");
+        let user_id = package_id(fixtures::METADATA_DEP_SYNTAX1_NS_USER);
+        let dep_id = package_id(fixtures::METADATA_DEP_SYNTAX1_NS_DEP);
+
+        let features = feature_graph
+            .query_forward(iter::once((&user_id, "enable-dep")))
+            .expect("valid feature ID")
+            .resolve();
+        assert_eq!(
+            features.contains(FeatureId::base(&dep_id)),
+            Some(true),
+            "enable-dep enables ns-dep"
+        );
+        assert_eq!(
+            features.contains((&dep_id, "used")),
+            Some(true),
+            "ns-dep is enabled with the features it's declared with"
+        );
+
+        let base_only = feature_graph
+            .query_forward(iter::once(FeatureId::base(&user_id)))
+            .expect("valid feature ID")
+            .resolve();
+        assert_eq!(
+            base_only.contains(FeatureId::base(&dep_id)),
+            Some(false),
+            "ns-dep is optional"
+        );
+    }
+
+    #[test]
+    fn metadata_platform_features1() {
+        let metadata = Fixture::metadata_platform_features1();
+        metadata.verify();
+
+        let feature_graph = metadata.graph().feature_graph();
+        let root_id = package_id(fixtures::METADATA_PLATFORM_FEATURES1_PF_ROOT);
+        let win_dep_id = package_id(fixtures::METADATA_PLATFORM_FEATURES1_WIN_DEP);
+        let never_dep_id = package_id(fixtures::METADATA_PLATFORM_FEATURES1_NEVER_DEP);
+        let unix_build_dep_id = package_id(fixtures::METADATA_PLATFORM_FEATURES1_UNIX_BUILD_DEP);
+        let dev_dep_id = package_id(fixtures::METADATA_PLATFORM_FEATURES1_DEV_DEP);
+
+        let windows =
+            Platform::new("x86_64-pc-windows-msvc", TargetFeatures::Unknown).expect("known triple");
+        let linux = Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown)
+            .expect("known triple");
+        let all_kinds = vec![
+            DependencyKind::Normal,
+            DependencyKind::Build,
+            DependencyKind::Development,
+        ];
+
+        let resolve = |resolver: PlatformFeatureResolver<'_>| {
+            feature_graph
+                .query_forward(iter::once(FeatureId::base(&root_id)))
+                .expect("valid feature ID")
+                .resolve_with(resolver)
+        };
+
+        // Building for Windows on a Linux host.
+        let features =
+            resolve(PlatformFeatureResolver::new(&windows, all_kinds.clone()).with_host(&linux));
+        for (dep_id, expected, msg) in [
+            (&win_dep_id, true, "win-dep is enabled on Windows"),
+            (&never_dep_id, false, "cfg(any()) is never enabled"),
+            (
+                &unix_build_dep_id,
+                true,
+                "build dependencies are evaluated against the host",
+            ),
+            (&dev_dep_id, true, "dev dependencies are enabled"),
+        ] {
+            assert_eq!(
+                features.contains(FeatureId::base(dep_id)),
+                Some(expected),
+                "{}",
+                msg
+            );
+            assert_eq!(
+                features.contains((dep_id, "extra")),
+                Some(expected),
+                "{}",
+                msg
+            );
+        }
+
+        // Only normal dependencies.
+        let features = resolve(
+            PlatformFeatureResolver::new(&windows, vec![DependencyKind::Normal]).with_host(&linux),
+        );
+        assert_eq!(
+            features.contains((&win_dep_id, "extra")),
+            Some(true),
+            "normal dependency is followed"
+        );
+        assert_eq!(
+            features.contains((&unix_build_dep_id, "extra")),
+            Some(false),
+            "build dependencies aren't selected"
+        );
+        assert_eq!(
+            features.contains((&dev_dep_id, "extra")),
+            Some(false),
+            "dev dependencies aren't selected"
+        );
+
+        // Building for Linux on a Windows host.
+        let features = resolve(PlatformFeatureResolver::new(&linux, all_kinds).with_host(&windows));
+        assert_eq!(
+            features.contains(FeatureId::base(&win_dep_id)),
+            Some(false),
+            "win-dep is disabled on Linux"
+        );
+        assert_eq!(
+            features.contains(FeatureId::base(&unix_build_dep_id)),
+            Some(false),
+            "unix-build-dep is disabled on a Windows host"
+        );
+        assert_eq!(
+            features.contains((&dev_dep_id, "extra")),
+            Some(true),
+            "dev dependencies are enabled on every platform"
+        );
+    }
+
+    #[test]
+    fn metadata_no_default1() {
+        let metadata = Fixture::metadata_no_default1();
+        metadata.verify();
+
+        let graph = metadata.graph();
+        let feature_graph = graph.feature_graph();
+        let app_id = package_id(fixtures::METADATA_NO_DEFAULT1_APP);
+        let lib_id = package_id(fixtures::METADATA_NO_DEFAULT1_LIB);
+        let app_query = || {
+            graph
+                .query_forward(iter::once(&app_id))
+                .expect("valid package ID")
+        };
+
+        // The default feature for app is suppressed. lib's default feature isn't enabled since
+        // app depends on it with default-features = false.
+        let feature_set = feature_graph
+            .query_packages(&app_query(), NoDefaultFeatures::new())
+            .resolve();
+        for (feature_id, expected) in [
+            (FeatureId::base(&app_id), true),
+            (FeatureId::new(&app_id, "default"), false),
+            (FeatureId::new(&app_id, "fancy"), false),
+            (FeatureId::base(&lib_id), true),
+            (FeatureId::new(&lib_id, "default"), false),
+            (FeatureId::new(&lib_id, "std"), false),
+        ] {
+            assert_eq!(
+                feature_set.contains(feature_id),
+                Some(expected),
+                "no default features: {:?}",
+                feature_id
+            );
+        }
+
+        // Compare against a regular build.
+        let default_set = feature_graph
+            .query_packages(&app_query(), default_filter())
+            .resolve();
+        assert_eq!(
+            default_set.contains((&app_id, "fancy")),
+            Some(true),
+            "fancy is a default feature"
+        );
+
+        // Explicitly requested features are selected.
+        let fancy_set = feature_graph
+            .query_packages(&app_query(), NoDefaultFeatures::with_features(["fancy"]))
+            .resolve();
+        assert_eq!(
+            fancy_set.contains((&app_id, "fancy")),
+            Some(true),
+            "fancy is requested explicitly"
+        );
+        assert_eq!(
+            fancy_set.contains((&app_id, "default")),
+            Some(false),
+            "default is still suppressed"
+        );
+
+        // other depends on app with default features, which re-enables them.
+        let workspace_set = feature_graph
+            .query_workspace(NoDefaultFeatures::new())
+            .resolve();
+        for (feature_id, expected) in [
+            (FeatureId::new(&app_id, "default"), true),
+            (FeatureId::new(&app_id, "fancy"), true),
+            (FeatureId::new(&lib_id, "default"), false),
+            (FeatureId::new(&lib_id, "std"), false),
+        ] {
+            assert_eq!(
+                workspace_set.contains(feature_id),
+                Some(expected),
+                "re-enabled through other: {:?}",
+                feature_id
+            );
+        }
+    }
+
+    #[test]
+    fn default_features_only() {
+        let metadata = Fixture::metadata_features1();
+        let feature_graph = metadata.graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+
+        let feature_set = feature_graph
+            .query_workspace(DefaultFeaturesOnly::new())
+            .resolve();
+        for (feature_id, expected) in [
+            (FeatureId::base(&member_a_id), true),
+            (FeatureId::new(&member_a_id, "default"), true),
+            (FeatureId::new(&member_a_id, "a-default"), true),
+            // a and b are regular features that default doesn't enable.
+            (FeatureId::new(&member_a_id, "a"), false),
+            (FeatureId::new(&member_a_id, "b"), false),
+            (FeatureId::base(&member_b_id), true),
+            (FeatureId::new(&member_b_id, "shared"), false),
+        ] {
+            assert_eq!(
+                feature_set.contains(feature_id),
+                Some(expected),
+                "default features only: {:?}",
+                feature_id
+            );
+        }
+
+        let default_set = feature_graph.query_workspace(default_filter()).resolve();
+        assert_eq!(
+            feature_set
+                .features(DependencyDirection::Forward)
+                .collect::<Vec<_>>(),
+            default_set
+                .features(DependencyDirection::Forward)
+                .collect::<Vec<_>>(),
+            "matches default_filter"
+        );
+    }
+
+    #[test]
+    fn metadata_rename1() {
+        let metadata = Fixture::metadata_rename1();
+        metadata.verify();
+
+        let graph = metadata.graph();
+        let renamer_id = package_id(fixtures::METADATA_RENAME1_RENAMER);
+        let real_dep_id = package_id(fixtures::METADATA_RENAME1_REAL_DEP);
+        let plain_dep_id = package_id(fixtures::METADATA_RENAME1_PLAIN_DEP);
+
+        let mut links: Vec<_> = graph
+            .metadata(&renamer_id)
+            .expect("valid package ID")
+            .direct_links()
+            .map(|link| {
+                (
+                    link.dep_name(),
+                    link.resolved_name(),
+                    link.to().name(),
+                    link.is_renamed(),
+                )
+            })
+            .collect();
+        links.sort();
+        assert_eq!(
+            links,
+            vec![
+                ("alias-dep", "alias_dep", "real-dep", true),
+                ("plain-dep", "plain_dep", "plain-dep", false),
+            ],
+            "renamed and regular dependencies"
+        );
+
+        // The feature dependency "alias-dep/extra" refers to real-dep through its alias.
+        let feature_set = graph
+            .feature_graph()
+            .query_forward(iter::once((&renamer_id, "use-alias")))
+            .expect("valid feature ID")
+            .resolve();
+        for (feature_id, expected) in [
+            (FeatureId::new(&real_dep_id, "extra"), true),
+            (FeatureId::base(&real_dep_id), true),
+            (FeatureId::base(&plain_dep_id), true),
+            (FeatureId::new(&renamer_id, "default"), false),
+        ] {
+            assert_eq!(
+                feature_set.contains(feature_id),
+                Some(expected),
+                "use-alias: {:?}",
+                feature_id
+            );
+        }
+    }
+
+    #[test]
+    fn metadata_minimal_versions1() {
+        let metadata = Fixture::metadata_minimal_versions1();
+        metadata.verify();
+
+        let graph = metadata.graph();
+        let consumer_id = package_id(fixtures::METADATA_MINIMAL_VERSIONS1_CONSUMER);
+        let legacy_id = package_id(fixtures::METADATA_MINIMAL_VERSIONS1_LEGACY);
+        let lib_old_id = package_id(fixtures::METADATA_MINIMAL_VERSIONS1_LIB_OLD);
+        let lib_new_id = package_id(fixtures::METADATA_MINIMAL_VERSIONS1_LIB_NEW);
+
+        let minimal = graph
+            .simulate_minimal_versions()
+            .expect("minimal versions graph is valid");
+        assert_eq!(
+            minimal.package_count(),
+            graph.package_count(),
+            "packages are unchanged"
+        );
+        assert_eq!(
+            minimal.link_count(),
+            graph.link_count(),
+            "link count is unchanged"
+        );
+
+        let dep_ids = |graph: &PackageGraph, package_id: &PackageId| -> Vec<PackageId> {
+            graph
+                .metadata(package_id)
+                .expect("valid package ID")
+                .direct_links()
+                .map(|link| link.to().id().clone())
+                .collect()
+        };
+        assert_eq!(
+            dep_ids(graph, &consumer_id),
+            vec![lib_new_id.clone()],
+            "consumer's ^1.2 requirement originally resolves to 1.5.0"
+        );
+        assert_eq!(
+            dep_ids(&minimal, &consumer_id),
+            vec![lib_old_id.clone()],
+            "consumer's ^1.2 requirement selects 1.2.0 with minimal versions"
+        );
+        assert_eq!(
+            dep_ids(&minimal, &legacy_id),
+            vec![lib_old_id],
+            "legacy's =1.2.0 requirement is unchanged"
+        );
+        assert_eq!(
+            minimal
+                .query_reverse(iter::once(&lib_new_id))
+                .expect("valid package ID")
+                .resolve()
+                .len(),
+            1,
+            "nothing depends on 1.5.0 with minimal versions"
+        );
+    }
+
+    #[test]
+    fn version_range_resolver() {
+        let graph = Fixture::metadata_minimal_versions1().graph();
+        let consumer_id = package_id(fixtures::METADATA_MINIMAL_VERSIONS1_CONSUMER);
+        let legacy_id = package_id(fixtures::METADATA_MINIMAL_VERSIONS1_LEGACY);
+        let lib_old_id = package_id(fixtures::METADATA_MINIMAL_VERSIONS1_LIB_OLD);
+        let lib_new_id = package_id(fixtures::METADATA_MINIMAL_VERSIONS1_LIB_NEW);
+        let resolve = |req: &str| {
+            let resolver = VersionRangeResolver::new(
+                "semver-lib",
+                VersionReq::parse(req).expect("valid version req"),
+            );
+            graph.query_workspace().resolve_with(resolver)
+        };
+
+        // consumer resolves to semver-lib 1.5.0, which is pruned.
+        let pinned = resolve("<1.3");
+        for (id, expected) in [
+            (&consumer_id, true),
+            (&legacy_id, true),
+            (&lib_old_id, true),
+            (&lib_new_id, false),
+        ] {
+            assert_eq!(pinned.contains(id), Some(expected), "<1.3: {}", id);
+        }
+
+        // Both versions match.
+        let wide = resolve("^1");
+        assert_eq!(wide.len(), 4, "^1 includes every package");
+
+        // Neither version matches, so only the workspace is left.
+        let none = resolve(">=2");
+        assert_eq!(none.len(), 2, ">=2 only includes the workspace");
+        assert_eq!(none.contains(&lib_old_id), Some(false), ">=2: lib-old");
+    }
+
+    #[test]
+    fn metadata_targets1() {
+        let metadata_targets1 = Fixture::metadata_targets1();
+        metadata_targets1.verify();
+
+        let package_graph = metadata_targets1.graph();
+        let package_set = package_graph.resolve_all();
+        let feature_graph = metadata_targets1.graph().feature_graph();
+        assert_eq!(feature_graph.feature_count(), 31, "feature count");
+
+        // Some code that might be useful for debugging.
+        if false {
+            for (source, target, edge) in feature_graph
+                .resolve_all()
+                .links(DependencyDirection::Forward)
+            {
+                let source_metadata = package_graph.metadata(source.package_id()).unwrap();
+                let target_metadata = package_graph.metadata(target.package_id()).unwrap();
+
+                println!(
+                    "feature link: {}:{} {} -> {}:{} {} {:?}",
+                    source_metadata.name(),
+                    source_metadata.version(),
+                    source.feature().unwrap_or("[base]"),
+                    target_metadata.name(),
+                    target_metadata.version(),
+                    target.feature().unwrap_or("[base]"),
+                    edge
+                );
+            }
+        }
+
+        assert_eq!(feature_graph.link_count(), 48, "feature link count");
+
+        // Check that resolve_packages + a feature filter works.
+        let feature_set = feature_graph.resolve_packages(
+            &package_set,
+            feature_filter(default_filter(), ["foo", "bar"].iter().copied()),
+        );
+        let dep_a_id = fixtures::package_id(fixtures::METADATA_TARGETS1_DEP_A);
+        assert!(feature_set
+            .contains((&dep_a_id, "foo"))
+            .expect("valid feature ID"));
+        assert!(feature_set
+            .contains((&dep_a_id, "bar"))
+            .expect("valid feature ID"));
+        assert!(!feature_set
+            .contains((&dep_a_id, "baz"))
+            .expect("valid feature ID"));
+        assert!(!feature_set
+            .contains((&dep_a_id, "quux"))
+            .expect("valid feature ID"));
+
+        assert_features_for_package(
+            &feature_set,
+            &fixtures::package_id(fixtures::METADATA_TARGETS1_TESTCRATE),
+            &[None],
+            "testcrate",
+        );
+        assert_features_for_package(
+            &feature_set,
+            &dep_a_id,
+            &[None, Some("foo"), Some("bar")],
+            "dep a",
+        );
+        assert_features_for_package(
+            &feature_set,
+            &fixtures::package_id(fixtures::METADATA_TARGETS1_LAZY_STATIC_1),
+            &[None],
+            "lazy_static",
+        );
+
+        // PackageSet::to_feature_set should produce the same set.
+        let to_feature_set = package_set.to_feature_set(feature_filter(
+            default_filter(),
+            ["foo", "bar"].iter().copied(),
+        ));
+        assert!(
+            to_feature_set.symmetric_difference(&feature_set).is_empty(),
+            "to_feature_set matches resolve_packages"
+        );
+    }
+
+    #[test]
+    fn platform_status_enabled_on() {
+        let package_graph = Fixture::metadata_targets1().graph();
+        let testcrate = package_graph
+            .metadata(&package_id(fixtures::METADATA_TARGETS1_TESTCRATE))
+            .expect("valid package ID");
+        let link_to = |to: &str| {
+            let to = package_id(to);
+            testcrate
+                .direct_links()
+                .find(|link| *link.to().id() == to)
+                .expect("link should exist")
+        };
+        let linux = Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown)
+            .expect("known triple");
+        let windows =
+            Platform::new("x86_64-pc-windows-msvc", TargetFeatures::Unknown).expect("known triple");
+
+        // testcrate -> bytes is an optional build dependency for an explicit target triple.
+        let status = link_to(fixtures::METADATA_TARGETS1_BYTES).build().status();
+        assert!(
+            status.required_status().is_never(),
+            "bytes is never a required build dependency"
+        );
+        assert_eq!(
+            status.optional_status().enabled_on(&linux),
+            EnabledTernary::Enabled,
+            "triple matches"
+        );
+        assert_eq!(
+            status.optional_status().enabled_on(&windows),
+            EnabledTernary::Disabled,
+            "triple doesn't match"
+        );
+
+        // testcrate -> lazy_static 0.1 is a dev dependency for cfg(windows).
+        let status = link_to(fixtures::METADATA_TARGETS1_LAZY_STATIC_01)
+            .dev()
+            .status();
+        assert!(
+            !status.required_status().is_always() && !status.required_status().is_never(),
+            "lazy_static 0.1 is platform-dependent"
+        );
+        assert_eq!(
+            status.required_status().enabled_on(&windows),
+            EnabledTernary::Enabled,
+            "cfg(windows) is true on Windows"
+        );
+        assert_eq!(
+            status.required_status().enabled_on(&linux),
+            EnabledTernary::Disabled,
+            "cfg(windows) is false on Linux"
+        );
+    }
+
+    proptest_suite!(metadata_targets1);
+
+    #[test]
+    fn metadata_build_targets1() {
+        let metadata_build_targets1 = Fixture::metadata_build_targets1();
+        metadata_build_targets1.verify();
+    }
+
+    // No need for proptests because there are no dependencies involved.
+
+    #[test]
+    fn metadata_proc_macro1() {
+        let metadata = Fixture::metadata_proc_macro1();
+        metadata.verify();
+        let graph = metadata.graph();
+
+        let package = graph
+            .metadata(&package_id(METADATA_PROC_MACRO1_MACRO))
+            .expect("valid package ID");
+        assert!(package.is_proc_macro(), "is proc macro");
+        assert!(matches!(
+            package
+                .build_target(&BuildTargetId::Library)
+                .expect("library package is present")
+                .kind(),
+            BuildTargetKind::ProcMacro
+        ));
+
+        // Proc macros can be filtered out of a resolved set.
+        let all = graph.resolve_all();
+        let mut without_macros = all.clone();
+        without_macros.retain(|package| !package.is_proc_macro());
+        assert_eq!(
+            without_macros.len(),
+            all.len() - 1,
+            "exactly one package was removed"
+        );
+        assert_eq!(
+            without_macros.contains(package.id()),
+            Some(false),
+            "proc macro was removed"
+        );
+
+        // filter can be used to find proc macros without modifying the set.
+        let macros = all.filter(DependencyDirection::Forward, |package| {
+            package.is_proc_macro()
+        });
+        assert_eq!(
+            macros
+                .package_ids(DependencyDirection::Forward)
+                .collect::<Vec<_>>(),
+            vec![package.id()],
+            "only the proc macro is selected"
+        );
+        assert_eq!(
+            macros.union(&without_macros).len(),
+            all.len(),
+            "proc macros and other packages make up the whole set"
+        );
+    }
+
+    // No need for proptests because this is a really simple test.
+
+    #[test]
+    fn metadata_custom_cfg1() {
+        let metadata = Fixture::metadata_custom_cfg1();
+        metadata.verify();
+
+        let feature_graph = metadata.graph().feature_graph();
+        let custom_cfg_id = package_id(METADATA_CUSTOM_CFG1_CUSTOM_CFG);
+        let fuzz_dep_id = package_id(METADATA_CUSTOM_CFG1_FUZZ_DEP);
+        let normal_dep_id = package_id(METADATA_CUSTOM_CFG1_NORMAL_DEP);
+        let platform = Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown)
+            .expect("platform should be known");
+
+        let resolve = |cfgs: &[(String, Option<String>)]| {
+            feature_graph
+                .resolve_for_platform_with_cfgs(
+                    iter::once(&custom_cfg_id),
+                    all_filter(),
+                    &platform,
+                    cfgs,
+                )
+                .expect("valid package IDs")
+        };
+
+        let feature_set = resolve(&[]);
+        assert!(
+            feature_set
+                .contains((&custom_cfg_id, "fuzz-dep"))
+                .expect("valid feature ID"),
+            "without cfgs: optional dep feature is enabled"
+        );
+        assert!(
+            !feature_set
+                .contains(FeatureId::base(&fuzz_dep_id))
+                .expect("valid feature ID"),
+            "without cfgs: cfg(fuzzing) dependency is not included"
+        );
+        assert!(
+            feature_set
+                .contains(FeatureId::base(&normal_dep_id))
+                .expect("valid feature ID"),
+            "without cfgs: normal dependency is included"
+        );
+
+        let feature_set = resolve(&[("fuzzing".to_string(), None)]);
+        assert!(
+            feature_set
+                .contains(FeatureId::base(&fuzz_dep_id))
+                .expect("valid feature ID"),
+            "with fuzzing: cfg(fuzzing) dependency is included"
+        );
+
+        // Key-value cfgs aren't supported.
+        assert_matches!(
+            feature_graph.resolve_for_platform_with_cfgs(
+                iter::once(&custom_cfg_id),
+                all_filter(),
+                &platform,
+                &[
+                    ("fuzzing".to_string(), None),
+                    ("fuzzing".to_string(), Some("1".to_string())),
+                ],
+            ),
+            Err(Error::UnsupportedKeyValueCfg(name, value)) if name == "fuzzing" && value == "1",
+            "key-value cfgs are rejected"
+        );
+
+        // The optional dependency is only pulled in if the feature enabling it is selected.
+        let feature_set = feature_graph
+            .resolve_for_platform_with_cfgs(
+                iter::once(&custom_cfg_id),
+                none_filter(),
+                &platform,
+                &[("fuzzing".to_string(), None)],
+            )
+            .expect("valid package IDs");
+        assert!(
+            !feature_set
+                .contains(FeatureId::base(&fuzz_dep_id))
+                .expect("valid feature ID"),
+            "with fuzzing but no features: cfg(fuzzing) dependency is not included"
+        );
+
+        // Only custom-cfg -> fuzz-dep is cfg-gated.
+        let is_cfg_gated = |link: PackageLink<'_>| {
+            let status = link.normal().status();
+            matches!(
+                status.required_status(),
+                PlatformStatus::PlatformDependent { .. }
+            ) || matches!(
+                status.optional_status(),
+                PlatformStatus::PlatformDependent { .. }
+            )
+        };
+        let package_set = metadata.graph().resolve_all();
+        let gated_to: Vec<_> = package_set
+            .packages_with_link(DependencyDirection::Forward, is_cfg_gated)
+            .map(|package| package.id())
+            .collect();
+        assert_eq!(gated_to, vec![&fuzz_dep_id], "fuzz-dep is behind a cfg");
+        let gated_from: Vec<_> = package_set
+            .packages_with_link(DependencyDirection::Reverse, is_cfg_gated)
+            .map(|package| package.id())
+            .collect();
+        assert_eq!(
+            gated_from,
+            vec![&custom_cfg_id],
+            "custom-cfg has a cfg-gated dependency"
+        );
+
+        // Filtering out optional dependencies during traversal cuts off fuzz-dep.
+        let fuzz_feature = FeatureId::new(&custom_cfg_id, "fuzz");
+        let unfiltered = feature_graph
+            .query_forward(iter::once(fuzz_feature))
+            .expect("valid feature ID")
+            .resolve();
+        assert!(
+            unfiltered
+                .contains(FeatureId::base(&fuzz_dep_id))
+                .expect("valid feature ID"),
+            "without filter: fuzz-dep is reachable through the fuzz feature"
+        );
+        let no_optional_deps = FeatureFilterFn::new(|graph: &FeatureGraph<'_>, feature_id| {
+            graph
+                .metadata(feature_id)
+                .expect("valid feature ID")
+                .feature_type()
+                != FeatureType::OptionalDep
+        });
+        let filtered = feature_graph
+            .query_forward_with_filter(iter::once(fuzz_feature), no_optional_deps)
+            .expect("valid feature ID")
+            .resolve();
+        assert!(
+            filtered.contains(fuzz_feature).expect("valid feature ID"),
+            "with filter: initial feature is included"
+        );
+        assert!(
+            filtered
+                .contains(FeatureId::base(&custom_cfg_id))
+                .expect("valid feature ID"),
+            "with filter: named feature dependencies are followed"
+        );
+        assert!(
+            !filtered
+                .contains((&custom_cfg_id, "fuzz-dep"))
+                .expect("valid feature ID"),
+            "with filter: optional dep feature is not traversed into"
+        );
+        assert!(
+            !filtered
+                .contains(FeatureId::base(&fuzz_dep_id))
+                .expect("valid feature ID"),
+            "with filter: fuzz-dep is cut off"
+        );
+        assert!(
+            feature_graph
+                .query_forward_with_filter(
+                    iter::once(FeatureId::new(&custom_cfg_id, "nonexistent")),
+                    all_filter(),
+                )
+                .is_err(),
+            "unknown feature produces an error"
+        );
+    }
+
+    proptest_suite!(metadata_custom_cfg1);
+
+    #[test]
+    fn metadata_features1() {
+        let metadata = Fixture::metadata_features1();
+        metadata.verify();
+    }
+
+    #[test]
+    fn feature_display_dot() {
+        let package_graph = Fixture::metadata_features1().graph();
+        let feature_graph = package_graph.feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+
+        // A dot graph of the features "a" pulls in. Feature node indexes depend on the order
+        // features are listed in, so compare with indexes replaced with labels.
+        let a_set = feature_graph
+            .query_forward(iter::once((&member_a_id, "a")))
+            .expect("valid feature ID")
+            .resolve();
+        let dot = format!("{}", a_set.display_dot(FeatureNameVisitor(package_graph)));
+        assert_eq!(
+            normalize_dot(&dot),
+            vec![
+                "dep-c/(base)",
+                "dev-only/(base)",
+                "dev-only/dev-feat",
+                "dev-only/dev-feat -> dev-only/(base) (FeatureToBase)",
+                "member-a/(base)",
+                "member-a/(base) -> dep-c/(base) (Dependency)",
+                "member-a/(base) -> dev-only/(base) (Dependency)",
+                "member-a/(base) -> dev-only/dev-feat (Dependency)",
+                "member-a/a",
+                "member-a/a -> member-a/(base) (FeatureToBase)",
+                "member-a/a -> member-a/b (FeatureDependency)",
+                "member-a/b",
+                "member-a/b -> member-a/(base) (FeatureToBase)",
+            ],
+            "feature dot output matches"
+        );
+
+        // ColoredDotVisitor colors dev-only links red and other links green.
+        static EXPECTED_DOT_COLORED: &str = r#"digraph {
+    0 [label="dep-c"]
+    1 [label="dev-only"]
+    2 [label="member-a"]
+    3 [label="member-b"]
+    4 [label="opt-dep"]
+    2 -> 0 [label="dep-c", color="green"]
+    2 -> 1 [label="dev-only", color="red"]
+    2 -> 4 [label="opt-dep", color="green"]
+    3 -> 0 [label="dep-c", color="green"]
+    3 -> 2 [label="member-a", color="green"]
+}
+"#;
+        assert_eq!(
+            EXPECTED_DOT_COLORED,
+            format!(
+                "{}",
+                package_graph.resolve_all().display_dot(ColoredDotVisitor)
+            ),
+            "colored dot output matches"
+        );
+    }
+
+    #[test]
+    fn is_workspace_internal() {
+        let package_graph = Fixture::metadata_features1().graph();
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+
+        let member_b = package_graph
+            .metadata(&member_b_id)
+            .expect("member-b is known");
+        let internal: Vec<_> = member_b
+            .direct_links()
+            .map(|link| (link.to().name(), link.is_workspace_internal()))
+            .collect();
+        assert_eq!(internal.len(), 2, "member-b has two dependencies");
+        assert!(
+            internal.contains(&("member-a", true)),
+            "member-b -> member-a is workspace internal"
+        );
+        assert!(
+            internal.contains(&("dep-c", false)),
+            "member-b -> dep-c is not workspace internal"
+        );
+    }
+
+    #[test]
+    fn path_through_avoiding() {
+        let package_graph = Fixture::metadata_features1().graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+        let opt_dep_id = package_id(METADATA_FEATURES1_OPT_DEP);
+
+        // member-b depends on dep-c both directly and through member-a. opt-dep is only reachable
+        // through member-a.
+        assert!(
+            package_graph
+                .path_through(&member_b_id, &member_a_id, &dep_c_id)
+                .expect("valid package IDs"),
+            "member-b -> member-a -> dep-c exists"
+        );
+        assert!(
+            package_graph
+                .path_avoiding(&member_b_id, &member_a_id, &dep_c_id)
+                .expect("valid package IDs"),
+            "member-b -> dep-c bypasses member-a"
+        );
+        assert!(
+            package_graph
+                .path_through(&member_b_id, &member_a_id, &opt_dep_id)
+                .expect("valid package IDs"),
+            "member-b -> member-a -> opt-dep exists"
+        );
+        assert!(
+            !package_graph
+                .path_avoiding(&member_b_id, &member_a_id, &opt_dep_id)
+                .expect("valid package IDs"),
+            "member-b -> opt-dep must go through member-a"
+        );
+        assert!(
+            !package_graph
+                .path_through(&member_a_id, &member_b_id, &dep_c_id)
+                .expect("valid package IDs"),
+            "member-a does not depend on member-b"
+        );
+        assert!(
+            package_graph
+                .path_avoiding(&member_a_id, &dep_c_id, &package_id("bogus 0.1.0 (bogus)"))
+                .is_err(),
+            "unknown package IDs produce an error"
+        );
+    }
+
+    #[test]
+    fn resolve_workspace_with_features() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+
+        // "shared" is declared by both workspace members, so enabling it should enable it on both.
+        let feature_set = feature_graph
+            .resolve_workspace_with_features(&["shared".to_string()])
+            .expect("shared is a known workspace feature");
+        assert_features_for_package(
+            &feature_set,
+            &member_a_id,
+            &[None, Some("default"), Some("a-default"), Some("shared")],
+            "member-a with shared",
+        );
+        assert_features_for_package(
+            &feature_set,
+            &member_b_id,
+            &[None, Some("shared")],
+            "member-b with shared",
+        );
+        // member-a/shared enables dep-c/extra, and member-b enables default features for dep-c.
+        assert_features_for_package(
+            &feature_set,
+            &dep_c_id,
+            &[
+                None,
+                Some("default"),
+                Some("c-default"),
+                Some("extra"),
+                Some("build-feat"),
+            ],
+            "dep-c with shared",
+        );
+
+        // "a" is only declared by member-a.
+        let feature_set = feature_graph
+            .resolve_workspace_with_features(&["a".to_string()])
+            .expect("a is a known workspace feature");
+        assert_features_for_package(
+            &feature_set,
+            &member_a_id,
+            &[
+                None,
+                Some("default"),
+                Some("a-default"),
+                Some("a"),
+                Some("b"),
+            ],
+            "member-a with a",
+        );
+        assert_features_for_package(&feature_set, &member_b_id, &[None], "member-b with a");
+
+        match feature_graph.resolve_workspace_with_features(&["extra".to_string()]) {
+            Err(Error::UnknownWorkspaceFeature(feature)) => {
+                assert_eq!(
+                    feature, "extra",
+                    "feature not declared by workspace members"
+                )
+            }
+            other => panic!("expected UnknownWorkspaceFeature, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn feature_set_without_dev() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+        let dev_only_id = package_id(METADATA_FEATURES1_DEV_ONLY);
+
+        // dev-only is only reachable through a dev-dependency, while dep-c is a regular one.
+        let feature_set = feature_graph.query_workspace(all_filter()).resolve();
+        assert_features_for_package(
+            &feature_set,
+            &dev_only_id,
+            &[None, Some("dev-feat")],
+            "dev-only with dev deps",
+        );
+        let without_dev = feature_set.without_dev();
+        assert_features_for_package(&without_dev, &dev_only_id, &[], "dev-only without dev deps");
+        assert_features_for_package(
+            &without_dev,
+            &dep_c_id,
+            &[
+                None,
+                Some("default"),
+                Some("c-default"),
+                Some("extra"),
+                Some("build-feat"),
+            ],
+            "dep-c without dev deps",
+        );
+        assert_eq!(
+            without_dev.len() + 2,
+            feature_set.len(),
+            "only dev-only features were removed"
+        );
+    }
+
+    #[test]
+    fn reachable_from_workspace() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+        let opt_dep_id = package_id(METADATA_FEATURES1_OPT_DEP);
+
+        // Only default features of workspace members are seeded.
+        let reachable = |feature_id: FeatureId<'_>| {
+            feature_graph
+                .reachable_from_workspace(feature_id)
+                .expect("valid feature ID")
+        };
+        assert!(
+            reachable(FeatureId::new(&member_a_id, "a-default")),
+            "member-a's default features are reachable"
+        );
+        assert!(
+            reachable(FeatureId::new(&dep_c_id, "c-default")),
+            "member-b enables dep-c's default features"
+        );
+        assert!(
+            !reachable(FeatureId::new(&dep_c_id, "extra")),
+            "dep-c/extra is only enabled by a non-default feature"
+        );
+        assert!(
+            !reachable(FeatureId::base(&opt_dep_id)),
+            "opt-dep is an optional dependency not enabled by default"
+        );
+        assert!(
+            feature_graph
+                .reachable_from_workspace(FeatureId::new(&dep_c_id, "nonexistent"))
+                .is_err(),
+            "unknown feature produces an error"
+        );
+    }
+
+    #[test]
+    fn feature_report() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+
+        let report = feature_graph
+            .feature_report(&member_a_id)
+            .expect("valid package ID");
+        assert_eq!(report.package_id(), member_a_id.repr(), "report package ID");
+        assert_eq!(
+            report.optional_deps(),
+            &["opt-dep"],
+            "member-a optional deps"
+        );
+        let feature = |name: &str| {
+            report
+                .features()
+                .iter()
+                .find(|feature| feature.name() == name)
+                .unwrap_or_else(|| panic!("feature {} not found in report", name))
+        };
+        assert_eq!(report.features().len(), 5, "member-a has 5 named features");
+        assert!(
+            feature("default").is_default(),
+            "default is a default feature"
+        );
+        assert_eq!(
+            feature("default").enables(),
+            &["a-default"],
+            "default enables"
+        );
+        assert!(
+            feature("a-default").is_default(),
+            "a-default is a default feature"
+        );
+        assert!(!feature("a").is_default(), "a is not a default feature");
+        assert_eq!(feature("a").enables(), &["b"], "a enables b");
+        assert!(
+            feature("a").enables_in_dependencies().is_empty(),
+            "a doesn't enable features in dependencies"
+        );
+        assert!(feature("shared").enables().is_empty(), "shared enables");
+        assert_eq!(
+            feature("shared").enables_in_dependencies(),
+            &["dep-c/extra"],
+            "shared enables dep-c/extra"
+        );
+        assert!(
+            feature_graph
+                .feature_report(&package_id("bogus 0.1.0 (bogus)"))
+                .is_none(),
+            "unknown package ID"
+        );
+    }
+
+    #[cfg(feature = "serde1")]
+    #[test]
+    fn feature_report_serialize() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+
+        let report = feature_graph
+            .feature_report(&member_a_id)
+            .expect("valid package ID");
+        let json = serde_json::to_value(&report).expect("report is serializable");
+        assert_eq!(
+            json["optional_deps"],
+            serde_json::json!(["opt-dep"]),
+            "serialized optional deps"
+        );
+    }
+
+    #[test]
+    fn top_features() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+
+        // a-default is only enabled by default, and b is only enabled by a.
+        let mut top_features: Vec<_> = feature_graph
+            .top_features(&member_a_id)
+            .expect("valid package ID")
+            .map(|feature_id| feature_id.feature().expect("named feature"))
+            .collect();
+        top_features.sort();
+        assert_eq!(
+            top_features,
+            vec!["a", "default", "shared"],
+            "top features for member-a"
+        );
+        assert!(
+            feature_graph
+                .top_features(&package_id("bogus 0.1.0 (bogus)"))
+                .is_none(),
+            "unknown package ID"
+        );
+    }
+
+    #[test]
+    fn feature_depends_on() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let feature_a = FeatureId::new(&member_a_id, "a");
+        let feature_b = FeatureId::new(&member_a_id, "b");
+
+        // member-a has a = ["b"], so "a" depends on "b" but not the other way around.
+        assert!(
+            feature_graph
+                .directly_depends_on(feature_a, feature_b)
+                .expect("valid feature IDs"),
+            "a directly depends on b"
+        );
+        assert!(
+            !feature_graph
+                .directly_depends_on(feature_b, feature_a)
+                .expect("valid feature IDs"),
+            "b doesn't directly depend on a"
+        );
+        assert!(
+            feature_graph
+                .depends_on(feature_a, feature_b)
+                .expect("valid feature IDs"),
+            "a depends on b"
+        );
+        assert!(
+            !feature_graph
+                .depends_on(feature_b, feature_a)
+                .expect("valid feature IDs"),
+            "b doesn't depend on a"
+        );
+    }
+
+    #[test]
+    fn feature_link() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let dev_only_id = package_id(METADATA_FEATURES1_DEV_ONLY);
+        let feature_a = FeatureId::new(&member_a_id, "a");
+        let feature_b = FeatureId::new(&member_a_id, "b");
+
+        // Links describe why one feature depends on another.
+        let link = feature_graph
+            .link(feature_a, feature_b)
+            .expect("valid feature IDs")
+            .expect("a directly depends on b");
+        assert_eq!(link.from(), feature_a, "link is from a");
+        assert_eq!(link.to(), feature_b, "link is to b");
+        assert_eq!(
+            link.kind(),
+            FeatureEdgeKind::FeatureDependency,
+            "a -> b kind"
+        );
+        assert_eq!(
+            feature_graph
+                .link(feature_a, FeatureId::base(&member_a_id))
+                .expect("valid feature IDs")
+                .map(|link| link.kind()),
+            Some(FeatureEdgeKind::FeatureToBase),
+            "a -> base kind"
+        );
+        assert!(
+            feature_graph
+                .link(feature_b, feature_a)
+                .expect("valid feature IDs")
+                .is_none(),
+            "no link from b to a"
+        );
+        let dev_link = feature_graph
+            .link(
+                FeatureId::base(&member_a_id),
+                FeatureId::new(&dev_only_id, "dev-feat"),
+            )
+            .expect("valid feature IDs")
+            .expect("member-a enables dev-feat");
+        match dev_link.link_type() {
+            FeatureLinkType::Dependency { normal, build, dev } => {
+                assert!(normal.is_never(), "dev-feat isn't enabled by normal deps");
+                assert!(build.is_never(), "dev-feat isn't enabled by build deps");
+                assert!(dev.is_always(), "dev-feat is always enabled by dev deps");
+            }
+            other => panic!("expected dependency link, found {:?}", other),
+        }
+        match feature_graph.link(feature_a, FeatureId::new(&member_a_id, "unknown")) {
+            Err(Error::UnknownFeatureId(..)) => {}
+            other => panic!("expected UnknownFeatureId, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn direct_dependents() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+
+        let mut dependents = feature_graph
+            .direct_dependents(FeatureId::base(&member_a_id), false)
+            .expect("valid feature ID");
+        dependents.sort();
+        let mut expected = vec![
+            FeatureId::base(&member_b_id),
+            FeatureId::new(&member_a_id, "a"),
+            FeatureId::new(&member_a_id, "a-default"),
+            FeatureId::new(&member_a_id, "b"),
+            FeatureId::new(&member_a_id, "default"),
+            FeatureId::new(&member_a_id, "opt-dep"),
+            FeatureId::new(&member_a_id, "shared"),
+        ];
+        expected.sort();
+        assert_eq!(dependents, expected, "direct dependents of member-a");
+        assert_eq!(
+            feature_graph
+                .direct_dependents(FeatureId::base(&member_a_id), true)
+                .expect("valid feature ID"),
+            vec![FeatureId::base(&member_b_id)],
+            "base links excluded"
+        );
+        assert_eq!(
+            feature_graph
+                .direct_dependents((&member_a_id, "b"), true)
+                .expect("valid feature ID"),
+            vec![FeatureId::new(&member_a_id, "a")],
+            "a enables b"
+        );
+        assert!(
+            feature_graph
+                .direct_dependents((&member_a_id, "nonexistent"), false)
+                .is_err(),
+            "unknown feature produces an error"
+        );
+    }
+
+    #[test]
+    fn feature_shortest_path() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+
+        // member-b enables member-a's default features, and member-a's default feature enables
+        // a-default.
+        assert_eq!(
+            feature_graph
+                .shortest_path(FeatureId::base(&member_b_id), (&member_a_id, "a-default"))
+                .expect("valid feature IDs"),
+            Some(vec![
+                FeatureId::base(&member_b_id),
+                FeatureId::new(&member_a_id, "default"),
+                FeatureId::new(&member_a_id, "a-default"),
+            ]),
+            "member-b -> member-a/default -> member-a/a-default"
+        );
+        // "a" depends on its base feature both directly and through "b".
+        assert_eq!(
+            feature_graph
+                .shortest_path((&member_a_id, "a"), FeatureId::base(&member_a_id))
+                .expect("valid feature IDs"),
+            Some(vec![
+                FeatureId::new(&member_a_id, "a"),
+                FeatureId::base(&member_a_id)
+            ]),
+            "direct link is shortest"
+        );
+        assert_eq!(
+            feature_graph
+                .shortest_path((&member_a_id, "a"), (&member_a_id, "a"))
+                .expect("valid feature IDs"),
+            Some(vec![FeatureId::new(&member_a_id, "a")]),
+            "path to self"
+        );
+        assert_eq!(
+            feature_graph
+                .shortest_path(FeatureId::base(&member_a_id), FeatureId::base(&member_b_id))
+                .expect("valid feature IDs"),
+            None,
+            "member-a doesn't depend on member-b"
+        );
+        match feature_graph.shortest_path((&member_a_id, "nonexistent"), (&member_a_id, "a")) {
+            Err(Error::UnknownFeatureId(package_id, feature)) => {
+                assert_eq!(package_id, member_a_id, "unknown feature's package");
+                assert_eq!(
+                    feature.as_deref(),
+                    Some("nonexistent"),
+                    "unknown feature name"
+                )
+            }
+            other => panic!("expected UnknownFeatureId, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn package_adding_features() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+
+        // Only opt-dep brings in a new package. "a" only enables "b" within member-a, and
+        // "shared" enables a feature in dep-c, which is always a dependency.
+        let adding: Vec<_> = feature_graph
+            .package_adding_features(&member_a_id)
+            .expect("member-a is known")
+            .collect();
+        assert_eq!(
+            adding,
+            vec![FeatureId::new(&member_a_id, "opt-dep")],
+            "only opt-dep adds packages"
+        );
+        assert_eq!(
+            feature_graph
+                .package_adding_features(&member_b_id)
+                .expect("member-b is known")
+                .count(),
+            0,
+            "member-b's features don't add packages"
+        );
+        assert!(
+            feature_graph
+                .package_adding_features(&package_id("bogus 0.1.0 (bogus)"))
+                .is_none(),
+            "unknown package ID"
+        );
+    }
+
+    #[test]
+    fn feature_type_totals() {
+        let package_graph = Fixture::metadata_features1().graph();
+        let feature_graph = package_graph.feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+
+        // member-a: default, a-default, shared, a, b, opt-dep. member-b: shared. dep-c: default,
+        // c-default, extra, build-feat. dev-only: dev-feat.
+        let totals = feature_graph.feature_type_totals();
+        assert_eq!(
+            totals.base_packages(),
+            package_graph.package_count(),
+            "one base feature per package"
+        );
+        assert_eq!(totals.named_features(), 11, "named feature count");
+        assert_eq!(totals.optional_deps(), 1, "optional dep count");
+        assert_eq!(
+            totals.total(),
+            feature_graph.feature_count(),
+            "totals add up to the feature count"
+        );
+
+        let optional_deps: Vec<_> = feature_graph
+            .features_by_type(FeatureType::OptionalDep)
+            .collect();
+        assert_eq!(
+            optional_deps,
+            vec![FeatureId::new(&member_a_id, "opt-dep")],
+            "opt-dep is the only optional dependency"
+        );
+        let base_features: Vec<_> = feature_graph
+            .features_by_type(FeatureType::BasePackage)
+            .collect();
+        assert_eq!(
+            base_features.len(),
+            totals.base_packages(),
+            "base feature count"
+        );
+        assert!(
+            base_features
+                .iter()
+                .all(|feature_id| feature_id.feature().is_none()),
+            "base features have no name"
+        );
+        assert_eq!(
+            feature_graph
+                .features_by_type(FeatureType::NamedFeature)
+                .count(),
+            totals.named_features(),
+            "named feature count"
+        );
+    }
+
+    #[test]
+    fn feature_set_minus_defaults() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+
+        // Subtracting defaults from an all-features build leaves the non-default features.
+        let feature_set = feature_graph.query_workspace(all_filter()).resolve();
+        let default_set = feature_graph.query_workspace(default_filter()).resolve();
+        let beyond_defaults = feature_set.minus_defaults();
+        assert_features_for_package(
+            &beyond_defaults,
+            &member_a_id,
+            &[Some("shared"), Some("a"), Some("b"), Some("opt-dep")],
+            "member-a beyond defaults",
+        );
+        // dep-c/build-feat is enabled by member-b's build-dependency, which is part of member-b's
+        // default closure.
+        assert_features_for_package(
+            &beyond_defaults,
+            &dep_c_id,
+            &[Some("extra")],
+            "dep-c beyond defaults",
+        );
+        assert_features_for_package(
+            &beyond_defaults,
+            &package_id(METADATA_FEATURES1_OPT_DEP),
+            &[],
+            "opt-dep beyond defaults (base is part of its default closure)",
+        );
+        assert!(
+            beyond_defaults
+                .intersection(&default_set.minus_defaults())
+                .symmetric_difference(&default_set.minus_defaults())
+                .is_empty(),
+            "features beyond defaults in a default build are a subset of all-features"
+        );
+        assert_features_for_package(
+            &default_set.minus_defaults(),
+            &member_a_id,
+            &[],
+            "member-a in a default build has nothing beyond defaults",
+        );
+    }
+
+    #[test]
+    fn feature_set_insert_remove() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+
+        // Build a set by hand that isn't closed under dependencies, then expand it.
+        let mut hand_built = feature_graph
+            .query_forward(iter::empty::<FeatureId>())
+            .expect("no feature IDs")
+            .resolve();
+        assert!(
+            hand_built
+                .insert((&member_a_id, "a"))
+                .expect("valid feature ID"),
+            "feature newly inserted"
+        );
+        assert!(
+            !hand_built
+                .insert((&member_a_id, "a"))
+                .expect("valid feature ID"),
+            "feature already present"
+        );
+        assert_eq!(hand_built.len(), 1, "insert doesn't add dependencies");
+        let closed = hand_built.closure(DependencyDirection::Forward);
+        let queried = feature_graph
+            .query_forward(iter::once((&member_a_id, "a")))
+            .expect("valid feature ID")
+            .resolve();
+        assert!(
+            closed.symmetric_difference(&queried).is_empty(),
+            "forward closure matches a forward query"
+        );
+        assert!(
+            closed
+                .contains((&member_a_id, "b"))
+                .expect("valid feature ID")
+                && closed
+                    .contains(FeatureId::base(&dep_c_id))
+                    .expect("valid feature ID"),
+            "closure pulls in dependencies"
+        );
+        assert!(
+            hand_built
+                .remove((&member_a_id, "a"))
+                .expect("valid feature ID"),
+            "feature was present"
+        );
+        assert!(hand_built.is_empty(), "feature removed");
+        assert!(
+            hand_built.insert((&member_a_id, "nonexistent")).is_err(),
+            "unknown feature produces an error"
+        );
+    }
+
+    #[test]
+    fn feature_impact() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+
+        // dep-c's default feature pulls in c-default and the base package.
+        let impact = feature_graph
+            .feature_impact((&dep_c_id, "default"))
+            .expect("valid feature ID");
+        assert_eq!(impact.reachable_features(), 3, "default, c-default, base");
+        assert_eq!(impact.reachable_packages(), 1, "only dep-c");
+        assert_eq!(
+            impact.added_edges(),
+            3,
+            "default -> c-default, default -> base, c-default -> base"
+        );
+
+        // member-a's "a" feature adds "a", "b" and three edges on top of member-a's base package.
+        let base_impact = feature_graph
+            .feature_impact(FeatureId::base(&member_a_id))
+            .expect("valid feature ID");
+        let a_impact = feature_graph
+            .feature_impact((&member_a_id, "a"))
+            .expect("valid feature ID");
+        assert_eq!(
+            a_impact.reachable_features(),
+            base_impact.reachable_features() + 2,
+            "a and b added"
+        );
+        assert_eq!(
+            a_impact.reachable_packages(),
+            base_impact.reachable_packages(),
+            "no packages added"
+        );
+        assert_eq!(
+            a_impact.added_edges(),
+            base_impact.added_edges() + 3,
+            "a -> b, a -> base, b -> base added"
+        );
+        assert!(
+            feature_graph
+                .feature_impact((&dep_c_id, "nonexistent"))
+                .is_err(),
+            "unknown feature produces an error"
+        );
+    }
+
+    #[test]
+    fn feature_all_links() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+
+        let all_links: Vec<_> = feature_graph
+            .all_links(DependencyDirection::Forward)
+            .collect();
+        assert_eq!(
+            all_links.len(),
+            feature_graph.link_count(),
+            "all_links returns every link"
+        );
+        for (from, to, kind) in &all_links {
+            match kind {
+                FeatureEdgeKind::FeatureToBase => {
+                    assert!(from.feature().is_some(), "{:?} is not a base feature", from);
+                    assert_eq!(
+                        *to,
+                        FeatureId::base(from.package_id()),
+                        "{:?} -> base",
+                        from
+                    );
+                }
+                FeatureEdgeKind::Dependency | FeatureEdgeKind::WeakFeatureDependency => assert_ne!(
+                    from.package_id(),
+                    to.package_id(),
+                    "{:?} -> {:?} crosses packages",
+                    from,
+                    to
+                ),
+                FeatureEdgeKind::FeatureDependency => {
+                    assert!(from.feature().is_some(), "{:?} is a named feature", from)
+                }
+            }
+        }
+        assert!(
+            all_links.contains(&(
+                FeatureId::new(&member_a_id, "a"),
+                FeatureId::new(&member_a_id, "b"),
+                FeatureEdgeKind::FeatureDependency
+            )),
+            "a -> b is a feature dependency"
+        );
+        assert!(
+            all_links.contains(&(
+                FeatureId::new(&member_a_id, "a"),
+                FeatureId::base(&member_a_id),
+                FeatureEdgeKind::FeatureToBase
+            )),
+            "a -> base is a feature-to-base link"
+        );
+        assert!(
+            all_links.contains(&(
+                FeatureId::base(&member_b_id),
+                FeatureId::base(&member_a_id),
+                FeatureEdgeKind::Dependency
+            )),
+            "member-b -> member-a is a dependency link"
+        );
+        let reverse_links: Vec<_> = feature_graph
+            .all_links(DependencyDirection::Reverse)
+            .map(|(from, to, kind)| (to, from, kind))
+            .collect();
+        assert_eq!(reverse_links, all_links, "reverse links are swapped");
+    }
+
+    #[test]
+    fn feature_count_for() {
+        let package_graph = Fixture::metadata_features1().graph();
+        let feature_graph = package_graph.feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+
+        // member-a has 5 named features, 1 optional dependency and a base feature.
+        assert_eq!(
+            feature_graph.feature_count_for(&member_a_id),
+            Some(7),
+            "member-a feature count"
+        );
+        let total: usize = package_graph
+            .packages()
+            .map(|package| {
+                feature_graph
+                    .feature_count_for(package.id())
+                    .expect("valid package ID")
+            })
+            .sum();
+        assert_eq!(
+            total,
+            feature_graph.feature_count(),
+            "per-package feature counts add up"
+        );
+        assert_eq!(
+            feature_graph.feature_count_for(&package_id(fixtures::METADATA1_TESTCRATE)),
+            None,
+            "unknown package ID"
+        );
+    }
+
+    #[test]
+    fn feature_resolve_no_dev() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let dev_only_id = package_id(METADATA_FEATURES1_DEV_ONLY);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+
+        let unified = feature_graph.query_workspace(all_filter()).resolve();
+        assert_eq!(
+            unified.contains((&dev_only_id, "dev-feat")),
+            Some(true),
+            "dev-dependency features are unified by default"
+        );
+
+        let no_dev = feature_graph
+            .query_workspace(all_filter())
+            .resolve_with(no_dev_resolver());
+        assert_eq!(
+            no_dev.contains(FeatureId::base(&dev_only_id)),
+            Some(false),
+            "dev-only dependency isn't followed"
+        );
+        assert_eq!(
+            no_dev.contains((&dep_c_id, "build-feat")),
+            Some(true),
+            "build dependency features are still unified"
+        );
+        assert!(
+            no_dev.difference(&unified).is_empty(),
+            "no-dev set is a subset of the unified set"
+        );
+    }
+
+    #[test]
+    fn features_by_edge_kind() {
+        let feature_graph = Fixture::metadata_features1().graph().feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+        let dev_only_id = package_id(METADATA_FEATURES1_DEV_ONLY);
+        let initials = [FeatureId::base(&member_a_id), FeatureId::base(&member_b_id)];
+
+        let normal = feature_graph
+            .features_by_edge_kind(initials.iter().copied(), DependencyKind::Normal)
+            .expect("valid feature IDs");
+        let build = feature_graph
+            .features_by_edge_kind(initials.iter().copied(), DependencyKind::Build)
+            .expect("valid feature IDs");
+        let dev = feature_graph
+            .features_by_edge_kind(initials.iter().copied(), DependencyKind::Development)
+            .expect("valid feature IDs");
+
+        assert_eq!(
+            normal.contains((&dep_c_id, "build-feat")),
+            Some(false),
+            "build-feat isn't enabled through normal dependencies"
+        );
+        assert_eq!(
+            build.contains((&dep_c_id, "build-feat")),
+            Some(true),
+            "build-feat is enabled through build dependencies"
+        );
+        assert_eq!(
+            dev.contains(FeatureId::base(&dev_only_id)),
+            Some(true),
+            "dev-only is reached through dev dependencies"
+        );
+        assert_eq!(
+            normal.contains(FeatureId::base(&dev_only_id)),
+            Some(false),
+            "dev-only isn't reached through normal dependencies"
+        );
+
+        let build_only = build.difference(&normal);
+        assert_eq!(
+            build_only.contains((&dep_c_id, "build-feat")),
+            Some(true),
+            "build-feat is build-only"
+        );
+    }
+
+    #[test]
+    fn feature_id_from_str() {
+        let graph = Fixture::metadata_features1().graph();
+        let feature_graph = graph.feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+
+        for (s, feature) in [
+            ("a", "a"),
+            ("member-a/shared", "shared"),
+            // Optional dependencies are features too.
+            ("opt-dep", "opt-dep"),
+        ] {
+            assert_eq!(
+                feature_graph
+                    .feature_id_from_str(&member_a_id, s)
+                    .expect("valid feature spec"),
+                FeatureId::new(&member_a_id, feature),
+                "feature spec '{}'",
+                s
+            );
+        }
+        assert_matches!(
+            feature_graph.feature_id_from_str(&member_a_id, "member-b/shared"),
+            Err(Error::FeatureSpecError(_)),
+            "package name doesn't match"
+        );
+        assert_matches!(
+            feature_graph.feature_id_from_str(&member_a_id, "member-a?/shared"),
+            Err(Error::FeatureSpecError(_)),
+            "weak dependency syntax"
+        );
+        assert_matches!(
+            feature_graph.feature_id_from_str(&member_a_id, "missing"),
+            Err(Error::UnknownFeatureId(package_id, Some(feature)))
+                if package_id == member_a_id && feature == "missing",
+            "unknown feature"
+        );
+        assert_matches!(
+            feature_graph.feature_id_from_str(&package_id("unknown 0.1.0"), "a"),
+            Err(Error::UnknownPackageId(_)),
+            "unknown package"
+        );
+
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+        assert_eq!(
+            feature_graph
+                .resolve_feature_spec("dep-c/extra")
+                .expect("valid feature spec"),
+            FeatureId::new(&dep_c_id, "extra"),
+            "package and feature"
+        );
+        assert_eq!(
+            feature_graph
+                .resolve_feature_spec("dep-c")
+                .expect("valid feature spec"),
+            FeatureId::base(&dep_c_id),
+            "bare package name resolves to the base feature"
+        );
+        assert_matches!(
+            feature_graph.resolve_feature_spec("missing/extra"),
+            Err(Error::FeatureSpecError(_)),
+            "unknown package name"
+        );
+        assert_matches!(
+            feature_graph.resolve_feature_spec("dep-c/missing"),
+            Err(Error::UnknownFeatureId(..)),
+            "unknown feature"
+        );
+
+        // lazy_static has two versions in this graph.
+        let dups_graph = Fixture::metadata_dups().graph();
+        assert_matches!(
+            dups_graph.feature_graph().resolve_feature_spec("lazy_static"),
+            Err(Error::FeatureSpecError(msg))
+                if msg.contains(fixtures::METADATA_DUPS_LAZY_STATIC_1)
+                    && msg.contains(fixtures::METADATA_DUPS_LAZY_STATIC_02),
+            "ambiguous package name"
+        );
+    }
+
+    #[test]
+    fn minimal_enabling_set() {
+        let graph = Fixture::metadata_features1().graph();
+        let feature_graph = graph.feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+        let opt_dep_id = package_id(METADATA_FEATURES1_OPT_DEP);
+        let member_a_roots = [member_a_id.clone()];
+        let member_b_roots = [member_b_id];
+
+        assert_eq!(
+            feature_graph
+                .minimal_enabling_set(FeatureId::new(&dep_c_id, "extra"), &member_a_roots)
+                .expect("valid IDs"),
+            vec![FeatureId::new(&member_a_id, "shared")],
+            "dep-c/extra is enabled through member-a/shared"
+        );
+        assert_eq!(
+            feature_graph
+                .minimal_enabling_set(FeatureId::new(&member_a_id, "b"), &member_a_roots)
+                .expect("valid IDs"),
+            vec![FeatureId::new(&member_a_id, "b")],
+            "a feature on a root is enabled directly, not through features depending on it"
+        );
+        assert_eq!(
+            feature_graph
+                .minimal_enabling_set(FeatureId::base(&opt_dep_id), &member_a_roots)
+                .expect("valid IDs"),
+            vec![FeatureId::new(&member_a_id, "opt-dep")],
+            "optional dependencies are features too"
+        );
+        assert_eq!(
+            feature_graph
+                .minimal_enabling_set(FeatureId::new(&member_a_id, "a-default"), &member_b_roots)
+                .expect("valid IDs"),
+            Vec::<FeatureId<'_>>::new(),
+            "member-b enables member-a's default features"
+        );
+        assert_matches!(
+            feature_graph
+                .minimal_enabling_set(FeatureId::new(&dep_c_id, "extra"), &member_b_roots),
+            Err(Error::UnreachableFeatureId(package_id, Some(feature)))
+                if package_id == dep_c_id && feature == "extra",
+            "member-b has no way to enable dep-c/extra"
+        );
+        assert_matches!(
+            feature_graph
+                .minimal_enabling_set(FeatureId::new(&dep_c_id, "missing"), &member_a_roots),
+            Err(Error::UnknownFeatureId(_, _)),
+            "unknown target"
+        );
+        assert_matches!(
+            feature_graph.minimal_enabling_set(
+                FeatureId::new(&dep_c_id, "extra"),
+                &[package_id("unknown 0.1.0")]
+            ),
+            Err(Error::UnknownPackageId(_)),
+            "unknown root"
+        );
+    }
+
+    #[test]
+    fn resolve_package_features() {
+        let graph = Fixture::metadata_features1().graph();
+        let feature_graph = graph.feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+
+        let feature_set = feature_graph
+            .resolve_package_features(&member_a_id, &["shared"], false)
+            .expect("valid package ID and features");
+        for (feature_id, expected, msg) in [
+            (
+                FeatureId::base(&member_a_id),
+                true,
+                "base is always enabled",
+            ),
+            (
+                FeatureId::new(&member_a_id, "shared"),
+                true,
+                "requested feature",
+            ),
+            (
+                FeatureId::new(&dep_c_id, "extra"),
+                true,
+                "enabled through member-a/shared",
+            ),
+            (
+                FeatureId::new(&member_a_id, "a-default"),
+                false,
+                "default features are disabled",
+            ),
+            (
+                FeatureId::new(&dep_c_id, "c-default"),
+                false,
+                "only enabled by member-b, which isn't unified in",
+            ),
+            (
+                FeatureId::base(&member_b_id),
+                false,
+                "member-b isn't a dependency",
+            ),
+        ] {
+            assert_eq!(feature_set.contains(feature_id), Some(expected), "{}", msg);
+        }
+
+        let feature_set = feature_graph
+            .resolve_package_features(&member_a_id, &[], true)
+            .expect("valid package ID");
+        assert_eq!(
+            feature_set.contains(FeatureId::new(&member_a_id, "a-default")),
+            Some(true),
+            "default features are enabled"
+        );
+        assert_eq!(
+            feature_set.contains(FeatureId::new(&member_a_id, "shared")),
+            Some(false),
+            "shared wasn't requested"
+        );
+
+        assert_matches!(
+            feature_graph.resolve_package_features(&member_a_id, &["missing"], true),
+            Err(Error::UnknownFeatureId(package_id, Some(feature)))
+                if package_id == member_a_id && feature == "missing",
+            "unknown feature"
+        );
+        assert_matches!(
+            feature_graph.resolve_package_features(&package_id("unknown 0.1.0"), &[], true),
+            Err(Error::UnknownPackageId(_)),
+            "unknown package"
+        );
+    }
+
+    #[test]
+    fn edges_for_link() {
+        let graph = Fixture::metadata_features1().graph();
+        let feature_graph = graph.feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+        let opt_dep_id = package_id(METADATA_FEATURES1_OPT_DEP);
+
+        let link_pairs = |from: &PackageId, to: &PackageId| {
+            let link = graph
+                .metadata(from)
+                .expect("valid package ID")
+                .direct_links()
+                .find(|link| link.to().id() == to)
+                .expect("link exists");
+            let mut pairs: Vec<_> = feature_graph
+                .edges_for_link(&link)
+                .into_iter()
+                .map(|feature_link| {
+                    assert_eq!(
+                        feature_link.kind(),
+                        FeatureEdgeKind::Dependency,
+                        "links for a dependency are dependency edges"
+                    );
+                    (feature_link.from(), feature_link.to())
+                })
+                .collect();
+            pairs.sort();
+            pairs
+        };
+
+        assert_eq!(
+            link_pairs(&member_b_id, &member_a_id),
+            vec![
+                (FeatureId::base(&member_b_id), FeatureId::base(&member_a_id)),
+                (
+                    FeatureId::base(&member_b_id),
+                    FeatureId::new(&member_a_id, "default")
+                ),
+            ],
+            "member-b depends on member-a with default features"
+        );
+        assert_eq!(
+            link_pairs(&member_a_id, &opt_dep_id),
+            vec![(
+                FeatureId::new(&member_a_id, "opt-dep"),
+                FeatureId::base(&opt_dep_id)
+            )],
+            "optional dependency edges come from the dependency's feature"
+        );
+    }
+
+    #[test]
+    fn resolve_with_features() {
+        let graph = Fixture::metadata_features1().graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+        let roots = [member_a_id.clone()];
+
+        let features = [(
+            member_a_id.clone(),
+            vec!["b".to_string(), "member-a/shared".to_string()],
+        )];
+        let feature_set = graph
+            .resolve_with_features(&roots, &features, false)
+            .expect("valid package IDs and features");
+        for (feature_id, expected, msg) in [
+            (FeatureId::new(&member_a_id, "b"), true, "feature name"),
+            (
+                FeatureId::new(&member_a_id, "shared"),
+                true,
+                "package name and feature name",
+            ),
+            (
+                FeatureId::new(&dep_c_id, "extra"),
+                true,
+                "features are followed transitively",
+            ),
+            (
+                FeatureId::new(&member_a_id, "a-default"),
+                false,
+                "default features are off",
+            ),
+            (
+                FeatureId::base(&member_b_id),
+                false,
+                "member-b isn't a root",
+            ),
+        ]
+        .iter()
+        {
+            assert_eq!(
+                feature_set.contains(*feature_id),
+                Some(*expected),
+                "{}",
+                msg
+            );
+        }
+
+        let feature_set = graph
+            .resolve_with_features(&roots, &[], true)
+            .expect("valid package IDs");
+        assert_eq!(
+            feature_set.contains((&member_a_id, "a-default")),
+            Some(true),
+            "default features are on"
+        );
+
+        let features = [
+            (
+                member_a_id.clone(),
+                vec!["missing".to_string(), "b".to_string()],
+            ),
+            (member_b_id.clone(), vec!["bogus".to_string()]),
+        ];
+        assert_matches!(
+            graph.resolve_with_features(&roots, &features, true),
+            Err(Error::UnknownFeatures(unknown))
+                if unknown == vec![
+                    (member_a_id.clone(), "missing".to_string()),
+                    (member_b_id.clone(), "bogus".to_string()),
+                ],
+            "all unknown features are collected"
+        );
+        assert_matches!(
+            graph.resolve_with_features(&[package_id("unknown 0.1.0")], &[], true),
+            Err(Error::UnknownPackageId(_)),
+            "unknown package"
+        );
+    }
+
+    #[test]
+    fn features_enabling_dep() {
+        let graph = Fixture::metadata_features1().graph();
+        let member_a = graph
+            .metadata(&package_id(METADATA_FEATURES1_MEMBER_A))
+            .expect("valid package ID");
+        assert_eq!(
+            member_a.features_enabling_dep("dep-c"),
+            vec!["shared"],
+            "dep/feature enables the dependency"
+        );
+        assert!(
+            member_a.features_enabling_dep("opt-dep").is_empty(),
+            "no named feature enables opt-dep"
+        );
+
+        let graph = Fixture::metadata_dep_syntax1().graph();
+        let ns_user = graph
+            .metadata(&package_id(METADATA_DEP_SYNTAX1_NS_USER))
+            .expect("valid package ID");
+        assert_eq!(
+            ns_user.features_enabling_dep("ns-dep"),
+            vec!["enable-dep"],
+            "dep: syntax enables the dependency"
+        );
+
+        let graph = Fixture::metadata_weak_features1().graph();
+        let weak_user = graph
+            .metadata(&package_id(METADATA_WEAK_FEATURES1_WEAK_USER))
+            .expect("valid package ID");
+        assert_eq!(
+            weak_user.features_enabling_dep("weak-dep"),
+            vec!["with-dep"],
+            "weak dependency features don't enable the dependency"
+        );
+    }
+
+    #[test]
+    fn feature_set_features_topo() {
+        for fixture in &[Fixture::metadata_features1(), Fixture::metadata_cycle2()] {
+            let feature_graph = fixture.graph().feature_graph();
+            let cycles = feature_graph.cycles();
+            let feature_set = feature_graph.resolve_all();
+
+            for direction in &[DependencyDirection::Forward, DependencyDirection::Reverse] {
+                let order: HashMap<_, _> = feature_set
+                    .features(*direction)
+                    .enumerate()
+                    .map(|(idx, feature)| (feature.feature_id(), idx))
+                    .collect();
+                assert_eq!(order.len(), feature_set.len(), "every feature is returned");
+
+                for (from, to, _) in feature_graph.all_links(*direction) {
+                    if cycles.is_cyclic(from, to).expect("valid feature IDs") {
+                        continue;
+                    }
+                    assert!(
+                        order[&from] < order[&to],
+                        "{:?}: {:?} is returned before {:?}",
+                        direction,
+                        from,
+                        to
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unreachable_features() {
+        let graph = Fixture::metadata_features1().graph();
+        let feature_graph = graph.feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let member_b_id = package_id(METADATA_FEATURES1_MEMBER_B);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+
+        let feature_set = feature_graph
+            .resolve_package_features(&member_a_id, &["a"], false)
+            .expect("valid package ID and features");
+        let unreachable = feature_graph.unreachable_features(&feature_set);
+
+        assert_eq!(
+            unreachable.len() + feature_set.len(),
+            feature_graph.feature_count(),
+            "every feature is either reachable or unreachable"
+        );
+        let mut sorted = unreachable.clone();
+        sorted.sort();
+        assert_eq!(unreachable, sorted, "unreachable features are sorted");
+        for (feature_id, expected, msg) in [
+            (FeatureId::new(&member_a_id, "a"), false, "in the set"),
+            (FeatureId::new(&member_a_id, "b"), false, "enabled by a"),
+            (
+                FeatureId::new(&member_a_id, "shared"),
+                true,
+                "nothing enables shared",
+            ),
+            (
+                FeatureId::new(&dep_c_id, "extra"),
+                true,
+                "only enabled through shared",
+            ),
+            (
+                FeatureId::base(&member_b_id),
+                true,
+                "member-b isn't depended on",
+            ),
+        ]
+        .iter()
+        {
+            assert_eq!(unreachable.contains(feature_id), *expected, "{}", msg);
+        }
+
+        let mut edited = feature_set.clone();
+        edited
+            .insert((&member_a_id, "shared"))
+            .expect("valid feature ID");
+        assert!(
+            !feature_graph
+                .unreachable_features(&edited)
+                .contains(&FeatureId::new(&dep_c_id, "extra")),
+            "features reachable from inserted ones are reachable"
+        );
+        assert!(
+            feature_graph
+                .unreachable_features(&feature_graph.resolve_all())
+                .is_empty(),
+            "every feature is reachable from the whole graph"
+        );
+    }
+
+    #[test]
+    fn base_feature() {
+        let graph = Fixture::metadata_features1().graph();
+        let feature_graph = graph.feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+
+        let base = feature_graph
+            .base_feature(&member_a_id)
+            .expect("member-a is in the graph");
+        assert_eq!(
+            base.feature_id(),
+            FeatureId::base(&member_a_id),
+            "base feature ID"
+        );
+        assert_eq!(
+            base.feature_type(),
+            FeatureType::BasePackage,
+            "base feature type"
+        );
+        assert!(
+            feature_graph
+                .base_feature(&package_id("unknown 0.1.0"))
+                .is_none(),
+            "unknown package"
+        );
+    }
+
+    #[test]
+    fn feature_id_buf() {
+        let graph = Fixture::metadata_features1().graph();
+        let feature_graph = graph.feature_graph();
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+
+        for feature_id in [
+            FeatureId::new(&member_a_id, "shared"),
+            FeatureId::base(&member_a_id),
+        ] {
+            let buf = FeatureIdBuf::from(feature_id);
+            assert_eq!(buf.package_id(), &member_a_id, "package ID");
+            assert_eq!(buf.feature(), feature_id.feature(), "feature name");
+            assert_eq!(buf.is_base(), feature_id.is_base(), "is base");
+            assert_eq!(
+                buf.to_feature_id(&feature_graph)
+                    .expect("feature ID is known"),
+                feature_id,
+                "round trip through FeatureIdBuf"
+            );
+        }
+
+        assert_matches!(
+            FeatureIdBuf::new(member_a_id.clone(), Some("missing".to_string()))
+                .to_feature_id(&feature_graph),
+            Err(Error::UnknownFeatureId(..)),
+            "unknown feature"
+        );
+        assert_matches!(
+            FeatureIdBuf::new(package_id("unknown 0.1.0"), None).to_feature_id(&feature_graph),
+            Err(Error::UnknownPackageId(_)),
+            "unknown package"
+        );
+    }
+
+    #[cfg(feature = "serde1")]
+    #[test]
+    fn feature_id_buf_serde() {
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let buf = FeatureIdBuf::new(member_a_id, Some("shared".to_string()));
+        let json = serde_json::to_string(&buf).expect("serialization succeeds");
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).expect("valid JSON"),
+            serde_json::json!({
+                "package_id": METADATA_FEATURES1_MEMBER_A,
+                "feature": "shared",
+            }),
+            "serialized form"
+        );
+        let deserialized: FeatureIdBuf =
+            serde_json::from_str(&json).expect("deserialization succeeds");
+        assert_eq!(deserialized, buf, "round trip through JSON");
+
+        for feature_type in [
+            FeatureType::NamedFeature,
+            FeatureType::OptionalDep,
+            FeatureType::BasePackage,
+        ] {
+            let json = serde_json::to_string(&feature_type).expect("serialization succeeds");
+            let deserialized: FeatureType =
+                serde_json::from_str(&json).expect("deserialization succeeds");
+            assert_eq!(
+                deserialized, feature_type,
+                "round trip for {:?}",
+                feature_type
+            );
+        }
+    }
+
+    #[test]
+    fn links_filtered() {
+        let graph = Fixture::metadata_features1().graph();
+        let package_set = graph.query_workspace().resolve();
+        let link_names = |kinds: Vec<DependencyKind>| {
+            let mut names: Vec<_> = package_set
+                .links_filtered(DependencyDirection::Forward, kinds)
+                .map(|link| (link.from().name(), link.to().name()))
+                .collect();
+            names.sort_unstable();
+            names
+        };
+
+        assert_eq!(
+            link_names(vec![DependencyKind::Development]),
+            vec![("member-a", "dev-only")],
+            "only dev links are returned"
+        );
+        assert_eq!(
+            link_names(vec![DependencyKind::Build]),
+            vec![("member-b", "dep-c")],
+            "only build links are returned"
+        );
+        assert_eq!(
+            link_names(vec![DependencyKind::Build, DependencyKind::Development]),
+            vec![("member-a", "dev-only"), ("member-b", "dep-c")],
+            "links for any selected kind are returned"
+        );
+        assert_eq!(
+            link_names(vec![]),
+            Vec::<(&str, &str)>::new(),
+            "no links are returned without kinds"
+        );
+        assert_eq!(
+            link_names(vec![
+                DependencyKind::Normal,
+                DependencyKind::Build,
+                DependencyKind::Development
+            ])
+            .len(),
+            package_set.links(DependencyDirection::Forward).count(),
+            "all links are returned with every kind"
+        );
+    }
+
+    #[cfg(feature = "serde1")]
+    #[test]
+    fn package_set_to_json() {
+        let graph = Fixture::metadata_features1().graph();
+        let package_set = graph.query_workspace().resolve();
+        let json = package_set
+            .to_json(DependencyDirection::Forward)
+            .expect("serialization succeeds");
+        let value: serde_json::Value = serde_json::from_str(&json).expect("valid JSON");
+
+        assert_eq!(value["schema_version"], 1, "schema version");
+        let packages = value["packages"].as_array().expect("packages is an array");
+        assert_eq!(
+            packages.len(),
+            package_set.len(),
+            "every package is exported"
+        );
+        let member_b = packages
+            .iter()
+            .find(|package| package["id"] == METADATA_FEATURES1_MEMBER_B)
+            .expect("member-b is exported");
+        assert_eq!(
+            member_b,
+            &serde_json::json!({
+                "id": METADATA_FEATURES1_MEMBER_B,
+                "name": "member-b",
+                "version": "0.1.0",
+                "in_workspace": true,
+            }),
+            "member-b details"
+        );
+
+        let links = value["links"].as_array().expect("links is an array");
+        assert_eq!(
+            links.len(),
+            package_set.links(DependencyDirection::Forward).count(),
+            "every link is exported"
+        );
+        let member_b_dep_c = links
+            .iter()
+            .find(|link| {
+                link["from"] == METADATA_FEATURES1_MEMBER_B
+                    && link["to"] == METADATA_FEATURES1_DEP_C
+            })
+            .expect("member-b -> dep-c is exported");
+        assert_eq!(
+            member_b_dep_c["kinds"],
+            serde_json::json!(["normal", "build"]),
+            "member-b -> dep-c kinds"
+        );
+    }
+
+    #[test]
+    fn summary_round_trip() {
+        let platforms: Vec<_> = [
+            "x86_64-unknown-linux-gnu",
+            "x86_64-pc-windows-msvc",
+            "x86_64-apple-darwin",
+        ]
+        .iter()
+        .map(|triple| Platform::new(*triple, TargetFeatures::Unknown).expect("known triple"))
+        .collect();
+
+        // Everything about a link that affects resolution, evaluated on each platform.
+        let link_summary = |link: PackageLink<'_>| {
+            let statuses: Vec<_> = [
+                DependencyKind::Normal,
+                DependencyKind::Build,
+                DependencyKind::Development,
+            ]
+            .iter()
+            .map(|kind| {
+                let req = link.req_for_kind(*kind);
+                let mut features: Vec<_> = req.features().map(|f| f.to_string()).collect();
+                features.sort_unstable();
+                features.dedup();
+                let evals: Vec<_> = platforms
+                    .iter()
+                    .map(|platform| {
+                        let feature_evals: Vec<_> = features
+                            .iter()
+                            .map(|feature| {
+                                let status = req.feature_status(feature);
+                                (status.required_on(platform), status.enabled_on(platform))
+                            })
+                            .collect();
+                        (
+                            req.status().required_on(platform),
+                            req.status().enabled_on(platform),
+                            req.default_features().required_on(platform),
+                            req.default_features().enabled_on(platform),
+                            feature_evals,
+                        )
+                    })
+                    .collect();
+                (features, evals)
+            })
+            .collect();
+            (
+                link.from().id().clone(),
+                link.to().id().clone(),
+                link.dep_name().to_string(),
+                link.resolved_name().to_string(),
+                statuses,
+            )
+        };
+
+        for fixture in &[
+            Fixture::metadata1(),
+            Fixture::metadata2(),
+            Fixture::metadata_dups(),
+            Fixture::metadata_cycle1(),
+            Fixture::metadata_cycle2(),
+            Fixture::metadata_targets1(),
+            Fixture::metadata_build_targets1(),
+            Fixture::metadata_proc_macro1(),
+            Fixture::metadata_custom_cfg1(),
+            Fixture::metadata_features1(),
+            Fixture::metadata_cycle_platform1(),
+            Fixture::metadata_minimal_versions1(),
+            Fixture::metadata_weak_features1(),
+            Fixture::metadata_dep_syntax1(),
+            Fixture::metadata_platform_features1(),
+            Fixture::metadata_no_default1(),
+            Fixture::metadata_rename1(),
+            Fixture::metadata_feature_cycle1(),
+            Fixture::metadata_version_change1_old(),
+            Fixture::metadata_version_change1_new(),
+        ] {
+            let graph = fixture.graph();
+            let summary = graph.to_summary().expect("summary serialized");
+            let rebuilt = PackageGraph::from_summary(&summary).expect("summary parsed");
+            rebuilt.verify().expect("rebuilt graph is valid");
+
+            assert!(graph.diff(&rebuilt).is_empty(), "packages match");
+            assert!(graph.edge_diff(&rebuilt).is_empty(), "links match");
+            assert_eq!(
+                graph.workspace().member_ids().collect::<Vec<_>>(),
+                rebuilt.workspace().member_ids().collect::<Vec<_>>(),
+                "workspace members match"
+            );
+            for package in graph.packages() {
+                let rebuilt_package = rebuilt.metadata(package.id()).expect("package exists");
+                let build_targets = |package: PackageMetadata<'_>| {
+                    package
+                        .build_targets()
+                        .map(|target| {
+                            format!(
+                                "{:?} {:?} {:?} {:?}",
+                                target.id(),
+                                target.kind(),
+                                target.required_features(),
+                                target.path()
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                };
+                assert_eq!(
+                    build_targets(package),
+                    build_targets(rebuilt_package),
+                    "{}: build targets match",
+                    package.id()
+                );
+
+                let mut links: Vec<_> = package.direct_links().map(link_summary).collect();
+                links.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+                let mut rebuilt_links: Vec<_> =
+                    rebuilt_package.direct_links().map(link_summary).collect();
+                rebuilt_links.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+                assert_eq!(links, rebuilt_links, "{}: link details match", package.id());
+            }
+
+            let feature_links = |graph: &PackageGraph| {
+                let mut links: Vec<_> = graph
+                    .feature_graph()
+                    .all_links(DependencyDirection::Forward)
+                    .map(|(from, to, kind)| {
+                        let from: (PackageId, Option<String>) = from.into();
+                        let to: (PackageId, Option<String>) = to.into();
+                        (from, to, format!("{:?}", kind))
+                    })
+                    .collect();
+                links.sort();
+                links
+            };
+            assert_eq!(
+                feature_links(graph),
+                feature_links(&rebuilt),
+                "feature graphs match"
+            );
+
+            assert_eq!(
+                rebuilt.to_summary().expect("summary serialized"),
+                summary,
+                "summaries are deterministic"
+            );
+        }
+
+        let summary = Fixture::metadata1()
+            .graph()
+            .to_summary()
+            .expect("summary serialized");
+        let future = summary.replacen(
+            &format!("\"summary_version\":{}", GRAPH_SUMMARY_VERSION),
+            &format!("\"summary_version\":{}", GRAPH_SUMMARY_VERSION + 1),
+            1,
+        );
+        assert_matches!(
+            PackageGraph::from_summary(&future),
+            Err(Error::PackageGraphConstructError(_)),
+            "unsupported summary version"
+        );
+    }
+
+    #[test]
+    fn summary_round_trip_modified() {
+        let round_trip = |graph: &PackageGraph| {
+            let summary = graph.to_summary().expect("summary serialized");
+            let rebuilt = PackageGraph::from_summary(&summary).expect("summary parsed");
+            rebuilt.verify().expect("rebuilt graph is valid");
+            assert!(graph.edge_diff(&rebuilt).is_empty(), "links match");
+            assert_eq!(
+                rebuilt.to_summary().expect("summary serialized"),
+                summary,
+                "summaries are deterministic"
+            );
+            rebuilt
+        };
+
+        // Removed links stay removed.
+        let mut graph = Fixture::metadata1().graph().clone();
+        let link_count = graph.link_count();
+        let (removed_from, removed_to) = {
+            let link = graph
+                .resolve_all()
+                .links(DependencyDirection::Forward)
+                .next()
+                .expect("at least one link");
+            (link.from().id().clone(), link.to().id().clone())
+        };
+        graph.retain_edges(|_, link| {
+            (link.from().id(), link.to().id()) != (&removed_from, &removed_to)
+        });
+        assert_eq!(graph.link_count(), link_count - 1, "one link removed");
+        let rebuilt = round_trip(&graph);
+        assert_eq!(
+            rebuilt.link_count(),
+            link_count - 1,
+            "link is still removed"
+        );
+        assert!(
+            rebuilt
+                .metadata(&removed_from)
+                .expect("valid package ID")
+                .direct_links()
+                .all(|link| *link.to().id() != removed_to),
+            "rebuilt graph doesn't have the removed link"
+        );
+
+        // Retargeted links keep their new targets.
+        let minimal = Fixture::metadata_minimal_versions1()
+            .graph()
+            .simulate_minimal_versions()
+            .expect("minimal versions graph is valid");
+        let rebuilt = round_trip(&minimal);
+        let consumer_id = package_id(fixtures::METADATA_MINIMAL_VERSIONS1_CONSUMER);
+        let targets: Vec<_> = rebuilt
+            .metadata(&consumer_id)
+            .expect("valid package ID")
+            .direct_links()
+            .map(|link| link.to().id().clone())
+            .collect();
+        assert_eq!(
+            targets,
+            vec![package_id(fixtures::METADATA_MINIMAL_VERSIONS1_LIB_OLD)],
+            "consumer depends on the lowest matching version"
+        );
+    }
+
+    #[test]
+    fn update_package() {
+        let member_a_id = package_id(METADATA_FEATURES1_MEMBER_A);
+        let dep_c_id = package_id(METADATA_FEATURES1_DEP_C);
+        let dev_only_id = package_id(METADATA_FEATURES1_DEV_ONLY);
+        let metadata_id = |repr: &str| cargo_metadata::PackageId {
+            repr: repr.to_string(),
+        };
+        let feature_links = |graph: &PackageGraph| {
+            let mut links: Vec<_> = graph
+                .feature_graph()
+                .all_links(DependencyDirection::Forward)
+                .map(|(from, to, kind)| {
+                    let from: (PackageId, Option<String>) = from.into();
+                    let to: (PackageId, Option<String>) = to.into();
+                    (from, to, format!("{:?}", kind))
+                })
+                .collect();
+            links.sort();
+            links
+        };
+
+        // Add a feature to member-a and remove its dev-only dependency.
+        let mut metadata: Metadata =
+            serde_json::from_str(fixtures::METADATA_FEATURES1).expect("valid metadata");
+        let package_idx = metadata
+            .packages
+            .iter()
+            .position(|package| package.name == "member-a")
+            .expect("member-a exists");
+        let package = &mut metadata.packages[package_idx];
+        package
+            .features
+            .insert("new-feat".to_string(), vec!["dep-c/extra".to_string()]);
+        package.dependencies.retain(|dep| dep.name != "dev-only");
+        let package = package.clone();
+        let resolve = metadata.resolve.as_mut().expect("resolve exists");
+        let node_idx = resolve
+            .nodes
+            .iter()
+            .position(|node| node.id == package.id)
+            .expect("member-a node exists");
+        let node = &mut resolve.nodes[node_idx];
+        node.deps.retain(|dep| dep.name != "dev_only");
+        node.dependencies.retain(|id| id.repr != dev_only_id.repr());
+        let node = node.clone();
+        let expected = PackageGraph::new(metadata).expect("valid graph");
+
+        let mut graph = Fixture::metadata_features1().graph().clone();
+        // Populate caches to ensure they're invalidated.
+        graph.feature_graph();
+        graph.cycles();
+
+        // Errors leave the graph unchanged.
+        let mut mismatched = node.clone();
+        mismatched.id = metadata_id(METADATA_FEATURES1_MEMBER_B);
+        assert_matches!(
+            graph.update_package(package.clone(), mismatched),
+            Err(Error::PackageGraphConstructError(_)),
+            "mismatched node ID"
+        );
+        let mut new_dep = node.clone();
+        new_dep.deps[0].pkg = metadata_id("new-dep 0.1.0 (registry+https://example.com)");
+        assert_matches!(
+            graph.update_package(package.clone(), new_dep),
+            Err(Error::PackageGraphConstructError(_)),
+            "dependency on a new package"
+        );
+        let mut unknown = package.clone();
+        unknown.id = metadata_id("unknown 0.1.0 (registry+https://example.com)");
+        assert_matches!(
+            graph.update_package(unknown, node.clone()),
+            Err(Error::UnknownPackageId(_)),
+            "unknown package ID"
+        );
+        assert!(
+            graph
+                .edge_diff(Fixture::metadata_features1().graph())
+                .is_empty(),
+            "graph unchanged after errors"
+        );
+
+        graph
+            .update_package(package, node)
+            .expect("update succeeded");
+        graph.verify().expect("updated graph is valid");
+        assert!(graph.diff(&expected).is_empty(), "packages match");
+        assert!(graph.edge_diff(&expected).is_empty(), "links match");
+        assert_eq!(
+            feature_links(&graph),
+            feature_links(&expected),
+            "feature graphs match"
+        );
+
+        let member_a = graph.metadata(&member_a_id).expect("valid package ID");
+        assert!(
+            !graph
+                .depends_on(&member_a_id, &dev_only_id)
+                .expect("valid package IDs"),
+            "dev-only dependency removed"
+        );
+        assert!(
+            member_a
+                .named_features()
+                .any(|feature| feature == "new-feat"),
+            "new feature added"
+        );
+        let new_feat_set = graph
+            .feature_graph()
+            .query_forward(iter::once((&member_a_id, "new-feat")))
+            .expect("valid feature ID")
+            .resolve();
+        assert_eq!(
+            new_feat_set.contains((&dep_c_id, "extra")),
+            Some(true),
+            "new feature enables dep-c/extra"
+        );
+    }
+
+    #[test]
+    fn stable_hash() {
+        // Graphs parsed independently produce the same fingerprint.
+        let graph = Fixture::metadata_cycle1().graph();
+        let other_graph =
+            PackageGraph::from_json(fixtures::METADATA_CYCLE1).expect("fixture is valid");
+        let package_set = graph.resolve_all();
+        assert_eq!(
+            package_set.stable_hash(),
+            other_graph.resolve_all().stable_hash(),
+            "independently parsed graphs"
+        );
+
+        // Package IDs are hashed in lexicographic order.
+        let mut reprs = vec![
+            fixtures::METADATA_CYCLE1_BASE,
+            fixtures::METADATA_CYCLE1_HELPER,
+        ];
+        reprs.sort_unstable();
+        let mut hasher = Sha256::new();
+        for repr in reprs {
+            hasher.update(repr.as_bytes());
+            hasher.update(b"\n");
+        }
+        assert_eq!(
+            package_set.stable_hash(),
+            <[u8; 32]>::from(hasher.finalize()),
+            "package IDs are sorted"
+        );
+
+        // Links aren't part of the fingerprint.
+        let mut other_graph = other_graph;
+        let base_id = package_id(fixtures::METADATA_CYCLE1_BASE);
+        other_graph.retain_edges(|_, link| *link.to().id() != base_id);
+        assert_eq!(
+            package_set.stable_hash(),
+            other_graph.resolve_all().stable_hash(),
+            "links are ignored"
+        );
+
+        // Different sets have different fingerprints.
+        let graph = Fixture::metadata_features1().graph();
+        let workspace = graph.query_workspace().resolve();
+        let member_a = graph
+            .query_forward(iter::once(&package_id(METADATA_FEATURES1_MEMBER_A)))
+            .expect("valid package ID")
+            .resolve();
+        assert_ne!(
+            workspace.stable_hash(),
+            member_a.stable_hash(),
+            "different sets"
+        );
+        assert_eq!(
+            member_a.stable_hash(),
+            member_a.union(&member_a).stable_hash(),
+            "same set"
+        );
+    }
+
+    proptest_suite!(metadata_features1);
+}
+
+mod large {
+    use super::*;
+    use crate::unit_tests::dep_helpers::GraphAssert;
+    use crate::unit_tests::fixtures::{
+        package_id, METADATA_LIBRA_ADMISSION_CONTROL_SERVICE, METADATA_LIBRA_EXECUTOR_UTILS,
+    };
+
+    #[test]
+    fn metadata_libra() {
+        let metadata_libra = Fixture::metadata_libra();
+        metadata_libra.verify();
+    }
+
+    proptest_suite!(metadata_libra);
+
+    #[test]
+    fn metadata_libra_f0091a4() {
+        let metadata = Fixture::metadata_libra_f0091a4();
+        metadata.verify();
+    }
+
+    proptest_suite!(metadata_libra_f0091a4);
+
+    #[test]
+    fn links_len_retain() {
+        // Retaining packages at random can leave only part of a dependency cycle in the set. Use a
+        // fixed linear congruential generator so that failures are reproducible.
+        for fixture in &[
+            Fixture::metadata_libra_f0091a4(),
+            Fixture::metadata_libra_9ffd93b(),
+        ] {
+            let graph = fixture.graph();
+            assert!(
+                graph.cycles().all_cycles().next().is_some(),
+                "fixture has cycles"
+            );
+            for seed in 0..64u64 {
+                let mut state = seed;
+                let mut package_set = graph.resolve_all();
+                package_set.retain(|_| {
+                    state = state
+                        .wrapping_mul(6_364_136_223_846_793_005)
+                        .wrapping_add(1_442_695_040_888_963_407);
+                    (state >> 33) % 4 != 0
+                });
+                for direction in &[DependencyDirection::Forward, DependencyDirection::Reverse] {
+                    let links = package_set.links(*direction);
+                    let len = links.len();
+                    assert_eq!(
+                        len,
+                        links.count(),
+                        "seed {}, {:?}: links len matches the number of links returned",
+                        seed,
+                        direction
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn metadata_libra_9ffd93b() {
+        let metadata = Fixture::metadata_libra_9ffd93b();
         metadata.verify();
 
         let graph = metadata.graph();
@@ -397,3 +5476,62 @@ impl PackageDotVisitor for NameVisitor {
         write!(f, "{}", link.dep_name())
     }
 }
+
+struct FeatureNameVisitor<'g>(&'g PackageGraph);
+
+impl<'g> FeatureDotVisitor for FeatureNameVisitor<'g> {
+    fn visit_feature(&self, feature: FeatureMetadata<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        let feature_id = feature.feature_id();
+        let package = self
+            .0
+            .metadata(feature_id.package_id())
+            .expect("valid package ID");
+        write!(
+            f,
+            "{}/{}",
+            package.name(),
+            feature_id.feature().unwrap_or("(base)")
+        )
+    }
+
+    fn visit_link(&self, kind: FeatureEdgeKind, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        write!(f, "{:?}", kind)
+    }
+}
+
+/// Replaces node indexes in dot output with their labels, and returns the nodes and edges in sorted
+/// order.
+///
+/// Each node is returned as its label, and each edge as `source -> target (label)`.
+fn normalize_dot(dot: &str) -> Vec<String> {
+    fn split_label(line: &str) -> (&str, &str) {
+        let mut parts = line.trim().splitn(2, " [label=\"");
+        let prefix = parts.next().expect("prefix exists");
+        let label = parts.next().expect("label exists").trim_end_matches("\"]");
+        (prefix, label)
+    }
+
+    let lines: Vec<_> = dot
+        .lines()
+        .filter(|line| line.contains("[label="))
+        .collect();
+    let labels: HashMap<_, _> = lines
+        .iter()
+        .map(|line| split_label(line))
+        .filter(|(prefix, _)| !prefix.contains(" -> "))
+        .collect();
+    let mut normalized: Vec<_> = lines
+        .iter()
+        .map(|line| {
+            let (prefix, label) = split_label(line);
+            let mut endpoints = prefix.splitn(2, " -> ");
+            let source = endpoints.next().expect("source exists");
+            match endpoints.next() {
+                Some(target) => format!("{} -> {} ({})", labels[source], labels[target], label),
+                None => label.to_string(),
+            }
+        })
+        .collect();
+    normalized.sort();
+    normalized
+}
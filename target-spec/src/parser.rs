@@ -30,9 +30,13 @@ use std::{error, fmt};
 /// assert_eq!(spec.eval(&i686_windows), None, "i686 Windows features are unknown");
 /// assert_eq!(spec.eval(&x86_64_mac), Some(false), "x86_64 MacOS matches no features");
 /// assert_eq!(spec.eval(&i686_linux), Some(true), "i686 Linux matches some features");
+///
+/// // Specifications display as the string they were parsed from.
+/// assert_eq!(spec.to_string(), "cfg(any(target_feature = \"sse2\", target_feature = \"sse\"))");
 /// ```
 #[derive(Clone, Debug)]
 pub struct TargetSpec {
+    input: Arc<str>,
     target: Target,
 }
 
@@ -52,11 +56,19 @@ impl FromStr for TargetSpec {
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
         Ok(Self {
+            input: input.into(),
             target: Target::parse(input)?,
         })
     }
 }
 
+/// Displays the string this specification was parsed from.
+impl fmt::Display for TargetSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.input)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) enum Target {
     TargetInfo(&'static TargetInfo),